@@ -0,0 +1,182 @@
+use mc_packets::chat::{convert_color_codes, strip_color_codes, sanitize, is_color_code};
+use std::time::{Duration, Instant};
+
+use crate::config::{Chat, Rank, Filter, FilterMode};
+
+/// A mute placed on a player, stopping them from chatting
+#[derive(Debug, Clone, Copy)]
+pub struct Mute {
+    /// When the mute runs out, None if it lasts until the player is unmuted
+    until: Option<Instant>,
+}
+
+impl Mute {
+    /// A mute lasting `duration`, or until the player is unmuted if it's None or too long to keep time for
+    pub fn new(duration: Option<Duration>) -> Self {
+        Self {
+            until: duration.and_then(|d| Instant::now().checked_add(d)),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.until.is_none_or(|until| Instant::now() < until)
+    }
+}
+
+/// Run a message through the word filter.
+/// Returns the message with filtered words censored, or None if the message should be blocked.
+///
+/// Colour codes don't split words, so `b&cad` is matched as `bad`. A word is matched both with its
+/// colour codes removed and with only the `&` and `%` removed, so however they are read they can't
+/// be used to sneak a word past the filter
+pub fn filter_message(filter: &Filter, message: &str) -> Option<String> {
+    if !filter.enabled || filter.words.is_empty() {
+        return Some(message.to_string());
+    }
+    let in_word = |c: char| c.is_alphanumeric() || c == '&' || c == '%';
+    let mut filtered = String::with_capacity(message.len());
+    let mut word_start: Option<usize> = None;
+    for (i, c) in message.char_indices().chain(std::iter::once((message.len(), ' '))) {
+        if i < message.len() && in_word(c) {
+            word_start.get_or_insert(i);
+            continue;
+        }
+        if let Some(start) = word_start.take() {
+            let word = &message[start..i];
+            let readings = [without_codes(word), without_code_markers(word)];
+            let matched = readings.iter()
+                .find(|read| !read.is_empty() && filter.words.iter().any(|w| w.eq_ignore_ascii_case(read)));
+            match matched {
+                Some(_) if filter.mode == FilterMode::Block => return None,
+                Some(read) => filtered.extend(std::iter::repeat_n('*', read.chars().count())),
+                None => filtered.push_str(word),
+            }
+        }
+        if i < message.len() {
+            filtered.push(c);
+        }
+    }
+    Some(filtered)
+}
+
+/// A word as shown, with colour codes and the stray `&` and `%` around them removed
+fn without_codes(word: &str) -> String {
+    let mut shown = String::with_capacity(word.len());
+    let mut chars = word.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '&' || c == '%' {
+            if chars.peek().is_some_and(|c| is_color_code(*c)) {
+                chars.next();
+            }
+        } else {
+            shown.push(c);
+        }
+    }
+    shown
+}
+
+/// A word with just the `&` and `%` removed, keeping the characters after them
+fn without_code_markers(word: &str) -> String {
+    word.chars().filter(|c| *c != '&' && *c != '%').collect()
+}
+
+/// Format a chat message typed by a player using the configured chat format
+pub fn format_message(chat: &Chat, rank: &Rank, username: &str, message: &str) -> String {
+    format_chat(&chat.format, rank, username, rank, message)
+}
+
+/// Fill in a chat template.
+/// `rank` and `username` belong to the player named in the message and `sender` is the rank of
+/// the player who typed it, which decides whether their colour codes are kept
+pub fn format_chat(template: &str, rank: &Rank, username: &str, sender: &Rank, message: &str) -> String {
+    let message = if sender.chat_colors {
+        convert_color_codes(message)
+    } else {
+        strip_color_codes(message)
+    };
+    sanitize(&fill_template(template, rank, username).replace("{message}", &message))
+}
+
+/// Format the message announcing a player joining the server
+pub fn format_join(chat: &Chat, rank: &Rank, username: &str) -> String {
+    sanitize(&fill_template(&chat.join_message, rank, username))
+}
+
+/// Format the message announcing a player leaving the server
+pub fn format_leave(chat: &Chat, rank: &Rank, username: &str) -> String {
+    sanitize(&fill_template(&chat.leave_message, rank, username))
+}
+
+fn fill_template(template: &str, rank: &Rank, username: &str) -> String {
+    template
+        .replace("{prefix}", &rank.prefix)
+        .replace("{color}", &rank.color)
+        .replace("{name}", username)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(mode: FilterMode) -> Filter {
+        Filter { enabled: true, mode, words: vec!["bad".to_string()] }
+    }
+
+    fn op() -> Rank {
+        Rank {
+            name: "op".to_string(),
+            prefix: "&c[Op] ".to_string(),
+            color: "&c".to_string(),
+            chat_colors: true,
+            op: true,
+        }
+    }
+
+    #[test]
+    fn chat_is_formatted_with_the_rank() {
+        let chat = Chat::default();
+        assert_eq!(format_message(&chat, &op(), "Sarah", "hi %ethere"), "&c[Op] &cSarah&f: hi &ethere");
+        // Guests can't use colour codes, a % is only a colour code for those who can
+        assert_eq!(format_message(&chat, &Rank::default(), "guest", "hi &ethere %a"), "&7guest&f: hi there %a");
+        assert_eq!(format_join(&chat, &op(), "Sarah"), "&eSarah joined the Server");
+        assert_eq!(format_leave(&chat, &Rank::default(), "guest"), "&eguest left the Server");
+    }
+
+    #[test]
+    fn templates_use_the_sender_rank_for_colours() {
+        // A guest messaging an op keeps the op's name colour but loses their own colour codes
+        let formatted = format_chat("{color}{name}: {message}", &op(), "Sarah", &Rank::default(), "&ahi");
+        assert_eq!(formatted, "&cSarah: hi");
+        // Broken codes and control characters never reach the client
+        let formatted = format_chat("{message}", &op(), "Sarah", &op(), "a & b\x07 &z c&");
+        assert_eq!(formatted, "a  b z c");
+    }
+
+    #[test]
+    fn filtered_words_are_censored_or_blocked() {
+        let censor = filter(FilterMode::Censor);
+        assert_eq!(filter_message(&censor, "a BAD word, bad!").as_deref(), Some("a *** word, ***!"));
+        assert_eq!(filter_message(&censor, "badger is fine").as_deref(), Some("badger is fine"));
+        assert_eq!(filter_message(&filter(FilterMode::Block), "not bad"), None);
+        let off = Filter { enabled: false, ..filter(FilterMode::Block) };
+        assert_eq!(filter_message(&off, "bad").as_deref(), Some("bad"));
+    }
+
+    #[test]
+    fn colour_codes_do_not_hide_filtered_words() {
+        let block = filter(FilterMode::Block);
+        for message in ["b&cad", "ba&&d", "%cbad", "ba&cd", "&b&a&d", "bad&"] {
+            assert_eq!(filter_message(&block, message), None, "{} got through", message);
+        }
+        assert_eq!(filter_message(&filter(FilterMode::Censor), "so b&cad").as_deref(), Some("so ***"));
+        assert_eq!(filter_message(&block, "&cbadger").as_deref(), Some("&cbadger"));
+    }
+
+    #[test]
+    fn mutes_run_out() {
+        assert!(Mute::new(None).is_active());
+        assert!(Mute::new(Some(Duration::from_secs(60))).is_active());
+        assert!(!Mute::new(Some(Duration::ZERO)).is_active());
+        assert!(Mute::new(Some(Duration::from_secs(u64::MAX))).is_active());
+    }
+}
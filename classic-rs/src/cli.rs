@@ -0,0 +1,36 @@
+use std::path::PathBuf;
+use structopt::StructOpt;
+use log::LevelFilter;
+
+use crate::config::{Overrides, CONFIG_PATH};
+
+/// Command line arguments, each can also be set with the environment variable named in `--help`.
+/// Anything given here wins over the environment, which wins over the config file
+#[derive(StructOpt)]
+#[structopt(name = "classic-rs", about = "A Minecraft Classic server")]
+pub struct Args {
+    /// Path of the config file
+    #[structopt(short, long, env = "CLASSIC_RS_CONFIG", default_value = CONFIG_PATH, parse(from_os_str))]
+    pub config: PathBuf,
+    /// Directory worlds are loaded from and saved to, overrides map.directory
+    #[structopt(short, long)]
+    pub world_dir: Option<String>,
+    /// Port to listen on, overrides server.port
+    #[structopt(short, long)]
+    pub port: Option<u16>,
+    /// One of off, error, warn, info, debug or trace
+    #[structopt(short, long, env = "CLASSIC_RS_LOG_LEVEL", default_value = "info")]
+    pub log_level: LevelFilter,
+    /// Write a config file with the default values and any overrides to the config path, then exit
+    #[structopt(long)]
+    pub generate_config: bool,
+}
+
+impl Args {
+    pub fn overrides(&self) -> Overrides {
+        Overrides {
+            port: self.port,
+            world_dir: self.world_dir.clone(),
+        }
+    }
+}
@@ -1,52 +1,78 @@
 use tokio::net::TcpStream;
 use tokio::io::{AsyncWriteExt, AsyncReadExt, Error, ErrorKind};
-use tokio::sync::{Mutex, MutexGuard};
-use flume::{Receiver, Sender};
-use log::{info, debug, error, warn};
+use tokio::sync::Mutex;
+use flume::Sender;
+use log::{info, debug};
+use std::convert::TryFrom;
 use std::sync::{Arc};
-use std::ops::{Deref, DerefMut};
-use flate2::Compression;
-use flate2::write::GzEncoder;
-use std::io::Write;
-use std::cell::{RefMut};
 use md5::{Md5, Digest};
 
 
 
 
 use mc_packets::Packet;
+use mc_packets::chat::{wrap_message, sanitize, strip_color_codes, LINE_LENGTH};
 use mc_packets::classic::{ClientBound, ServerBound};
-use mc_worlds::classic::{ClassicWorld, Block};
+use mc_packets::level::{LevelStreamer, CHUNK_SIZE};
+use mc_worlds::classic::{ClassicWorld, Block, Spawn};
+use mc_worlds::level::LevelStream;
 
-use crate::config::Config;
-use md5::digest::Update;
-
-const STRING_LENGTH: usize = 64;
+use crate::config::{Config, Chat, Rank};
+use crate::chat::{format_message, format_join, format_leave, filter_message, Mute};
+use crate::command::Command;
 
 pub struct Client {
     pub(crate) username: String,
+    #[allow(dead_code)]
     ip: String,
     id: u8,
     // The rank of the user, 0x64 for op, 0x00 for normal
     user_type: u8,
+    rank: Rank,
     logged_in: bool,
     socket: TcpStream,
-    n_tx: Sender<(u8, Vec<ClientBound>)>,
+    /// Packets for the other players, with the sender's id and the world they were sent in
+    n_tx: Sender<(u8, String, Vec<ClientBound>)>,
     current_x: i16,
     current_y: i16,
     current_z: i16,
     current_yaw: u8,
     current_pitch: u8,
+    #[allow(dead_code)]
     flying: bool,
+    /// Commands typed by the player waiting to be handled by the server
+    commands: Vec<Command>,
+    /// The last player to send this player a private message
+    pub(crate) reply_to: Option<String>,
+    pub(crate) mute: Option<Mute>,
+    /// Players whose chat is hidden from this player
+    pub(crate) ignored: Vec<String>,
+    /// Name of the world the player is in
+    pub(crate) world: String,
+    /// The level being sent to the player, they can't be sent anything else from the world until it is done
+    download: Option<Download>,
+}
+
+/// A level being sent a few chunks each tick, so nobody waits on a player downloading a large world
+struct Download {
+    stream: LevelStream,
+    /// How many bytes of the gzipped level have been sent
+    sent: usize,
+    /// Where the player is put once it has downloaded
+    spawn: Spawn,
 }
 
+/// Bytes of level sent to a player each tick, 256 chunks of 1024
+const DOWNLOAD_PER_TICK: usize = 256 * 1024;
+
 impl Client {
-    pub async fn new(id: u8, sock: TcpStream, n_tx: Sender<(u8, Vec<ClientBound>)>) -> Self {
+    pub async fn new(id: u8, sock: TcpStream, n_tx: Sender<(u8, String, Vec<ClientBound>)>) -> Self {
         Self {
             username: "".to_string(),
             ip: sock.peer_addr().expect("Failed to get peers address").ip().to_string(),
             id,
             user_type: 0x00,
+            rank: Rank::default(),
             logged_in: false,
             socket: sock,
             n_tx,
@@ -56,6 +82,12 @@ impl Client {
             current_yaw: 0,
             current_pitch: 0,
             flying: false,
+            commands: vec![],
+            reply_to: None,
+            mute: None,
+            ignored: vec![],
+            world: String::new(),
+            download: None,
         }
     }
 
@@ -63,6 +95,34 @@ impl Client {
         self.id
     }
 
+    pub fn get_rank(&self) -> &Rank {
+        &self.rank
+    }
+
+    /// Change the rank of the player, letting their client know if they gained or lost op
+    pub async fn set_rank(&mut self, rank: Rank) {
+        let user_type = if rank.op { 0x64 } else { 0x00 };
+        self.rank = rank;
+        if user_type != self.user_type {
+            self.user_type = user_type;
+            self.write_packets(vec![ClientBound::UpdateUserType(user_type)]).await;
+        }
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.mute.is_some_and(|m| m.is_active())
+    }
+
+    pub fn is_ignoring(&self, username: &str) -> bool {
+        self.ignored.iter().any(|i| i.eq_ignore_ascii_case(username))
+    }
+
+    /// Take the commands typed since this was last called
+    pub fn take_commands(&mut self) -> Vec<Command> {
+        std::mem::take(&mut self.commands)
+    }
+
+    #[allow(dead_code)]
     pub fn get_ip(&self) -> String {
         self.ip.clone()
     }
@@ -79,20 +139,20 @@ impl Client {
         )
     }
 
-    pub fn despawn_self(&self) -> [ClientBound; 2] {
+    pub fn despawn_self(&self, chat: &Chat) -> Vec<ClientBound> {
         info!("{} has left the server", self.username);
-        [
-            ClientBound::DespawnPlayer(self.id),
-            ClientBound::Message(255, {
-                let msg = format!("{} left the Server", self.username);
-                encode_string(&msg)
-            })
-        ]
+        let mut packets = vec![ClientBound::DespawnPlayer(self.id)];
+        packets.append(&mut self.build_message(255, &format_leave(chat, &self.rank, &self.username)));
+        packets
     }
 
-    pub async fn handle_connect(&mut self, salt: &str, world: Arc<Mutex<ClassicWorld>>) -> Result<(), tokio::io::Error> {
+    pub async fn handle_connect(&mut self, salt: &str, world: Arc<Mutex<ClassicWorld>>, config: &Config,
+                                players_online: usize) -> Result<(), tokio::io::Error> {
         let mut receive_buffer = [0x00; 1460];
-        self.socket.read(&mut receive_buffer).await?;
+        let read = self.socket.read(&mut receive_buffer).await?;
+        if read == 0 {
+            return Err(Error::from(ErrorKind::ConnectionAborted));
+        }
 
         if !self.logged_in && receive_buffer[0] != 0x00 {
             self.socket.write_u8(0x01).await.unwrap();
@@ -106,23 +166,23 @@ impl Client {
 
         let mut buffer_handled: usize = 0;
         while buffer_handled < receive_buffer[..].len() &&
-            buffer_handled + ServerBound::size(*&receive_buffer[buffer_handled]) < receive_buffer.len() {
-            if self.logged_in && *&receive_buffer[buffer_handled] == 0x00  {
+            buffer_handled + ServerBound::size(receive_buffer[buffer_handled]) < receive_buffer.len() {
+            if self.logged_in && receive_buffer[buffer_handled] == 0x00  {
                 break;
             }
             if receive_buffer[buffer_handled..buffer_handled +
-                    ServerBound::size(*&receive_buffer[buffer_handled])].len() == 0 {
+                    ServerBound::size(receive_buffer[buffer_handled])].is_empty() {
                 break;
             }
             serverbound_packets.push(
                 Packet::from(
                     &receive_buffer[buffer_handled..buffer_handled +
-                            ServerBound::size(*&receive_buffer[buffer_handled])])
+                            ServerBound::size(receive_buffer[buffer_handled])])
             );
             if !self.logged_in {
-                self.logged_in = *&receive_buffer[buffer_handled] == 0x00;
+                self.logged_in = receive_buffer[buffer_handled] == 0x00;
             }
-            buffer_handled += ServerBound::size(*&receive_buffer[buffer_handled]);
+            buffer_handled += ServerBound::size(receive_buffer[buffer_handled]);
         }
 
         for packet in serverbound_packets {
@@ -130,10 +190,13 @@ impl Client {
                 ServerBound::PlayerIdentification(protocol, username,
                                                   key, _) => {
                     if protocol == 0x07 {
+                        if players_online >= config.server.max_players as usize {
+                            self.disconnect("The server is full").await?;
+                            break;
+                        }
                         let mut world_lock = world.lock().await;
                         self.username = username;
-                        if self.username == "" {break}
-                        let config = Config::get();
+                        if self.username.is_empty() {break}
                         if config.server.online_mode {
                             let mut hasher = Md5::new();
                             let mut concat: Vec<u8> = vec![];
@@ -148,43 +211,19 @@ impl Client {
                                 break;
                             }
                         }
+                        self.rank = config.permissions.rank_for(&self.username);
+                        self.user_type = if self.rank.op { 0x64 } else { 0x00 };
                         self.write_packets(vec![ClientBound::ServerIdentification(
                             7,
                             encode_string(&config.server.name),
                             encode_string(&config.server.motd),
-                            0x00,
-                        ), ClientBound::LevelInitialize]).await;
-                        self.send_blocks(world_lock.deref_mut()).await.expect("Failed to send blocks");
-                        let size = world_lock.get_size();
-                        self.write_packets(vec![
-                            ClientBound::LevelFinalize(size[0], size[1], size[2]),
-                            ClientBound::PlayerTeleport(
-                                255,
-                                (((size[0] / 2) * 32) + 16) as i16,
-                                (((size[1] / 2) + 2) * 32) as i16,
-                                (((size[2] / 2) * 32) + 16) as i16,
-                                0,
-                                0,
-                            )
-                        ]).await;
-                        self.current_x = (((size[0] / 2) * 32) + 16) as i16;
-                        self.current_y = (((size[1] / 2) + 2) * 32) as i16;
-                        self.current_z = (((size[2] / 2) * 32) + 16) as i16;
-                        echo_packets.push(ClientBound::SpawnPlayer(
-                            255,
-                            self.get_username_as_bytes(),
-                            self.current_x,
-                            self.current_y,
-                            self.current_z,
-                            0,
-                            0,
-                        ));
+                            self.user_type,
+                        )]).await;
+                        let (stream, spawn) = (world_lock.get_gzipped(), world_lock.get_spawn());
+                        self.start_download(stream, spawn).await;
                         info!("{} joined the Server", self.username);
-                        clientbound_packets.push(ClientBound::Message(255, {
-                                let msg = format!("{} joined the Server", self.username);
-                                encode_string(&msg)
-                            }
-                        ));
+                        clientbound_packets.append(&mut self.build_message(
+                            255, &format_join(&config.chat, &self.rank, &self.username)));
                         clientbound_packets.push(ClientBound::SpawnPlayer(
                             self.id,
                             self.get_username_as_bytes(),
@@ -198,14 +237,11 @@ impl Client {
                     }
                 }
                 ServerBound::PositionAndOrientation(
-                    p_id, x, y, z, yaw, pitch) => {
+                    _p_id, x, y, z, yaw, pitch) => {
                     let mut pos_changed: bool = false;
                     let mut ori_changed: bool = false;
                     let y = y + 3;
-                    let moved_teleport_distance =
-                        -15 < (self.current_x - x) && (self.current_x - x) > 15 ||
-                        -15 < (self.current_y - y) && (self.current_y - y) > 15 ||
-                        -15 < (self.current_z - z) && (self.current_z - z) > 15;
+                    let relative = relative_move([self.current_x, self.current_y, self.current_z], [x, y, z]);
                     if x != self.current_x || y != self.current_y || z != self.current_z {
                         pos_changed = true;
                         // debug!("{:#}:{:#}:{:#}", self.current_x - x, self.current_y - y, self.current_z - z);
@@ -214,13 +250,13 @@ impl Client {
                         ori_changed = true;
                     }
                     if pos_changed && ori_changed {
-                        if  moved_teleport_distance {
+                        if let Some([dx, dy, dz]) = relative {
                             clientbound_packets.push(
                                 ClientBound::PositionAndOrientationUpdate(
                                     self.id,
-                                    -(self.current_x - x) as i8,
-                                    -(self.current_y - y) as i8,
-                                    -(self.current_z - z) as i8,
+                                    dx,
+                                    dy,
+                                    dz,
                                     yaw,
                                     pitch
                                 )
@@ -238,13 +274,13 @@ impl Client {
                             )
                         }
                     } else if pos_changed {
-                        if  moved_teleport_distance {
+                        if let Some([dx, dy, dz]) = relative {
                             clientbound_packets.push(
                                 ClientBound::PositionUpdate(
                                     self.id,
-                                    -(self.current_x - x) as i8,
-                                    -(self.current_y - y) as i8,
-                                    -(self.current_z - z) as i8,
+                                    dx,
+                                    dy,
+                                    dz,
                                 )
                             );
                         } else {
@@ -268,7 +304,7 @@ impl Client {
                                 pitch
                             )
                         );
-                    } else {}
+                    }
 
                     self.current_x = x;
                     self.current_y = y;
@@ -278,10 +314,10 @@ impl Client {
                 }
                 ServerBound::SetBlock(x, y, z, mode, block) => {
                     let mut world_lock = world.lock().await;
-                    let block = Block::from(block).clone();
+                    let block = Block::from(block);
                     if mode == 0x00 {
                         if block != Block::Bedrock {
-                            world_lock.set_block(x as usize, y as usize, z as usize, Block::Air.into());
+                            world_lock.set_block(x as usize, y as usize, z as usize, Block::Air);
                             echo_packets.push(
                                 ClientBound::SetBlock(x, y, z, Block::Air.into())
                             );
@@ -295,7 +331,7 @@ impl Client {
                         }
                     } else {
                         let (n_x, n_y, n_z, block) =
-                            world_lock.set_block(x as usize, y as usize, z as usize, block.into());
+                            world_lock.set_block(x as usize, y as usize, z as usize, block);
                         echo_packets.push(
                             ClientBound::SetBlock(x, y, z, Block::Air.into())
                         );
@@ -309,19 +345,19 @@ impl Client {
                     drop(world_lock);
                 }
                 ServerBound::Message(_, message) => {
-                    let mut f_msg: String = "".to_owned();
-                    let split: Vec<&str> = message.split_ascii_whitespace().collect();
-                    for i in 0..split.len() {
-                        if i != 0 {
-                            f_msg.push_str(&format!(" {}", split[i]));
-                        }else{
-                            f_msg.push_str(&format!("{}", split[i]));
-                        }
+                    if let Some(command) = Command::parse(&message) {
+                        self.commands.push(command);
+                    } else if self.is_muted() {
+                        echo_packets.append(&mut self.build_message(255, "&cYou are muted"));
+                    } else if let Some(message) = filter_message(&config.filter, &message) {
+                        let formatted = format_message(&config.chat, &self.rank, &self.username, &message);
+                        info!("{}", strip_color_codes(&formatted));
+                        let msg = self.build_message(self.id, &formatted);
+                        echo_packets.append(&mut msg.clone());
+                        clientbound_packets.append(&mut msg.clone());
+                    } else {
+                        echo_packets.append(&mut self.build_message(255, "&cYour message was blocked by the chat filter"));
                     }
-                    let mut msg = self.build_message(
-                        self.username.as_str(), self.id, f_msg.as_str()).await;
-                    echo_packets.append(&mut msg.clone());
-                    clientbound_packets.append(&mut msg.clone());
                 }
                 ServerBound::UnknownPacket => {
                     let msg = String::from_utf8(receive_buffer.to_vec())
@@ -333,11 +369,106 @@ impl Client {
         echo_packets.push(ClientBound::Ping);
 
         self.write_packets(echo_packets).await;
-        self.n_tx.send((self.id, clientbound_packets)).expect("Failed to send Packets");
+        self.n_tx.send((self.id, self.world.clone(), clientbound_packets)).expect("Failed to send Packets");
 
         Ok(())
     }
 
+    /// Start sending a level to the player, it is sent by [Client::continue_download] over the following ticks.
+    /// Used when joining, when moving between worlds and when too much of a world changed at once to send
+    /// block by block. The player is put at `spawn` for everyone else, and for themselves once it has downloaded
+    pub async fn start_download(&mut self, stream: LevelStream, spawn: Spawn) {
+        self.set_position(spawn);
+        self.write_packets(vec![ClientBound::LevelInitialize]).await;
+        self.download = Some(Download { stream, sent: 0, spawn });
+    }
+
+    pub fn is_downloading(&self) -> bool {
+        self.download.is_some()
+    }
+
+    /// Send the next part of the level once it has been built. When all of it is sent, put the player where
+    /// the download was started for and replay the blocks changed since the level was taken, returning true
+    pub async fn continue_download(&mut self, world: &Mutex<ClassicWorld>) -> Result<bool, Error> {
+        let (level, sent) = match &self.download {
+            Some(download) => match download.stream.get() {
+                Some(level) => (level, download.sent),
+                None => return Ok(false),
+            },
+            None => return Ok(true),
+        };
+        let mut streamer = LevelStreamer::resume(&level, sent);
+        let chunks: Vec<ClientBound> = streamer.by_ref().take(DOWNLOAD_PER_TICK / CHUNK_SIZE).collect();
+        self.try_write_packets(&chunks).await?;
+        if let Some(download) = &mut self.download {
+            download.sent = streamer.sent();
+        }
+        if !streamer.is_done() {
+            return Ok(false);
+        }
+
+        let mut world = world.lock().await;
+        let download = self.download.take().expect("Download was checked above");
+        let (revision, spawn) = (download.stream.revision(), download.spawn);
+        let changes = match world.changes_since(revision) {
+            Some(changes) => changes,
+            None => {
+                // Too much changed while this was downloading, start again with the latest level
+                let stream = world.get_gzipped();
+                self.start_download(stream, spawn).await;
+                return Ok(false);
+            }
+        };
+        let size = world.get_size();
+        drop(world);
+
+        self.set_position(spawn);
+        let mut packets = vec![
+            ClientBound::LevelFinalize(size[0], size[1], size[2]),
+            ClientBound::PlayerTeleport(255, spawn.x, spawn.y, spawn.z, spawn.h, spawn.p),
+            ClientBound::SpawnPlayer(
+                255,
+                self.get_username_as_bytes(),
+                spawn.x,
+                spawn.y,
+                spawn.z,
+                spawn.h,
+                spawn.p,
+            ),
+        ];
+        packets.extend(changes.into_iter()
+            .map(|(x, y, z, block)| ClientBound::SetBlock(x as i16, y as i16, z as i16, block.into())));
+        self.try_write_packets(&packets).await?;
+        Ok(true)
+    }
+
+    /// The players current position and look
+    pub fn get_position(&self) -> Spawn {
+        Spawn {
+            x: self.current_x,
+            y: self.current_y,
+            z: self.current_z,
+            h: self.current_yaw,
+            p: self.current_pitch,
+        }
+    }
+
+    fn set_position(&mut self, position: Spawn) {
+        self.current_x = position.x;
+        self.current_y = position.y;
+        self.current_z = position.z;
+        self.current_yaw = position.h;
+        self.current_pitch = position.p;
+    }
+
+    /// Move the player, returning the packet that shows the move to everyone else
+    pub async fn teleport(&mut self, position: Spawn) -> ClientBound {
+        self.set_position(position);
+        let Spawn { x, y, z, h, p } = position;
+        self.write_packets(vec![ClientBound::PlayerTeleport(255, x, y, z, h, p)]).await;
+        ClientBound::PlayerTeleport(self.id, x, y, z, h, p)
+    }
+
     pub async fn disconnect(&mut self, msg: &str) -> Result<(), tokio::io::Error> {
         self.write_packets(vec![ClientBound::DisconnectPlayer({
             encode_string(msg)
@@ -350,134 +481,51 @@ impl Client {
         self.write_packets(messages).await;
     }
 
-    pub async fn build_message(&self, sender_name: &str, sender_id: u8, msg: &str) -> Vec<ClientBound> {
-        let mut messages: Vec<ClientBound> = vec![];
-        let split_msg = msg.split_ascii_whitespace();
-        if split_msg.clone().count() > 1 {
-            let mut split_indx = 0;
-            let mut char_count: usize = 0;
-            let msg_full: String = split_msg.map(|word|
-                if char_count + word.len() < (64 - (sender_name.len() + 4)) {
-                    split_indx += 1;
-                    char_count += word.len() + 1;
-                    format!("{} ", word)
-                } else { "".to_string() }
-            ).collect();
-            let first_msg = format!("<{}>: {}", sender_name, msg_full);
-            info!("{}", first_msg);
-            messages.push(ClientBound::Message(sender_id, encode_string(&first_msg)));
-            while char_count < msg.len() {
-                let mut fresh_char_count = 0;
-                let split_msg = msg.split_at(char_count).1.split_ascii_whitespace();
-                let msg_full: String = split_msg.map(|word|
-                    if fresh_char_count + word.len() < 64 {
-                        fresh_char_count += word.len() + 1;
-                        char_count += word.len() + 1;
-                        format!("{} ", word)
-                    } else { "".to_string() }
-                ).collect();
-                let msg = format!("{}", msg_full);
-                info!("<{}>: {}", sender_name, msg_full);
-                messages.push(ClientBound::Message(sender_id, encode_string(&msg)));
-            }
-        }else {
-            let first_msg = format!("<{}>: {}", sender_name, msg);
-            info!("{}", first_msg);
-            messages.push(ClientBound::Message(sender_id, encode_string(&first_msg)));
-        }
-        messages
+    /// Split a formatted message into the Message packets needed to show it
+    pub fn build_message(&self, sender_id: u8, msg: &str) -> Vec<ClientBound> {
+        wrap_message(&sanitize(msg)).iter()
+            .map(|line| ClientBound::Message(sender_id, encode_string(line)))
+            .collect()
     }
 
-    async fn send_blocks(&mut self, world: &mut ClassicWorld) -> Result<(), tokio::io::Error> {
-        let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
-        encoder.write(&(world.get_blocks().len() as u32).to_be_bytes()).unwrap();
-        encoder.write_all(world.get_blocks().as_slice()).unwrap();
-        let compressed = encoder.finish().expect("Failed to compress data");
-        // let compressed = world.get_gzipped();
-        let mut sent: usize = 0;
-        let mut left: usize = compressed.len();
-
-        while left > 0 && sent < compressed.len() {
-            let mut send_buffer: [u8; 1024] = [0x00; 1024];
-            for i in 0..1024 {
-                if sent+i < compressed.len() {
-                    send_buffer[i] = compressed[sent + i];
+    pub async fn write_packets(&mut self, packets: Vec<ClientBound>) {
+        match self.try_write_packets(&packets).await {
+            Ok(_) => {}
+            Err(e) => {
+                if e.kind() == ErrorKind::ConnectionAborted || e.kind() == ErrorKind::ConnectionReset ||
+                    e.kind() == ErrorKind::BrokenPipe {} else {
+                    panic!("Error: {:?}", e);
                 }
             }
-            if left > 1024 {
-                let world_packet = ClientBound::LevelDataChunk(1024, send_buffer,
-                                                               ((sent / compressed.len()) * 100) as u8);
-                sent += 1024;
-                left -= 1024;
-                self.write_packets(vec![world_packet]).await;
-            }else {
-                // let world_packet = ClientBound::LevelDataChunk(left as i16, send_buffer, progress);
-                sent += left;
-                left = 0;
-                let world_packet = ClientBound::LevelDataChunk(1024, send_buffer,
-                                                               ((sent / compressed.len()) * 100) as u8);
-                self.write_packets(vec![world_packet]).await;
-            }
         }
-
-        Ok(())
     }
 
-    pub async fn write_packets(&mut self, packets: Vec<ClientBound>) {
+    /// Write packets to the socket, batched into buffers the size of a TCP segment
+    async fn try_write_packets(&mut self, packets: &[ClientBound]) -> Result<(), Error> {
         let mut packet_buffer: [u8; 1460] = [0u8; 1460];
         let mut buffer_filled: usize = 0;
-        for p in 0..packets.len() {
-            let c_packet = Packet::into(&packets[p]);
+        for packet in packets {
+            let c_packet = Packet::into(packet);
             let c_slice = c_packet.as_slice();
             if c_slice.len() > (1460 - buffer_filled) {
-                match self.socket.write_all(&packet_buffer[0..buffer_filled]).await {
-                    Ok(_) => {}
-                    Err(e) => {
-                        if e.kind() == ErrorKind::ConnectionAborted || e.kind() == ErrorKind::ConnectionReset {
-                            break;
-                        } else {
-                            panic!("Error: {:?}", e);
-                        }
-                    }
-                }
+                self.socket.write_all(&packet_buffer[0..buffer_filled]).await?;
                 buffer_filled = 0;
                 packet_buffer = [0u8; 1460];
             }
-            let mut s_index = 0;
-            for i in buffer_filled..1460 {
-                if s_index < c_slice.len() {
-                    packet_buffer[i] = c_slice[s_index];
-                    s_index += 1;
-                } else { break; }
-            }
+            packet_buffer[buffer_filled..buffer_filled + c_slice.len()].copy_from_slice(c_slice);
             buffer_filled += c_slice.len();
         }
-
-        match self.socket.write_all(&packet_buffer[0..buffer_filled]).await {
-            Ok(_) => {}
-            Err(e) => {
-                if e.kind() == ErrorKind::ConnectionAborted || e.kind() == ErrorKind::ConnectionReset
-                    {} else {
-                    panic!("Error: {:?}", e);
-                }
-            }
-        }
+        self.socket.write_all(&packet_buffer[0..buffer_filled]).await
     }
 
-    fn get_username_as_bytes(&self) -> [u8; 64] {
-        let mut username: [u8; 64] = [0x20; 64];
-        for i in 0..username.len() {
-            if i < self.username.len() {
-                username[i] = self.username.as_bytes()[i];
-            }
-        }
-        username
+    fn get_username_as_bytes(&self) -> [u8; LINE_LENGTH] {
+        encode_string(&self.username)
     }
 
 }
 
-fn encode_string(string: &str) -> [u8; 64] {
-    let mut string_bytes: [u8; 64] = [0x20; 64];
+fn encode_string(string: &str) -> [u8; LINE_LENGTH] {
+    let mut string_bytes: [u8; LINE_LENGTH] = [0x20; LINE_LENGTH];
     for i in 0..string.len() {
         if i >= string_bytes.len() {
             break;
@@ -485,4 +533,28 @@ fn encode_string(string: &str) -> [u8; 64] {
         string_bytes[i] = string.as_bytes()[i];
     }
     string_bytes
-}
\ No newline at end of file
+}
+
+/// How far a player moved from `from` to `to` on each axis, if every axis fits in the byte
+/// relative updates send it in. Anything further has to be sent as a teleport
+fn relative_move(from: [i16; 3], to: [i16; 3]) -> Option<[i8; 3]> {
+    let delta = |axis: usize| i8::try_from(i32::from(to[axis]) - i32::from(from[axis])).ok();
+    Some([delta(0)?, delta(1)?, delta(2)?])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_that_fit_in_a_byte_are_relative() {
+        assert_eq!(relative_move([100, 64, 100], [100, 64, 100]), Some([0, 0, 0]));
+        assert_eq!(relative_move([100, 64, 100], [227, 64, 100]), Some([127, 0, 0]));
+        assert_eq!(relative_move([100, 64, 100], [100, -64, 100]), Some([0, -128, 0]));
+        assert_eq!(relative_move([100, 64, 100], [85, 79, 115]), Some([-15, 15, 15]));
+        // One byte too far either way is a teleport
+        assert_eq!(relative_move([100, 64, 100], [228, 64, 100]), None);
+        assert_eq!(relative_move([100, 64, 100], [100, 64, -29]), None);
+        assert_eq!(relative_move([i16::MIN; 3], [i16::MAX; 3]), None);
+    }
+}
@@ -0,0 +1,193 @@
+use std::time::Duration;
+
+/// Commands typed into chat by players, starting with `/`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Send a private message to a player
+    Msg(String, String),
+    /// Reply to the last player to send a private message
+    Reply(String),
+    /// Emote to everyone
+    Me(String),
+    /// Broadcast a message from the server, ops only
+    Say(String),
+    /// Stop a player from chatting, optionally for a limited time, ops only
+    Mute(String, Option<Duration>),
+    /// Let a muted player chat again, ops only
+    Unmute(String),
+    /// Hide or show chat from a player
+    Ignore(String),
+    /// Reload server.toml, ops only
+    Reload,
+    /// Move to another world
+    Goto(String),
+    /// List the loaded worlds
+    Worlds,
+    /// Go back to the spawn of the current world
+    Spawn,
+    /// Move the spawn of the current world to where the player is standing, ops only
+    SetSpawn,
+    /// Back up a loaded world, the current world if none is given, ops only
+    Backup(Option<String>),
+    /// Draw a loaded world to images, the current world if none is given, ops only
+    Render(Option<String>),
+    /// Turn physics on or off in the current world, toggling it if neither is given, ops only
+    Physics(Option<bool>),
+    /// Light all TNT within reach, or the TNT at a block which only ops can do, in worlds with TNT on
+    Detonate(Option<[u16; 3]>),
+    /// A known command used with the wrong arguments, holds the correct usage
+    Usage(&'static str),
+    /// A command that wasn't recognised
+    Unknown(String),
+}
+
+impl Command {
+    /// Parse a chat message into a command, returns None if the message is not a command
+    pub fn parse(message: &str) -> Option<Self> {
+        let message = message.trim().strip_prefix('/')?;
+        let (name, args) = split_word(message);
+        let command = match name.to_ascii_lowercase().as_str() {
+            "msg" | "tell" | "w" => match split_word(args) {
+                (target, text) if !target.is_empty() && !text.is_empty() =>
+                    Command::Msg(target.to_string(), text.to_string()),
+                _ => Command::Usage("/msg <player> <message>"),
+            },
+            "r" | "reply" if !args.is_empty() => Command::Reply(args.to_string()),
+            "r" | "reply" => Command::Usage("/r <message>"),
+            "me" if !args.is_empty() => Command::Me(args.to_string()),
+            "me" => Command::Usage("/me <action>"),
+            "say" if !args.is_empty() => Command::Say(args.to_string()),
+            "say" => Command::Usage("/say <message>"),
+            "mute" => match split_word(args) {
+                (target, "") if !target.is_empty() => Command::Mute(target.to_string(), None),
+                (target, duration) if !target.is_empty() => match parse_duration(duration) {
+                    Some(duration) => Command::Mute(target.to_string(), Some(duration)),
+                    None => Command::Usage("/mute <player> [duration, e.g. 30s, 10m, 2h, 1d]"),
+                },
+                _ => Command::Usage("/mute <player> [duration, e.g. 30s, 10m, 2h, 1d]"),
+            },
+            "unmute" if !args.is_empty() => Command::Unmute(args.to_string()),
+            "unmute" => Command::Usage("/unmute <player>"),
+            "ignore" if !args.is_empty() => Command::Ignore(args.to_string()),
+            "ignore" => Command::Usage("/ignore <player>"),
+            "reload" => Command::Reload,
+            "goto" | "g" if !args.is_empty() => Command::Goto(args.to_string()),
+            "goto" | "g" => Command::Usage("/goto <world>"),
+            "worlds" => Command::Worlds,
+            "spawn" => Command::Spawn,
+            "setspawn" => Command::SetSpawn,
+            "backup" => Command::Backup(Some(args.to_string()).filter(|w| !w.is_empty())),
+            "render" => Command::Render(Some(args.to_string()).filter(|w| !w.is_empty())),
+            "physics" => match args.to_ascii_lowercase().as_str() {
+                "" => Command::Physics(None),
+                "on" => Command::Physics(Some(true)),
+                "off" => Command::Physics(Some(false)),
+                _ => Command::Usage("/physics [on|off]"),
+            },
+            "detonate" => match args.split_whitespace().map(str::parse).collect::<Result<Vec<u16>, _>>() {
+                Ok(position) if position.is_empty() => Command::Detonate(None),
+                Ok(position) if position.len() == 3 => Command::Detonate(Some([position[0], position[1], position[2]])),
+                _ => Command::Usage("/detonate [x y z]"),
+            },
+            _ => Command::Unknown(name.to_string()),
+        };
+        Some(command)
+    }
+}
+
+/// Parse a duration such as `30s`, `10m`, `2h` or `1d`, a number on its own is taken as minutes
+fn parse_duration(text: &str) -> Option<Duration> {
+    let (number, unit) = match text.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => text.split_at(i),
+        None => (text, "m"),
+    };
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        _ => return None,
+    };
+    number.parse::<u64>().ok()?.checked_mul(seconds).map(Duration::from_secs)
+}
+
+/// Split the first word off of some text, returning it and the trimmed remainder
+fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim();
+    match text.find(char::is_whitespace) {
+        Some(i) => (&text[..i], text[i..].trim()),
+        None => (text, ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chat_commands_are_parsed() {
+        assert_eq!(Command::parse("hello"), None);
+        assert_eq!(Command::parse("/msg Sarah  hi there "), Some(Command::Msg("Sarah".to_string(), "hi there".to_string())));
+        assert_eq!(Command::parse("/TELL Sarah hi"), Some(Command::Msg("Sarah".to_string(), "hi".to_string())));
+        assert_eq!(Command::parse("/r back"), Some(Command::Reply("back".to_string())));
+        assert_eq!(Command::parse("/me waves"), Some(Command::Me("waves".to_string())));
+        assert_eq!(Command::parse("/say restarting"), Some(Command::Say("restarting".to_string())));
+        assert_eq!(Command::parse("/nope"), Some(Command::Unknown("nope".to_string())));
+    }
+
+    #[test]
+    fn chat_commands_without_arguments_show_usage() {
+        assert_eq!(Command::parse("/msg"), Some(Command::Usage("/msg <player> <message>")));
+        assert_eq!(Command::parse("/msg Sarah"), Some(Command::Usage("/msg <player> <message>")));
+        assert_eq!(Command::parse("/r  "), Some(Command::Usage("/r <message>")));
+        assert_eq!(Command::parse("/me"), Some(Command::Usage("/me <action>")));
+        assert_eq!(Command::parse("/say"), Some(Command::Usage("/say <message>")));
+    }
+
+    #[test]
+    fn durations_take_a_unit() {
+        assert_eq!(parse_duration("30s"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_duration("10m"), Some(Duration::from_secs(600)));
+        assert_eq!(parse_duration("2h"), Some(Duration::from_secs(2 * 60 * 60)));
+        assert_eq!(parse_duration("1d"), Some(Duration::from_secs(24 * 60 * 60)));
+        assert_eq!(parse_duration("5"), Some(Duration::from_secs(300)));
+        for bad in ["", "m", "10x", "10mm", "-5m", "1.5h", "99999999999999999999s", "999999999999999999d"] {
+            assert_eq!(parse_duration(bad), None, "{} was accepted", bad);
+        }
+    }
+
+    #[test]
+    fn moderation_commands_are_parsed() {
+        let usage = Command::Usage("/mute <player> [duration, e.g. 30s, 10m, 2h, 1d]");
+        assert_eq!(Command::parse("/mute Sarah"), Some(Command::Mute("Sarah".to_string(), None)));
+        assert_eq!(Command::parse("/mute Sarah 30s"),
+                   Some(Command::Mute("Sarah".to_string(), Some(Duration::from_secs(30)))));
+        assert_eq!(Command::parse("/mute Sarah forever"), Some(usage.clone()));
+        assert_eq!(Command::parse("/mute"), Some(usage));
+        assert_eq!(Command::parse("/unmute Sarah"), Some(Command::Unmute("Sarah".to_string())));
+        assert_eq!(Command::parse("/unmute"), Some(Command::Usage("/unmute <player>")));
+        assert_eq!(Command::parse("/ignore Sarah"), Some(Command::Ignore("Sarah".to_string())));
+        assert_eq!(Command::parse("/ignore"), Some(Command::Usage("/ignore <player>")));
+    }
+
+    #[test]
+    fn world_commands_are_parsed() {
+        assert_eq!(Command::parse("/spawn"), Some(Command::Spawn));
+        assert_eq!(Command::parse("/SetSpawn"), Some(Command::SetSpawn));
+        assert_eq!(Command::parse("/g lobby"), Some(Command::Goto("lobby".to_string())));
+        assert_eq!(Command::parse("/goto"), Some(Command::Usage("/goto <world>")));
+        assert_eq!(Command::parse("/backup"), Some(Command::Backup(None)));
+        assert_eq!(Command::parse("/render lobby"), Some(Command::Render(Some("lobby".to_string()))));
+        assert_eq!(Command::parse("/physics OFF"), Some(Command::Physics(Some(false))));
+        assert_eq!(Command::parse("/physics maybe"), Some(Command::Usage("/physics [on|off]")));
+    }
+
+    #[test]
+    fn detonate_takes_a_block_inside_a_map() {
+        assert_eq!(Command::parse("/detonate"), Some(Command::Detonate(None)));
+        assert_eq!(Command::parse("/detonate 1 2 3"), Some(Command::Detonate(Some([1, 2, 3]))));
+        assert_eq!(Command::parse("/detonate 18446744073709551615 0 0"), Some(Command::Usage("/detonate [x y z]")));
+        assert_eq!(Command::parse("/detonate -1 0 0"), Some(Command::Usage("/detonate [x y z]")));
+        assert_eq!(Command::parse("/detonate 1 2"), Some(Command::Usage("/detonate [x y z]")));
+    }
+}
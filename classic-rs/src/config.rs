@@ -1,11 +1,27 @@
 use std::fs::read_to_string;
 use std::fs::write;
-use std::str::FromStr;
-use std::path::PathBuf;
-use std::io::Error;
+use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::net::IpAddr;
+use std::{env, fmt};
 use serde_derive::{Deserialize, Serialize};
-use toml::{to_string, from_str};
-use log::{debug, warn};
+use toml::{to_string, from_str, Value};
+use log::warn;
+use mc_packets::chat::{is_color_code, LINE_LENGTH};
+use mc_worlds::classic::DEFAULT_DIRECTORY;
+use mc_worlds::classic::explosion::MAX_RADIUS;
+use mc_worlds::generation::{self, GeneratorSettings};
+
+/// Player ids are a signed byte in the protocol and -1 is used for the player themselves
+const MAX_PLAYERS: u16 = 127;
+/// Player positions are sent as fixed point shorts, 1024 blocks is the furthest they can reach
+const MAX_MAP_SIZE: usize = 1024;
+/// Prefix of the environment variables that override config values,
+/// `CLASSIC_RS_SERVER_PORT` overrides `port` in the `[server]` table.
+/// Tables can also be set whole as inline TOML, which is the only way to set ones whose keys aren't known
+/// ahead, `CLASSIC_RS_WORLDS='{ arena = { x_width = 64 } }'` adds the world `arena` to `[worlds]`
+pub const ENV_PREFIX: &str = "CLASSIC_RS";
 
 #[derive(Serialize, Deserialize)]
 pub struct MineOnline {
@@ -88,59 +104,698 @@ impl Default for Server {
 
 #[derive(Serialize, Deserialize)]
 pub struct Map {
+    /// Name of the world players join in
     pub name: String,
+    /// Directory worlds are loaded from and saved to
+    #[serde(default = "default_map_directory")]
+    pub directory: String,
+    /// Worlds loaded at startup and kept loaded even when empty
+    #[serde(default)]
+    pub preload: Vec<String>,
+    /// Minutes a world can sit empty before it is saved and unloaded, 0 keeps worlds loaded forever
+    #[serde(default = "default_unload_after")]
+    pub unload_after: u64,
     pub creator_username: String,
+    /// Size of the default world, and of any world without its own size in `[worlds]`
     pub x_width: usize,
     pub y_height: usize,
     pub z_depth: usize,
+    /// How new worlds are generated, unless they have their own generator in `[worlds]`
+    #[serde(default)]
+    pub generator: GeneratorSettings,
+    /// Whether fluids flow, sand falls and plants grow, unless a world sets its own `physics` in `[worlds]`
+    #[serde(default)]
+    pub physics: bool,
+    /// Whether TNT explodes, unless a world sets its own `tnt` in `[worlds]`
+    #[serde(default)]
+    pub tnt: bool,
+}
+
+fn default_map_directory() -> String {
+    DEFAULT_DIRECTORY.to_string()
+}
+
+fn default_unload_after() -> u64 {
+    5
 }
 
 impl Default for Map {
     fn default() -> Self {
         Self {
             name: "world".to_string(),
+            directory: default_map_directory(),
+            preload: vec![],
+            unload_after: default_unload_after(),
             creator_username: "".to_string(),
             x_width: 32,
             y_height: 32,
             z_depth: 32,
+            generator: GeneratorSettings::default(),
+            physics: false,
+            tnt: false,
+        }
+    }
+}
+
+/// Settings for one world, keyed by the world name in `[worlds]`.
+/// Sizes, the generator, physics and TNT left out are taken from `[map]`
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct World {
+    pub x_width: Option<usize>,
+    pub y_height: Option<usize>,
+    pub z_depth: Option<usize>,
+    pub generator: Option<GeneratorSettings>,
+    pub physics: Option<bool>,
+    pub tnt: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Rank {
+    pub name: String,
+    /// Shown before the name of every player with this rank
+    pub prefix: String,
+    /// Colour code used for the names of players with this rank
+    pub color: String,
+    /// Whether players with this rank can use `&` and `%` colour codes in chat
+    pub chat_colors: bool,
+    pub op: bool,
+}
+
+impl Default for Rank {
+    fn default() -> Self {
+        Self {
+            name: "guest".to_string(),
+            prefix: "".to_string(),
+            color: "&7".to_string(),
+            chat_colors: false,
+            op: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Permissions {
+    pub default_rank: String,
+    pub ranks: Vec<Rank>,
+    /// Usernames mapped to the name of their rank
+    pub players: HashMap<String, String>,
+}
+
+impl Permissions {
+    /// Get the rank of a player, falling back to the default rank
+    pub fn rank_for(&self, username: &str) -> Rank {
+        let rank_name = self.players.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(username))
+            .map_or(&self.default_rank, |(_, rank)| rank);
+        self.ranks.iter()
+            .find(|r| r.name.eq_ignore_ascii_case(rank_name))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+impl Default for Permissions {
+    fn default() -> Self {
+        Self {
+            default_rank: "guest".to_string(),
+            ranks: vec![
+                Rank::default(),
+                Rank {
+                    name: "builder".to_string(),
+                    prefix: "".to_string(),
+                    color: "&a".to_string(),
+                    chat_colors: true,
+                    op: false,
+                },
+                Rank {
+                    name: "op".to_string(),
+                    prefix: "&c[Op] ".to_string(),
+                    color: "&c".to_string(),
+                    chat_colors: true,
+                    op: true,
+                },
+            ],
+            players: HashMap::new(),
         }
     }
 }
 
+/// Templates for messages sent to chat.
+/// `{prefix}`, `{color}` and `{name}` are replaced with the players rank prefix, rank colour and name,
+/// `{message}` with what they typed
 #[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Chat {
+    pub format: String,
+    pub join_message: String,
+    pub leave_message: String,
+    /// Used for /me
+    pub emote_format: String,
+    /// Used for /say
+    pub say_format: String,
+    /// Shown to the sender of a private message, `{name}` is the recipient
+    pub private_to_format: String,
+    /// Shown to the recipient of a private message, `{name}` is the sender
+    pub private_from_format: String,
+}
+
+impl Default for Chat {
+    fn default() -> Self {
+        Self {
+            format: "{prefix}{color}{name}&f: {message}".to_string(),
+            join_message: "&e{name} joined the Server".to_string(),
+            leave_message: "&e{name} left the Server".to_string(),
+            emote_format: "* {color}{name}&f {message}".to_string(),
+            say_format: "&d[Server] {message}".to_string(),
+            private_to_format: "&7[to {name}] {message}".to_string(),
+            private_from_format: "&7[from {name}] {message}".to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterMode {
+    /// Filtered words are replaced with `*`
+    Censor,
+    /// Messages containing filtered words are not sent
+    Block,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Filter {
+    pub enabled: bool,
+    pub mode: FilterMode,
+    /// Words to filter, matched as whole words ignoring case
+    pub words: Vec<String>,
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mode: FilterMode::Censor,
+            words: vec![],
+        }
+    }
+}
+
+/// Timestamped copies of every loaded world, kept on top of the regular saves
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Backup {
+    /// Directory backups are written to, each world gets a directory of its own inside it
+    pub directory: String,
+    /// Minutes between backups of every loaded world, 0 only backs up with /backup
+    pub interval: u64,
+    /// Backups kept for each world, the oldest are deleted past this, 0 keeps any number
+    pub keep: usize,
+    /// Hours a backup is kept for, 0 keeps them until there are more than `keep`
+    pub max_age: u64,
+}
+
+impl Default for Backup {
+    fn default() -> Self {
+        Self {
+            directory: "./backups".to_string(),
+            interval: 60,
+            keep: 24,
+            max_age: 24 * 7,
+        }
+    }
+}
+
+/// Where /render draws worlds to
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Render {
+    /// Directory images are written to, `<world>.png` from above and `<world>-isometric.png`
+    pub directory: String,
+    /// How many times bigger than 4 pixels wide each block is in isometric images
+    pub scale: usize,
+}
+
+impl Default for Render {
+    fn default() -> Self {
+        Self {
+            directory: "./renders".to_string(),
+            scale: 1,
+        }
+    }
+}
+
+/// Largest [Render::scale]. Big worlds are drawn smaller than this anyway, to stay under [mc_worlds::render::MAX_PIXELS]
+pub const MAX_RENDER_SCALE: usize = 8;
+
+/// How TNT explodes in worlds it's turned on in
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tnt {
+    /// How far explosions reach, in blocks
+    pub radius: usize,
+}
+
+impl Default for Tnt {
+    fn default() -> Self {
+        Self {
+            radius: 4,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
 pub struct Config {
     pub server: Server,
     pub map: Map,
     pub heartbeat: Heartbeat,
+    #[serde(default)]
+    pub chat: Chat,
+    #[serde(default)]
+    pub permissions: Permissions,
+    #[serde(default)]
+    pub filter: Filter,
+    #[serde(default)]
+    pub backup: Backup,
+    #[serde(default)]
+    pub render: Render,
+    #[serde(default)]
+    pub tnt: Tnt,
+    /// Worlds loaded alongside the default world, worlds saved in the world directory are loaded too
+    #[serde(default)]
+    pub worlds: HashMap<String, World>,
+}
+
+/// Path of the server configuration file when none is given
+pub const CONFIG_PATH: &str = "./server.toml";
+
+/// Values given on the command line, these win over both the environment and the config file
+#[derive(Default, Clone)]
+pub struct Overrides {
+    pub port: Option<u16>,
+    pub world_dir: Option<String>,
 }
 
+impl Overrides {
+    fn apply(&self, config: &mut Config) {
+        if let Some(port) = self.port {
+            config.server.port = port;
+        }
+        if let Some(world_dir) = &self.world_dir {
+            config.map.directory = world_dir.clone();
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    /// An environment variable override that couldn't be parsed, holds the variable name
+    Env(String, String),
+    /// The config parsed but has values that can't be used, one message per problem
+    Invalid(PathBuf, Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, e) => write!(f, "Failed to access {}: {}", path.display(), e),
+            ConfigError::Parse(path, e) => write!(f, "Failed to parse {}: {}", path.display(), e),
+            ConfigError::Env(var, e) => write!(f, "Failed to parse environment variable {}: {}", var, e),
+            ConfigError::Invalid(path, problems) => {
+                write!(f, "{} has {} invalid value(s):", path.display(), problems.len())?;
+                for problem in problems {
+                    write!(f, "\n  - {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
 impl Config {
-    pub fn create() -> String {
-        let path = PathBuf::from_str("./server.toml").expect("Could not get path");
-        let config = Self {
-            server: Server::default(),
-            map: Map::default(),
-            heartbeat: Heartbeat::default(),
-        };
-        let out = to_string(&config)
+    /// Write a config file at `path`, returning what was written
+    pub fn create(path: &Path, config: &Config) -> Result<String, ConfigError> {
+        let out = to_string(config)
             .expect("Failed to convert to TOML string");
-        write(path, &out).expect("Failed to write to server.toml");
-        out
+        write(path, &out).map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+        Ok(out)
     }
 
-    pub fn get() -> Self {
-        let path = PathBuf::from_str("./server.toml").expect("Could not get path");
-        let mut file = "".to_string();
-        match read_to_string(path) {
-            Ok(f) => {
-                file = f;
-            },
-            Err(e) => {
-                warn!("Error occurred reading config file: {}", e);
-                file = Config::create();
+    /// Read the config file at `path`, creating a default one if it doesn't exist.
+    /// Environment variables are applied over the file and `overrides` over both, then the result is validated
+    pub fn load(path: &Path, overrides: &Overrides) -> Result<Self, ConfigError> {
+        let file = match read_to_string(path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                warn!("No config file found, creating {}", path.display());
+                Config::create(path, &Config::default())?
             },
+            Err(e) => return Err(ConfigError::Io(path.to_path_buf(), e)),
+        };
+
+        let mut value: Value = from_str(&file).map_err(|e| ConfigError::Parse(path.to_path_buf(), e))?;
+        let defaults = Value::try_from(Config::default()).expect("Failed to convert default config");
+        apply_env(&mut value, &defaults, ENV_PREFIX)?;
+        let mut config: Config = value.try_into().map_err(|e| ConfigError::Parse(path.to_path_buf(), e))?;
+        overrides.apply(&mut config);
+        config.validate().map_err(|problems| ConfigError::Invalid(path.to_path_buf(), problems))?;
+        Ok(config)
+    }
+
+    /// The default config with environment variables and `overrides` applied, used by `--generate-config`
+    pub fn generate(overrides: &Overrides) -> Result<Self, ConfigError> {
+        let defaults = Value::try_from(Config::default()).expect("Failed to convert default config");
+        let mut value = defaults.clone();
+        apply_env(&mut value, &defaults, ENV_PREFIX)?;
+        let mut config: Config = value.try_into()
+            .map_err(|e| ConfigError::Env(ENV_PREFIX.to_string(), e.to_string()))?;
+        overrides.apply(&mut config);
+        Ok(config)
+    }
+
+    /// The size of the world called `name`, from its `[worlds]` entry or `[map]`
+    pub fn world_size(&self, name: &str) -> [usize; 3] {
+        let world = self.world(name).cloned().unwrap_or_default();
+        [world.x_width.unwrap_or(self.map.x_width),
+         world.y_height.unwrap_or(self.map.y_height),
+         world.z_depth.unwrap_or(self.map.z_depth)]
+    }
+
+    /// Generator settings used to create the world called `name` if it doesn't exist yet
+    pub fn world_generator(&self, name: &str) -> &GeneratorSettings {
+        self.world(name)
+            .and_then(|w| w.generator.as_ref())
+            .unwrap_or(&self.map.generator)
+    }
+
+    /// Whether physics runs in the world called `name`
+    pub fn world_physics(&self, name: &str) -> bool {
+        self.world(name).and_then(|w| w.physics).unwrap_or(self.map.physics)
+    }
+
+    /// Radius of explosions in the world called `name`, None if TNT is off there
+    pub fn world_tnt(&self, name: &str) -> Option<usize> {
+        Some(self.tnt.radius).filter(|_| self.world(name).and_then(|w| w.tnt).unwrap_or(self.map.tnt))
+    }
+
+    fn world(&self, name: &str) -> Option<&World> {
+        self.worlds.iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, w)| w)
+    }
+
+    /// Check the values in the config can be used by the server, returning every problem found
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut problems: Vec<String> = vec![];
+
+        if self.server.port == 0 {
+            problems.push("server.port must be between 1 and 65535".to_string());
+        }
+        if self.server.local_ip.parse::<IpAddr>().is_err() {
+            problems.push(format!("server.local_ip \"{}\" is not a valid IP address", self.server.local_ip));
+        }
+        if self.server.ip.is_empty() {
+            problems.push("server.ip must not be empty".to_string());
+        }
+        if self.server.name.len() > LINE_LENGTH {
+            problems.push(format!("server.name must be at most {} characters", LINE_LENGTH));
+        }
+        if self.server.motd.len() > LINE_LENGTH {
+            problems.push(format!("server.motd must be at most {} characters", LINE_LENGTH));
+        }
+        if self.server.max_players == 0 || self.server.max_players > MAX_PLAYERS {
+            problems.push(format!("server.max_players must be between 1 and {}, got {}",
+                                  MAX_PLAYERS, self.server.max_players));
+        }
+        if self.server.save_interval == 0 {
+            problems.push("server.save_interval must be at least 1 minute".to_string());
+        }
+
+        if self.map.directory.is_empty() {
+            problems.push("map.directory must not be empty".to_string());
+        }
+        if self.backup.directory.is_empty() {
+            problems.push("backup.directory must not be empty".to_string());
+        }
+        if self.render.directory.is_empty() {
+            problems.push("render.directory must not be empty".to_string());
+        }
+        if self.render.scale == 0 || self.render.scale > MAX_RENDER_SCALE {
+            problems.push(format!("render.scale must be between 1 and {}, got {}", MAX_RENDER_SCALE, self.render.scale));
+        }
+        if self.tnt.radius == 0 || self.tnt.radius > MAX_RADIUS {
+            problems.push(format!("tnt.radius must be between 1 and {}, got {}", MAX_RADIUS, self.tnt.radius));
+        }
+        let mut worlds: Vec<(String, &str)> = vec![("map".to_string(), &self.map.name)];
+        worlds.extend(self.worlds.keys().map(|name| (format!("worlds.{}", name), name.as_str())));
+        worlds.extend(self.map.preload.iter().map(|name| ("map.preload".to_string(), name.as_str())));
+        for (table, name) in worlds {
+            if name.is_empty() || name.contains(['/', '\\', '.']) {
+                problems.push(format!("World name \"{}\" must not be empty or contain '/', '\\' or '.'", name));
+            }
+            let [x, y, z] = self.world_size(name);
+            for (field, size) in [("x_width", x), ("y_height", y), ("z_depth", z)] {
+                if size == 0 || size % 16 != 0 {
+                    problems.push(format!("{}.{} must be a multiple of 16, got {}", table, field, size));
+                } else if size > MAX_MAP_SIZE {
+                    problems.push(format!("{}.{} must be at most {}, got {}", table, field, MAX_MAP_SIZE, size));
+                }
+            }
+        }
+        let mut generators: Vec<(String, &GeneratorSettings)> = vec![("map.generator".to_string(), &self.map.generator)];
+        generators.extend(self.worlds.iter()
+            .filter_map(|(name, w)| Some((format!("worlds.{}.generator", name), w.generator.as_ref()?))));
+        for (table, settings) in generators {
+            if let Err(e) = generation::from_settings(settings) {
+                problems.push(format!("{}: {}", table, e));
+            }
         }
 
-        from_str(&file).expect("Failed to parse TOML to Config")
+        if self.heartbeat.mineonline.active && self.heartbeat.mineonline.url.is_empty() {
+            problems.push("heartbeat.mineonline.url must be set when it is active".to_string());
+        }
+        if self.heartbeat.mojang.active && self.heartbeat.mojang.url.is_empty() {
+            problems.push("heartbeat.mojang.url must be set when it is active".to_string());
+        }
+
+        if !self.permissions.ranks.iter().any(|r| r.name.eq_ignore_ascii_case(&self.permissions.default_rank)) {
+            problems.push(format!("permissions.default_rank \"{}\" is not one of permissions.ranks",
+                                  self.permissions.default_rank));
+        }
+        for rank in &self.permissions.ranks {
+            let mut color = rank.color.chars();
+            let valid_color = rank.color.is_empty() ||
+                (color.next() == Some('&') && color.next().is_some_and(is_color_code) && color.next().is_none());
+            if !valid_color {
+                problems.push(format!("permissions.ranks color \"{}\" of {} is not a colour code like &a",
+                                      rank.color, rank.name));
+            }
+        }
+        for (player, rank) in &self.permissions.players {
+            if !self.permissions.ranks.iter().any(|r| r.name.eq_ignore_ascii_case(rank)) {
+                problems.push(format!("permissions.players gives {} the unknown rank \"{}\"", player, rank));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
     }
-}
\ No newline at end of file
+}
+
+/// Override values in `config` from environment variables named after their path, `PREFIX_TABLE_KEY`.
+/// `defaults` decides which variables are looked up and what type their values are parsed as,
+/// so tables left out of the config file can still be set from the environment. Keys missing from
+/// `defaults`, the entries of `[worlds]` and `[permissions.players]` and a world's unset fields,
+/// are only set through their table's variable, `PREFIX_TABLE`, holding an inline TOML table that is
+/// merged into it
+fn apply_env(config: &mut Value, defaults: &Value, prefix: &str) -> Result<(), ConfigError> {
+    let (config, defaults) = match (config.as_table_mut(), defaults.as_table()) {
+        (Some(config), Some(defaults)) => (config, defaults),
+        _ => return Ok(()),
+    };
+    for (key, default) in defaults {
+        let var = format!("{}_{}", prefix, key.to_ascii_uppercase());
+        if default.is_table() {
+            let table = config.entry(key.clone())
+                .or_insert_with(|| Value::Table(Default::default()));
+            if let Some(raw) = env_var(&var)? {
+                match (toml_value(&var, &raw)?, table.as_table_mut()) {
+                    (Value::Table(entries), Some(table)) => table.extend(entries),
+                    _ => return Err(ConfigError::Env(var, format!("\"{}\" is not a TOML table", raw))),
+                }
+            }
+            apply_env(table, default, &var)?;
+            continue;
+        }
+        let raw = match env_var(&var)? {
+            Some(raw) => raw,
+            None => continue,
+        };
+        let value = match default {
+            Value::String(_) => Value::String(raw),
+            Value::Integer(_) => Value::Integer(raw.trim().parse()
+                .map_err(|e: std::num::ParseIntError| ConfigError::Env(var.clone(), e.to_string()))?),
+            Value::Boolean(_) => Value::Boolean(raw.trim().parse()
+                .map_err(|e: std::str::ParseBoolError| ConfigError::Env(var.clone(), e.to_string()))?),
+            // Arrays and anything else are written as TOML, e.g. `["word", "other"]`
+            _ => toml_value(&var, &raw)?,
+        };
+        config.insert(key.clone(), value);
+    }
+    Ok(())
+}
+
+/// The value of the environment variable `var`, if it's set
+fn env_var(var: &str) -> Result<Option<String>, ConfigError> {
+    match env::var(var) {
+        Ok(raw) => Ok(Some(raw)),
+        Err(env::VarError::NotPresent) => Ok(None),
+        Err(e) => Err(ConfigError::Env(var.to_string(), e.to_string())),
+    }
+}
+
+/// Parse `raw`, the value of `var`, as a single TOML value
+fn toml_value(var: &str, raw: &str) -> Result<Value, ConfigError> {
+    from_str::<Value>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut v| v.as_table_mut().and_then(|t| t.remove("value")))
+        .ok_or_else(|| ConfigError::Env(var.to_string(), format!("\"{}\" is not a TOML value", raw)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn players_get_their_rank_or_the_default() {
+        let mut permissions = Permissions::default();
+        permissions.players.insert("Sarah".to_string(), "OP".to_string());
+        permissions.players.insert("ghost".to_string(), "nobody".to_string());
+        assert_eq!(permissions.rank_for("sarah").name, "op");
+        assert!(permissions.rank_for("SARAH").op);
+        assert_eq!(permissions.rank_for("someone").name, "guest");
+        // A rank that doesn't exist falls back to a plain guest rather than failing
+        let rank = permissions.rank_for("ghost");
+        assert_eq!(rank.name, "guest");
+        assert!(!rank.op && !rank.chat_colors);
+
+        permissions.default_rank = "builder".to_string();
+        assert_eq!(permissions.rank_for("someone").name, "builder");
+        permissions.default_rank = "missing".to_string();
+        assert_eq!(permissions.rank_for("someone").name, "guest");
+    }
+
+    #[test]
+    fn default_config_is_valid() {
+        assert!(Config::default().validate().is_ok());
+    }
+
+    #[test]
+    fn every_problem_is_reported() {
+        let mut config = Config::default();
+        config.server.port = 0;
+        config.server.local_ip = "localhost".to_string();
+        config.server.max_players = 200;
+        config.server.save_interval = 0;
+        config.map.x_width = 40;
+        config.map.z_depth = 2048;
+        config.render.scale = 0;
+        config.tnt.radius = MAX_RADIUS + 1;
+        config.permissions.default_rank = "nobody".to_string();
+        config.permissions.ranks[0].color = "red".to_string();
+        config.permissions.players.insert("Sarah".to_string(), "admin".to_string());
+        let problems = config.validate().unwrap_err();
+        for expected in [
+            "server.port must be between 1 and 65535",
+            "server.local_ip \"localhost\" is not a valid IP address",
+            "server.max_players must be between 1 and 127, got 200",
+            "server.save_interval must be at least 1 minute",
+            "map.x_width must be a multiple of 16, got 40",
+            "map.z_depth must be at most 1024, got 2048",
+            "render.scale must be between 1 and 8, got 0",
+            "tnt.radius must be between 1 and 16, got 17",
+            "permissions.default_rank \"nobody\" is not one of permissions.ranks",
+            "permissions.ranks color \"red\" of guest is not a colour code like &a",
+            "permissions.players gives Sarah the unknown rank \"admin\"",
+        ] {
+            assert!(problems.iter().any(|p| p == expected), "missing \"{}\" in {:?}", expected, problems);
+        }
+        assert_eq!(problems.len(), 11);
+    }
+
+    #[test]
+    fn world_names_and_sizes_are_checked() {
+        let mut config = Config::default();
+        config.map.name = "../world".to_string();
+        config.map.preload = vec!["".to_string()];
+        config.worlds.insert("small".to_string(), World { y_height: Some(0), ..World::default() });
+        let problems = config.validate().unwrap_err();
+        assert!(problems.contains(&"World name \"../world\" must not be empty or contain '/', '\\' or '.'".to_string()));
+        assert!(problems.contains(&"World name \"\" must not be empty or contain '/', '\\' or '.'".to_string()));
+        assert!(problems.contains(&"worlds.small.y_height must be a multiple of 16, got 0".to_string()));
+    }
+
+    /// The default config as a TOML value, to apply environment variables over
+    fn defaults() -> Value {
+        Value::try_from(Config::default()).unwrap()
+    }
+
+    #[test]
+    fn environment_overrides_values_of_their_type() {
+        // Each test uses its own prefix as the environment is shared between tests
+        env::set_var("ENV_TEST_SERVER_PORT", " 25570 ");
+        env::set_var("ENV_TEST_SERVER_NAME", "From the environment");
+        env::set_var("ENV_TEST_MAP_PHYSICS", "true");
+        env::set_var("ENV_TEST_FILTER_WORDS", "[\"bad\", \"worse\"]");
+        let mut value = defaults();
+        // Tables missing from the file are created
+        value.as_table_mut().unwrap().remove("filter");
+        apply_env(&mut value, &defaults(), "ENV_TEST").unwrap();
+        let config: Config = value.try_into().unwrap();
+        assert_eq!(config.server.port, 25570);
+        assert_eq!(config.server.name, "From the environment");
+        assert!(config.map.physics);
+        assert_eq!(config.filter.words, vec!["bad", "worse"]);
+        assert_eq!(config.server.motd, Server::default().motd);
+    }
+
+    #[test]
+    fn environment_sets_tables_without_default_keys() {
+        env::set_var("ENV_TABLE_WORLDS", "{ arena = { x_width = 64, physics = true } }");
+        env::set_var("ENV_TABLE_PERMISSIONS_PLAYERS", "{ Sarah = \"op\" }");
+        env::set_var("ENV_TABLE_PERMISSIONS_DEFAULT_RANK", "builder");
+        let mut value = defaults();
+        value["worlds"] = from_str("lobby = { tnt = true }").unwrap();
+        apply_env(&mut value, &defaults(), "ENV_TABLE").unwrap();
+        let config: Config = value.try_into().unwrap();
+        // Entries are added to those from the file
+        assert_eq!(config.worlds["arena"].x_width, Some(64));
+        assert_eq!(config.worlds["arena"].physics, Some(true));
+        assert_eq!(config.worlds["arena"].tnt, None);
+        assert_eq!(config.worlds["lobby"].tnt, Some(true));
+        assert_eq!(config.permissions.players["Sarah"], "op");
+        // Keys with defaults can still be set on their own alongside
+        assert_eq!(config.permissions.default_rank, "builder");
+    }
+
+    #[test]
+    fn invalid_environment_values_name_the_variable() {
+        for (var, raw) in [("ENV_BAD_INT_SERVER_PORT", "port"), ("ENV_BAD_BOOL_MAP_PHYSICS", "yes"),
+                           ("ENV_BAD_TOML_FILTER_WORDS", "[unclosed"), ("ENV_BAD_TABLE_WORLDS", "5")] {
+            env::set_var(var, raw);
+            let prefix = var.splitn(4, '_').take(3).collect::<Vec<_>>().join("_");
+            match apply_env(&mut defaults(), &defaults(), &prefix) {
+                Err(ConfigError::Env(name, _)) => assert_eq!(name, var),
+                _ => panic!("{}={} was accepted", var, raw),
+            }
+        }
+    }
+}
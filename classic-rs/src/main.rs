@@ -1,29 +1,50 @@
 use tokio::net::{TcpListener};
-use tokio::time::{Instant, Duration};
-use tokio::sync::Mutex;
+use tokio::time::{Instant, Duration, delay_for};
+use tokio::sync::{Mutex, watch};
 use tokio::signal::ctrl_c;
 use flume::{Receiver, Sender};
 use fern::colors::{Color, ColoredLevelConfig};
-use log::{info, debug, error, warn, Level};
+use log::{info, error, warn};
 use rand::{thread_rng, Rng};
 use rand::distributions::Alphanumeric;
 // use specs::{World, WorldExt, DispatcherBuilder, Builder};
 use std::sync::{Arc};
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
 
-use mc_packets::Packet;
-use mc_packets::classic::{ClientBound, ServerBound};
-use mc_worlds::classic::ClassicWorld;
-use grey_mc_api::event;
+use mc_packets::classic::ClientBound;
+use mc_worlds::classic::{ClassicWorld, Block};
+use mc_worlds::classic::physics;
 
+mod chat;
+mod cli;
 mod client;
+mod command;
 mod config;
+mod worlds;
 
 use client::Client;
-use config::Config;
+use command::Command;
+use config::{Config, ConfigError, Overrides};
+use cli::Args;
+use structopt::StructOpt;
+use chat::{format_chat, filter_message, Mute};
+use worlds::WorldManager;
+use mc_packets::chat::strip_color_codes;
+
+/// Log target private messages are written to, kept out of the main log
+const PRIVATE_LOG: &str = "private";
+/// Most physics ticks run at once to catch up after a slow loop, any more are skipped
+const MAX_PHYSICS_CATCH_UP: u32 = 10;
+/// How far from a player /detonate lights TNT when no block is given, about as far as they can reach
+const DETONATE_REACH: usize = 5;
+/// Most blocks sent to players one by one when a tick changes them, past this such as after a large
+/// explosion the players in the world are sent the level again
+const MAX_BLOCK_UPDATES: usize = 2048;
 
 struct Server {
+    #[allow(dead_code)]
     protocol: u8,
     salt: String,
     mo_heartbeat: Arc<Mutex<mineonline_api::heartbeat::Heartbeat>>,
@@ -31,18 +52,30 @@ struct Server {
     running: Arc<AtomicBool>,
     beatdate: Arc<AtomicBool>,
     client_rx: Receiver<Client>,
-    network_rx: Receiver<(u8, Vec<ClientBound>)>,
-    network_tx: Sender<(u8, Vec<ClientBound>)>,
-    world: Arc<Mutex<ClassicWorld>>,
+    network_rx: Receiver<(u8, String, Vec<ClientBound>)>,
+    #[allow(dead_code)]
+    network_tx: Sender<(u8, String, Vec<ClientBound>)>,
+    worlds: WorldManager,
     // ecs_world: World,
-    config: Config,
+    config: Arc<Config>,
+    /// Where the config was loaded from and the command line values applied over it, kept for reloading
+    config_path: PathBuf,
+    overrides: Overrides,
+    /// Shares the current config with the heartbeat task
+    config_tx: watch::Sender<Arc<Config>>,
+    /// Set when the config should be reloaded, by SIGHUP
+    reload: Arc<AtomicBool>,
     clients: Vec<Client>,
     usernames: Vec<String>,
+    /// Mutes placed on players, by lowercase username
+    mutes: HashMap<String, Mute>,
+    /// When the next physics tick is due
+    physics_tick: Instant,
 }
 
 impl Server {
-    pub async fn new() -> Self {
-        let config = Config::get();
+    pub async fn new(config: Config, config_path: PathBuf, overrides: Overrides) -> Self {
+        let config = Arc::new(config);
         let salt: String = thread_rng().sample_iter(&Alphanumeric).take(16).collect();
         let running = Arc::new(AtomicBool::new(false));
 
@@ -52,12 +85,30 @@ impl Server {
             ctrl_c().await.expect("Failed to listen for event");
             r.store(false, Ordering::SeqCst);
         });
-        let world: Arc<Mutex<ClassicWorld>>  = Arc::new(Mutex::new(ClassicWorld::get_or_create(
-            &config.map.name, &config.map.creator_username,
-            config.map.x_width, config.map.y_height, config.map.z_depth).await));
+
+        let reload = Arc::new(AtomicBool::new(false));
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+            let r = reload.clone();
+            tokio::spawn(async move {
+                let mut hangup = signal(SignalKind::hangup()).expect("Failed to listen for SIGHUP");
+                while hangup.recv().await.is_some() {
+                    r.store(true, Ordering::SeqCst);
+                }
+            });
+        }
+
+        let worlds = match WorldManager::load(&config).await {
+            Ok(worlds) => worlds,
+            Err(e) => {
+                error!("Failed to load world {}: {}", config.map.name, e);
+                std::process::exit(1);
+            }
+        };
 
         // #[cfg(feature = "mineonline_api")]
-        let mut mo_heartbeat = Arc::new(Mutex::new(mineonline_api::heartbeat::Heartbeat::new(
+        let mo_heartbeat = Arc::new(Mutex::new(mineonline_api::heartbeat::Heartbeat::new(
             &config.heartbeat.mineonline.url,
             &config.server.ip,
             config.server.port,
@@ -71,7 +122,7 @@ impl Server {
         )));
 
         // #[cfg(feature = "mojang_api")]
-        let mut m_heartbeat = Arc::new(Mutex::new(mojang_api::heartbeat::Heartbeat::new(
+        let m_heartbeat = Arc::new(Mutex::new(mojang_api::heartbeat::Heartbeat::new(
             &config.heartbeat.mojang.url,
             &config.server.ip,
             config.server.port,
@@ -102,27 +153,27 @@ impl Server {
 
         let (tx, rx) = flume::unbounded::<Client>();
         let local_ip = config.server.local_ip.clone();
-        let port = config.server.port.clone();
+        let port = config.server.port;
         let tx_clone = tx.clone();
-        let (n_tx, n_rx) = flume::unbounded::<(u8, Vec<ClientBound>)>();
+        let (n_tx, n_rx) = flume::unbounded::<(u8, String, Vec<ClientBound>)>();
         let n_tx_clone = n_tx.clone();
 
-        let r = running.clone();
+        let listener = TcpListener::bind(format!("{}:{:#}", local_ip, port)).
+            await.expect("Failed to bind");
+        running.store(true, Ordering::SeqCst);
         tokio::spawn(async move {
-            let listener = TcpListener::bind(format!("{}:{:#}", local_ip, port)).
-                await.expect("Failed to bind");
-            r.store(true, Ordering::SeqCst);
             Server::listen(listener, tx_clone, n_tx_clone).await.expect("Failed to listen");
         });
 
         let beatdate = Arc::new(AtomicBool::new(false));
-        if config.heartbeat.enabled {
+        let (config_tx, config_rx) = watch::channel(config.clone());
+        {
             let r = running.clone();
             let mo_beat = mo_heartbeat.clone();
             let m_beat = m_heartbeat.clone();
             let bd = beatdate.clone();
 
-            Server::spawn_heartbeats(r, mo_beat, m_beat, bd).await;
+            Server::spawn_heartbeats(r, mo_beat, m_beat, bd, config_rx).await;
         }
 
         Self {
@@ -135,11 +186,17 @@ impl Server {
             client_rx: rx,
             network_rx: n_rx,
             network_tx: n_tx,
-            world,
+            worlds,
             // ecs_world,
-            config: Config::get(),
+            config,
+            config_path,
+            overrides,
+            config_tx,
+            reload,
             clients: Vec::new(),
             usernames: Vec::new(),
+            mutes: HashMap::new(),
+            physics_tick: Instant::now(),
         }
     }
 
@@ -148,14 +205,20 @@ impl Server {
             panic!("Failed to bind to port {:#}", self.config.server.port);
         }
         info!("Server Running at {}:{:#}", self.config.server.ip, self.config.server.port);
-        let mut timer = Instant::now();
-        let mut start = Instant::now();
         let mut save = Instant::now();
+        let mut backup = Instant::now();
         while self.running.load(Ordering::SeqCst) {
-            timer = Instant::now();
+            let timer = Instant::now();
+
+            if self.reload.swap(false, Ordering::SeqCst) {
+                if let Err(e) = self.reload_config().await {
+                    error!("{}", e);
+                }
+            }
 
             self.update_network().await;
             self.update_game().await;
+            self.update_worlds().await;
 
             if timer.elapsed().as_millis() > 250 {
                 warn!("Last tick took {:?}", timer.elapsed());
@@ -166,44 +229,97 @@ impl Server {
                 self.save_world().await;
                 save = Instant::now();
             }
+            if self.config.backup.interval > 0 && backup.elapsed().as_secs() >= self.config.backup.interval * 60 {
+                self.worlds.backup_all(&self.config).await;
+                backup = Instant::now();
+            }
         }
 
         info!("Disconnecting all Clients..");
         let start_disconnect = Instant::now();
         for i in 0..self.clients.len() {
             info!("Disconnecting {}", self.clients[i].username);
-            self.clients[i].disconnect(&"Server shutting down")
+            self.clients[i].disconnect("Server shutting down")
                 .await.expect("Failed to disconnect user");
         }
         info!("Disconnecting took {:?}", start_disconnect.elapsed());
 
-        info!("Saving World...");
+        info!("Saving Worlds...");
         let start_save = Instant::now();
-        self.world.lock().await.save_crs_file().await;
+        self.worlds.save_all().await;
         info!("Saving took {:?}", start_save.elapsed());
 
         if self.config.heartbeat.enabled {
             // #[cfg(feature = "mineonline_api")]
             let mo_beat = &self.mo_heartbeat.lock().await;
-            mineonline_api::heartbeat::Heartbeat::delete(&mo_beat.get_url(),
-                                                         &mo_beat.get_uuid()).await
+            mineonline_api::heartbeat::Heartbeat::delete(mo_beat.get_url(),
+                                                         mo_beat.get_uuid()).await
                 .expect("Failed to send delete request");
         }
 
         Ok(())
     }
 
+    /// Run physics and random block ticks in every loaded world once per [physics::TICK],
+    /// sending the blocks they change to the players in that world
     async fn update_game(&mut self) {
+        let mut ticks = 0;
+        while self.physics_tick.elapsed() >= physics::TICK {
+            if ticks == MAX_PHYSICS_CATCH_UP {
+                self.physics_tick = Instant::now();
+                break;
+            }
+            self.physics_tick += physics::TICK;
+            ticks += 1;
+            let worlds: Vec<(String, Arc<Mutex<ClassicWorld>>)> = self.worlds.loaded()
+                .map(|(name, world)| (name.to_string(), world)).collect();
+            for (name, world) in worlds {
+                let mut world_lock = world.lock().await;
+                let mut changes = world_lock.tick();
+                changes.extend(world_lock.random_tick());
+                if changes.is_empty() {
+                    continue;
+                }
+                let packets = match block_updates(changes) {
+                    Some(packets) => packets,
+                    None => {
+                        let stream = world_lock.get_gzipped();
+                        drop(world_lock);
+                        for c in &mut self.clients {
+                            if !c.username.is_empty() && !c.is_downloading() && c.world == name {
+                                let position = c.get_position();
+                                c.start_download(stream.clone(), position).await;
+                            }
+                        }
+                        continue;
+                    }
+                };
+                drop(world_lock);
+                for c in &mut self.clients {
+                    if !c.username.is_empty() && !c.is_downloading() && c.world == name {
+                        c.write_packets(packets.clone()).await;
+                    }
+                }
+            }
+        }
+    }
 
+    /// Unload worlds that have been empty for too long
+    async fn update_worlds(&mut self) {
+        let occupied: Vec<&str> = self.clients.iter().map(|c| c.world.as_str()).collect();
+        self.worlds.unload_idle(&occupied, &self.config).await;
     }
 
     async fn update_network(&mut self) {
-        let mut packet_buffer: Vec<(u8, Vec<ClientBound>)> = vec![(0, Vec::new())];
+        let mut packet_buffer: Vec<(u8, String, Vec<ClientBound>)> = vec![(0, String::new(), Vec::new())];
         let mut player_cleanup: Vec<usize> = vec![];
         let mut fresh_clients: Vec<(u8, usize)> = vec![];
+        let mut commands: Vec<(u8, Command)> = vec![];
+        let mut departures: Vec<(String, Vec<ClientBound>)> = vec![];
         loop {
             match self.client_rx.try_recv() {
-                Ok(client) => {
+                Ok(mut client) => {
+                    client.world = self.worlds.default_world().to_string();
                     self.clients.push(client);
                     self.beatdate.store(true, Ordering::SeqCst);
                 },
@@ -223,23 +339,48 @@ impl Server {
             }
         }
 
-        let mut clients = &mut self.clients;
+        let players_online = self.usernames.len();
+        let names: HashMap<u8, String> = self.clients.iter()
+            .map(|c| (c.get_id(), c.username.clone())).collect();
 
-        for c_pos in 0..clients.len() {
-            let client = &mut clients[c_pos];
-            if client.username != "" && !self.usernames.contains(&client.username) {
-                fresh_clients.push((client.get_id(), c_pos));
+        for (c_pos, client) in self.clients.iter_mut().enumerate() {
+            if !client.username.is_empty() && !self.usernames.contains(&client.username) {
+                self.mutes.retain(|_, mute| mute.is_active());
+                client.mute = self.mutes.get(&client.username.to_ascii_lowercase()).copied();
                 self.usernames.push(client.username.clone());
                 self.beatdate.store(true, Ordering::SeqCst);
             }
             let mut closed = false;
-            match client.handle_connect(&self.salt, self.world.clone()).await {
+            let world = match self.worlds.get(&client.world) {
+                Some((_, world)) => world,
+                None => {
+                    client.world = self.worlds.default_world().to_string();
+                    self.worlds.get(&client.world).expect("Default world is not loaded").1
+                }
+            };
+            // Players sit quietly while the level downloads, so only read from them once they have it.
+            // Anything they missed while downloading is in the level or replayed after it
+            let downloading = client.is_downloading();
+            let result = if downloading {
+                match client.continue_download(&world).await {
+                    Ok(true) => {
+                        fresh_clients.push((client.get_id(), c_pos));
+                        Ok(())
+                    }
+                    Ok(false) => Ok(()),
+                    Err(e) => Err(e),
+                }
+            } else {
+                client.handle_connect(&self.salt, world, &self.config, players_online).await
+            };
+            match result {
                 Ok(_) => {},
                 Err(e) => {
                     if e.kind() == tokio::io::ErrorKind::ConnectionReset ||
-                        e.kind() == tokio::io::ErrorKind::ConnectionAborted {
+                        e.kind() == tokio::io::ErrorKind::ConnectionAborted ||
+                        e.kind() == tokio::io::ErrorKind::BrokenPipe {
                         if !client.username.is_empty() {
-                            self.network_tx.send((client.get_id(), client.despawn_self().to_vec())).unwrap();
+                            departures.push((client.world.clone(), client.despawn_self(&self.config.chat)));
                         }
                         closed = true;
                     } else {
@@ -247,32 +388,34 @@ impl Server {
                     }
                 }
             }
-            if !closed {
-                for i in 0..packet_buffer.len() {
-                    let packets = &packet_buffer[i];
-                    if packets.0 != client.get_id() {
-                        client.write_packets(packets.1.clone()).await;
-                    }
-                }
-            } else {
-                for i in 0..self.usernames.len() {
-                    if i >= self.usernames.len() {
-                        break;
-                    }
-                    if self.usernames[i] == client.username {
-                        self.usernames.remove(i);
+            let id = client.get_id();
+            commands.extend(client.take_commands().into_iter().map(|c| (id, c)));
+            if !closed && !downloading && !client.is_downloading() {
+                for packets in &packet_buffer {
+                    // Only to players still in the world the packets were sent in, they may have left since
+                    if packets.0 != client.get_id() && packets.1 == client.world {
+                        if names.get(&packets.0).is_some_and(|name| client.is_ignoring(name)) {
+                            client.write_packets(packets.2.iter()
+                                .filter(|p| !matches!(p, ClientBound::Message(..)))
+                                .cloned().collect()).await;
+                        } else {
+                            client.write_packets(packets.2.clone()).await;
+                        }
                     }
                 }
+            } else if closed {
+                self.usernames.retain(|u| *u != client.username);
                 player_cleanup.push(c_pos);
                 self.beatdate.store(true, Ordering::SeqCst);
             }
         }
 
-        if fresh_clients.len() > 0 {
+        if !fresh_clients.is_empty() {
             for f_client in &fresh_clients {
                 let mut packets: Vec<ClientBound> = vec![];
+                let world = self.clients[f_client.1].world.clone();
                 for c in &mut self.clients {
-                    if c.get_id() != f_client.0 {
+                    if c.get_id() != f_client.0 && !c.username.is_empty() && c.world == world {
                         packets.push(c.spawn_self().await);
                     }
                 }
@@ -281,9 +424,21 @@ impl Server {
             }
         }
 
-        for id in player_cleanup {
+        for id in player_cleanup.into_iter().rev() {
             self.clients.remove(id);
         }
+
+        for (world, packets) in departures {
+            for c in &mut self.clients {
+                if !c.username.is_empty() && !c.is_downloading() && c.world == world {
+                    c.write_packets(packets.clone()).await;
+                }
+            }
+        }
+
+        for (id, command) in commands {
+            self.handle_command(id, command).await;
+        }
         if self.beatdate.clone().load(Ordering::SeqCst) {
             if self.config.heartbeat.enabled {
                 if self.config.heartbeat.mineonline.active {
@@ -303,10 +458,424 @@ impl Server {
 
     }
 
-    async fn listen(mut listener: TcpListener, tx: Sender<Client>, n_tx: Sender<(u8, Vec<ClientBound>)>)
+    /// Reload the config file, applying everything that can change while the server is running
+    async fn reload_config(&mut self) -> Result<(), ConfigError> {
+        let config = Config::load(&self.config_path, &self.overrides)?;
+        let old = &self.config;
+        if config.server.port != old.server.port || config.server.local_ip != old.server.local_ip {
+            warn!("Changes to server.port and server.local_ip take effect after a restart");
+        }
+        if config.map.name != old.map.name || config.map.directory != old.map.directory || config.map.x_width != old.map.x_width ||
+            config.map.y_height != old.map.y_height || config.map.z_depth != old.map.z_depth {
+            warn!("Changes to the default world in map take effect after a restart");
+        }
+
+        let mut mo_beat = self.mo_heartbeat.lock().await;
+        mo_beat.update_name(&config.server.name);
+        mo_beat.update_motd(&config.server.motd);
+        mo_beat.update_max_users(config.server.max_players);
+        drop(mo_beat);
+        let mut m_beat = self.m_heartbeat.lock().await;
+        m_beat.update_name(&config.server.name);
+        m_beat.update_max_users(config.server.max_players);
+        drop(m_beat);
+
+        for c in &mut self.clients {
+            if !c.username.is_empty() {
+                let rank = config.permissions.rank_for(&c.username);
+                c.set_rank(rank).await;
+            }
+        }
+        self.worlds.preload(&config).await;
+        self.worlds.apply_rules(old, &config).await;
+
+        self.config = Arc::new(config);
+        if self.config_tx.broadcast(self.config.clone()).is_err() {
+            warn!("Heartbeat task is no longer running");
+        }
+        self.beatdate.store(true, Ordering::SeqCst);
+        info!("Reloaded config");
+        Ok(())
+    }
+
+    async fn handle_command(&mut self, sender_id: u8, command: Command) {
+        let sender = match self.clients.iter().position(|c| c.get_id() == sender_id) {
+            Some(sender) => sender,
+            None => return,
+        };
+        let name = self.clients[sender].username.clone();
+        let rank = self.clients[sender].get_rank().clone();
+        match command {
+            Command::Msg(target, message) => {
+                if let Some(message) = self.check_chat(sender, &message).await {
+                    self.private_message(sender, &target, &message).await;
+                }
+            }
+            Command::Reply(message) => {
+                match self.clients[sender].reply_to.clone() {
+                    Some(target) => {
+                        if let Some(message) = self.check_chat(sender, &message).await {
+                            self.private_message(sender, &target, &message).await;
+                        }
+                    }
+                    None => self.tell(sender, "&cNobody has messaged you yet").await,
+                }
+            }
+            Command::Me(message) => {
+                if let Some(message) = self.check_chat(sender, &message).await {
+                    let formatted = format_chat(&self.config.chat.emote_format, &rank, &name, &rank, &message);
+                    info!("{}", strip_color_codes(&formatted));
+                    let world = self.clients[sender].world.clone();
+                    self.broadcast_from(&name, &world, &formatted).await;
+                }
+            }
+            Command::Say(message) => {
+                if !rank.op {
+                    self.tell(sender, "&cYou must be an op to use /say").await;
+                } else if let Some(message) = self.check_chat(sender, &message).await {
+                    let formatted = format_chat(&self.config.chat.say_format, &rank, &name, &rank, &message);
+                    info!("{}", strip_color_codes(&formatted));
+                    self.broadcast(&formatted).await;
+                }
+            }
+            Command::Mute(target, duration) => {
+                if !rank.op {
+                    self.tell(sender, "&cYou must be an op to use /mute").await;
+                    return;
+                }
+                let mute = Mute::new(duration);
+                self.mutes.insert(target.to_ascii_lowercase(), mute);
+                let length = match duration {
+                    Some(duration) => format!("for {}s", duration.as_secs()),
+                    None => "until unmuted".to_string(),
+                };
+                info!("{} muted {} {}", name, target, length);
+                if let Some(muted) = self.find_client(&target) {
+                    self.clients[muted].mute = Some(mute);
+                    self.tell(muted, &format!("&cYou have been muted {}", length)).await;
+                }
+                self.tell(sender, &format!("&eMuted {} {}", target, length)).await;
+            }
+            Command::Unmute(target) => {
+                if !rank.op {
+                    self.tell(sender, "&cYou must be an op to use /unmute").await;
+                    return;
+                }
+                if self.mutes.remove(&target.to_ascii_lowercase()).is_none() {
+                    self.tell(sender, &format!("&c{} is not muted", target)).await;
+                    return;
+                }
+                info!("{} unmuted {}", name, target);
+                if let Some(muted) = self.find_client(&target) {
+                    self.clients[muted].mute = None;
+                    self.tell(muted, "&eYou are no longer muted").await;
+                }
+                self.tell(sender, &format!("&eUnmuted {}", target)).await;
+            }
+            Command::Ignore(target) => {
+                let client = &mut self.clients[sender];
+                if client.is_ignoring(&target) {
+                    client.ignored.retain(|i| !i.eq_ignore_ascii_case(&target));
+                    self.tell(sender, &format!("&eNo longer ignoring {}", target)).await;
+                } else if target.eq_ignore_ascii_case(&name) {
+                    self.tell(sender, "&cYou can't ignore yourself").await;
+                } else {
+                    client.ignored.push(target.clone());
+                    self.tell(sender, &format!("&eIgnoring {}", target)).await;
+                }
+            }
+            Command::Reload => {
+                if !rank.op {
+                    self.tell(sender, "&cYou must be an op to use /reload").await;
+                    return;
+                }
+                info!("{} reloaded the config", name);
+                match self.reload_config().await {
+                    Ok(_) => self.tell(sender, "&eReloaded the config").await,
+                    Err(e) => {
+                        error!("{}", e);
+                        self.tell(sender, "&cFailed to reload the config, see the console").await;
+                    }
+                }
+            }
+            Command::Goto(world) => {
+                let (world_name, world) = match self.worlds.get_or_load(&world, &self.config).await {
+                    Ok(Some(world)) => world,
+                    Ok(None) => {
+                        self.tell(sender, &format!("&cNo world named {}, see /worlds", world)).await;
+                        return;
+                    }
+                    Err(e) => {
+                        error!("Failed to load world {}: {}", world, e);
+                        self.tell(sender, &format!("&cFailed to load {}, see the console", world)).await;
+                        return;
+                    }
+                };
+                if world_name == self.clients[sender].world {
+                    self.tell(sender, &format!("&cYou are already in {}", world_name)).await;
+                    return;
+                }
+                self.change_world(sender, &world_name, world).await;
+            }
+            Command::Worlds => {
+                let worlds: Vec<String> = self.worlds.available(&self.config).await.iter()
+                    .map(|w| if self.worlds.is_loaded(w) {
+                        let players = self.clients.iter()
+                            .filter(|c| !c.username.is_empty() && c.world == *w).count();
+                        format!("{} ({})", w, players)
+                    } else {
+                        format!("&7{}&f", w)
+                    })
+                    .collect();
+                self.tell(sender, &format!("&eWorlds: &f{}", worlds.join(", "))).await;
+            }
+            Command::Spawn => {
+                let world = self.clients[sender].world.clone();
+                let spawn = match self.worlds.get(&world) {
+                    Some((_, world)) => world.lock().await.get_spawn(),
+                    None => return,
+                };
+                let teleport = self.clients[sender].teleport(spawn).await;
+                for c in &mut self.clients {
+                    if c.get_id() != sender_id && !c.username.is_empty() && !c.is_downloading() && c.world == world {
+                        c.write_packets(vec![teleport]).await;
+                    }
+                }
+            }
+            Command::SetSpawn => {
+                if !rank.op {
+                    self.tell(sender, "&cYou must be an op to use /setspawn").await;
+                    return;
+                }
+                let world_name = self.clients[sender].world.clone();
+                let spawn = self.clients[sender].get_position();
+                if let Some((_, world)) = self.worlds.get(&world_name) {
+                    world.lock().await.set_spawn(spawn);
+                    info!("{} set the spawn of {} to {}, {}, {}", name, world_name,
+                          spawn.x / 32, spawn.y / 32, spawn.z / 32);
+                    self.tell(sender, &format!("&eSpawn of {} set to where you are standing", world_name)).await;
+                }
+            }
+            Command::Backup(world) => {
+                if !rank.op {
+                    self.tell(sender, "&cYou must be an op to use /backup").await;
+                    return;
+                }
+                let world = world.unwrap_or_else(|| self.clients[sender].world.clone());
+                let world_name = match self.worlds.get(&world) {
+                    Some((world_name, _)) => world_name.to_string(),
+                    None => {
+                        self.tell(sender, &format!("&c{} is not loaded, see /worlds", world)).await;
+                        return;
+                    }
+                };
+                info!("{} backed up {}", name, world_name);
+                match self.worlds.backup(&world_name, &self.config).await {
+                    Ok(_) => self.tell(sender, &format!("&eBacked up {}", world_name)).await,
+                    Err(e) => {
+                        error!("Failed to back up world {}: {}", world_name, e);
+                        self.tell(sender, &format!("&cFailed to back up {}, see the console", world_name)).await;
+                    }
+                }
+            }
+            Command::Render(world) => {
+                if !rank.op {
+                    self.tell(sender, "&cYou must be an op to use /render").await;
+                    return;
+                }
+                let world = world.unwrap_or_else(|| self.clients[sender].world.clone());
+                let world_name = match self.worlds.get(&world) {
+                    Some((world_name, _)) => world_name.to_string(),
+                    None => {
+                        self.tell(sender, &format!("&c{} is not loaded, see /worlds", world)).await;
+                        return;
+                    }
+                };
+                info!("{} rendered {}", name, world_name);
+                match self.worlds.render(&world_name, &self.config).await {
+                    Ok(paths) => {
+                        let files: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+                        self.tell(sender, &format!("&eDrew {} to {}", world_name, files.join(" and "))).await;
+                    }
+                    Err(e) => {
+                        error!("Failed to render world {}: {}", world_name, e);
+                        self.tell(sender, &format!("&cFailed to render {}, see the console", world_name)).await;
+                    }
+                }
+            }
+            Command::Physics(enabled) => {
+                if !rank.op {
+                    self.tell(sender, "&cYou must be an op to use /physics").await;
+                    return;
+                }
+                let world_name = self.clients[sender].world.clone();
+                if let Some((_, world)) = self.worlds.get(&world_name) {
+                    let mut world = world.lock().await;
+                    let enabled = enabled.unwrap_or(!world.physics_enabled());
+                    world.set_physics(enabled);
+                    drop(world);
+                    let state = if enabled { "on" } else { "off" };
+                    info!("{} turned physics {} in {}", name, state, world_name);
+                    self.tell(sender, &format!("&ePhysics is now {} in {}", state, world_name)).await;
+                }
+            }
+            Command::Detonate(position) => {
+                if position.is_some() && !rank.op {
+                    self.tell(sender, "&cYou must be an op to use /detonate on a block").await;
+                    return;
+                }
+                let world_name = self.clients[sender].world.clone();
+                let world = match self.worlds.get(&world_name) {
+                    Some((_, world)) => world,
+                    None => return,
+                };
+                let mut world = world.lock().await;
+                if world.tnt_radius().is_none() {
+                    drop(world);
+                    self.tell(sender, &format!("&cTNT is off in {}", world_name)).await;
+                    return;
+                }
+                let (centre, reach) = match position {
+                    Some(position) => (position.map(usize::from), 0),
+                    None => {
+                        let at = self.clients[sender].get_position();
+                        ([at.x, at.y, at.z].map(|c| (c.max(0) / 32) as usize), DETONATE_REACH)
+                    }
+                };
+                let size = world.get_size();
+                let range = |axis: usize| centre[axis].saturating_sub(reach)..centre[axis].saturating_add(reach + 1).min(size[axis]);
+                let mut lit = 0;
+                for y in range(1) {
+                    for z in range(2) {
+                        for x in range(0) {
+                            if world.prime(x, y, z) {
+                                lit += 1;
+                            }
+                        }
+                    }
+                }
+                drop(world);
+                if lit > 0 {
+                    info!("{} lit {} TNT in {}", name, lit, world_name);
+                    self.tell(sender, &format!("&eLit {} TNT", lit)).await;
+                } else if position.is_some() {
+                    self.tell(sender, "&cThere is no TNT there").await;
+                } else {
+                    self.tell(sender, &format!("&cThere is no TNT within {} blocks of you", DETONATE_REACH)).await;
+                }
+            }
+            Command::Usage(usage) => {
+                self.tell(sender, &format!("&cUsage: {}", usage)).await;
+            }
+            Command::Unknown(command) => {
+                self.tell(sender, &format!("&cUnknown command: /{}", command)).await;
+            }
+        }
+    }
+
+    /// Move a player into another world, starting to send them the level and
+    /// swapping who they can see in the world they left and the world they joined.
+    /// They are sent the players already in the new world once the level has downloaded
+    async fn change_world(&mut self, client: usize, world_name: &str, world: Arc<Mutex<ClassicWorld>>) {
+        let id = self.clients[client].get_id();
+        let old_world = std::mem::replace(&mut self.clients[client].world, world_name.to_string());
+        for c in &mut self.clients {
+            if c.get_id() != id && !c.username.is_empty() && !c.is_downloading() && c.world == old_world {
+                c.write_packets(vec![ClientBound::DespawnPlayer(id)]).await;
+            }
+        }
+
+        let mut world_lock = world.lock().await;
+        let (stream, spawn) = (world_lock.get_gzipped(), world_lock.get_spawn());
+        drop(world_lock);
+        self.clients[client].start_download(stream, spawn).await;
+
+        let spawn = self.clients[client].spawn_self().await;
+        for c in &mut self.clients {
+            if c.get_id() != id && !c.username.is_empty() && !c.is_downloading() && c.world == world_name {
+                c.write_packets(vec![spawn]).await;
+            }
+        }
+        info!("{} moved from {} to {}", self.clients[client].username, old_world, world_name);
+        self.tell(client, &format!("&eYou are now in {}", world_name)).await;
+    }
+
+    /// Check a player is allowed to chat and run their message through the word filter,
+    /// telling them why if it can't be sent
+    async fn check_chat(&mut self, sender: usize, message: &str) -> Option<String> {
+        if self.clients[sender].is_muted() {
+            self.tell(sender, "&cYou are muted").await;
+            return None;
+        }
+        if self.clients[sender].mute.take().is_some() {
+            // The mute ran out
+            self.mutes.remove(&self.clients[sender].username.to_ascii_lowercase());
+        }
+        let filtered = filter_message(&self.config.filter, message);
+        if filtered.is_none() {
+            self.tell(sender, "&cYour message was blocked by the chat filter").await;
+        }
+        filtered
+    }
+
+    /// Find the position of a logged in client by their username
+    fn find_client(&self, username: &str) -> Option<usize> {
+        self.clients.iter().position(|c|
+            !c.username.is_empty() && c.username.eq_ignore_ascii_case(username))
+    }
+
+    /// Deliver a private message to only the sender and recipient
+    async fn private_message(&mut self, sender: usize, target_name: &str, message: &str) {
+        let target = match self.find_client(target_name) {
+            Some(target) => target,
+            None => {
+                self.tell(sender, &format!("&cNo player named {} is online", target_name)).await;
+                return;
+            }
+        };
+        let sender_name = self.clients[sender].username.clone();
+        let sender_rank = self.clients[sender].get_rank().clone();
+        let target_name = self.clients[target].username.clone();
+        let target_rank = self.clients[target].get_rank().clone();
+        let chat = &self.config.chat;
+        let to = format_chat(&chat.private_to_format, &target_rank, &target_name, &sender_rank, message);
+        let from = format_chat(&chat.private_from_format, &sender_rank, &sender_name, &sender_rank, message);
+        info!(target: PRIVATE_LOG, "{} -> {}: {}", sender_name, target_name, strip_color_codes(message));
+        self.tell(sender, &to).await;
+        if !self.clients[target].is_ignoring(&sender_name) {
+            self.tell(target, &from).await;
+            self.clients[target].reply_to = Some(sender_name);
+        }
+    }
+
+    /// Send a message from the server to one client
+    async fn tell(&mut self, client: usize, msg: &str) {
+        let c = &mut self.clients[client];
+        c.send_message(c.build_message(255, msg)).await;
+    }
+
+    /// Send a message from the server to every logged in client
+    async fn broadcast(&mut self, msg: &str) {
+        for c in &mut self.clients {
+            if !c.username.is_empty() {
+                c.send_message(c.build_message(255, msg)).await;
+            }
+        }
+    }
+
+    /// Send a message on behalf of a player to every logged in client in `world` not ignoring them
+    async fn broadcast_from(&mut self, sender_name: &str, world: &str, msg: &str) {
+        for c in &mut self.clients {
+            if !c.username.is_empty() && c.world == world && !c.is_ignoring(sender_name) {
+                c.send_message(c.build_message(255, msg)).await;
+            }
+        }
+    }
+
+    async fn listen(mut listener: TcpListener, tx: Sender<Client>, n_tx: Sender<(u8, String, Vec<ClientBound>)>)
         -> Result<(), tokio::io::Error> {
         let mut id: u8 = 0;
-        while let Ok((stream, addr)) = listener.accept().await {
+        while let Ok((stream, _addr)) = listener.accept().await {
             let client = Client::new(id, stream, n_tx.clone()).await;
             if tx.send(client).is_err() {
                 panic!("Failed to send client");
@@ -317,12 +886,14 @@ impl Server {
     }
 
     async fn spawn_heartbeats(running: Arc<AtomicBool>, mo_heartbeat: Arc<Mutex<mineonline_api::heartbeat::Heartbeat>>,
-                              m_heartbeat: Arc<Mutex<mojang_api::heartbeat::Heartbeat>>, beatdate: Arc<AtomicBool>) {
+                              m_heartbeat: Arc<Mutex<mojang_api::heartbeat::Heartbeat>>, beatdate: Arc<AtomicBool>,
+                              config: watch::Receiver<Arc<Config>>) {
         tokio::spawn(async move {
-            let mut duration = Instant::now();
-            let config = Config::get();
+            let mut last_beat = Instant::now();
             while running.load(Ordering::SeqCst) {
-                if duration.elapsed().as_secs() % 40 == 0 || beatdate.load(Ordering::SeqCst) {
+                let config = config.borrow().clone();
+                if config.heartbeat.enabled &&
+                    (last_beat.elapsed().as_secs() >= 40 || beatdate.load(Ordering::SeqCst)) {
                     let mut mo_heartbeat = mo_heartbeat.lock().await;
                     let mut m_heartbeat = m_heartbeat.lock().await;
                     if config.heartbeat.mineonline.active {
@@ -336,31 +907,66 @@ impl Server {
                     // beatdate.store(false, Ordering::SeqCst);
                     drop(mo_heartbeat);
                     drop(m_heartbeat);
+                    last_beat = Instant::now();
                 }
-                duration = Instant::now();
+                delay_for(Duration::from_secs(1)).await;
             }
         });
     }
 
     async fn save_world(&mut self) {
-        let w_lock = self.world.clone();
-        let world = w_lock.lock().await;
         for c in &mut self.clients {
             c.send_message(
-                c.build_message("Console", 255, "Saving World..").await).await;
+                c.build_message(255, "<Console>: Saving World..")).await;
         }
-        world.save_crs_file().await;
+        self.worlds.save_all().await;
         for c in &mut self.clients {
             c.send_message(
-                c.build_message("Console", 255, "Saving Complete").await).await;
+                c.build_message(255, "<Console>: Saving Complete")).await;
         }
     }
 }
 
+/// The packets sending blocks changed in a world to its players,
+/// None if there are more than [MAX_BLOCK_UPDATES] and the level should be sent again instead
+fn block_updates(changes: Vec<(usize, usize, usize, Block)>) -> Option<Vec<ClientBound>> {
+    if changes.len() > MAX_BLOCK_UPDATES {
+        return None;
+    }
+    Some(changes.into_iter()
+        .map(|(x, y, z, block)| ClientBound::SetBlock(x as i16, y as i16, z as i16, block.into()))
+        .collect())
+}
+
 #[tokio::main]
 async fn main() -> Result<(), tokio::io::Error> {
-    init_logging().await?;
-    let mut server = Server::new().await;
+    let args = Args::from_args();
+    init_logging(args.log_level).await?;
+    let overrides = args.overrides();
+
+    if args.generate_config {
+        if args.config.exists() {
+            error!("{} already exists, not overwriting it", args.config.display());
+            std::process::exit(1);
+        }
+        match Config::generate(&overrides).and_then(|config| Config::create(&args.config, &config)) {
+            Ok(_) => info!("Wrote default config to {}", args.config.display()),
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    let config = match Config::load(&args.config, &overrides) {
+        Ok(config) => config,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let mut server = Server::new(config, args.config, overrides).await;
     server.run().await.expect("Server loop Failed");
 
 
@@ -368,12 +974,13 @@ async fn main() -> Result<(), tokio::io::Error> {
     Ok(())
 }
 
-async fn init_logging() -> Result<(), tokio::io::Error> {
+async fn init_logging(level: log::LevelFilter) -> Result<(), tokio::io::Error> {
     let colors = ColoredLevelConfig::new()
         .info(Color::Magenta)
         .error(Color::BrightRed);
     let datetime = chrono::Local::now().format("%Y-%m-%d_%H-%M");
     let log_file_path: PathBuf = PathBuf::from(&format!("./logs/{}.log", datetime));
+    let private_log_path: PathBuf = PathBuf::from(&format!("./logs/{}_private.log", datetime));
     if tokio::fs::read_dir("./logs").await.is_err() {
         tokio::fs::create_dir("./logs").await.expect("Failed to create logs folder");
         tokio::fs::File::open(&log_file_path).await.unwrap_or(
@@ -381,7 +988,6 @@ async fn init_logging() -> Result<(), tokio::io::Error> {
         );
     }
     fern::Dispatch::new()
-        .chain(std::io::stdout())
         .format(move |out, message, record| {
             out.finish(format_args!(
                 "[{}]{}[{}] {}",
@@ -392,17 +998,40 @@ async fn init_logging() -> Result<(), tokio::io::Error> {
                 message
             ))
         })
-        .level_for("hyper", log::LevelFilter::Info)
-        .level_for("want", log::LevelFilter::Info)
-        .level_for("mio", log::LevelFilter::Info)
-        .level_for("reqwest", log::LevelFilter::Info)
-        .level_for("tokio", log::LevelFilter::Info)
-        .chain(fern::log_file(log_file_path)?)
+        .level(level)
+        .level_for("hyper", level.min(log::LevelFilter::Info))
+        .level_for("want", level.min(log::LevelFilter::Info))
+        .level_for("mio", level.min(log::LevelFilter::Info))
+        .level_for("reqwest", level.min(log::LevelFilter::Info))
+        .level_for("tokio", level.min(log::LevelFilter::Info))
+        .chain(fern::Dispatch::new()
+            .filter(|metadata| metadata.target() != PRIVATE_LOG)
+            .chain(std::io::stdout())
+            .chain(fern::log_file(log_file_path)?))
+        .chain(fern::Dispatch::new()
+            .filter(|metadata| metadata.target() == PRIVATE_LOG)
+            .chain(fern::log_file(private_log_path)?))
         .apply()
         .unwrap();
     std::panic::set_hook(Box::new(|panic_info| {
-        error!("{}", panic_info.to_string());
+        error!("{}", panic_info);
     }));
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn large_changes_resend_the_level() {
+        let changes = |count: usize| (0..count).map(|i| (i % 16, i / 256, (i / 16) % 16, Block::Air)).collect();
+        let packets = block_updates(changes(3)).expect("A few blocks are sent one by one");
+        assert_eq!(packets.len(), 3);
+        assert!(matches!(packets[1], ClientBound::SetBlock(1, 0, 0, 0)));
+        assert_eq!(block_updates(changes(MAX_BLOCK_UPDATES)).map(|p| p.len()), Some(MAX_BLOCK_UPDATES));
+        // A radius 16 explosion clears far more than that
+        assert!(block_updates(changes(MAX_BLOCK_UPDATES + 1)).is_none());
+    }
+}
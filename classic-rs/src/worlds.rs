@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::fs::{create_dir_all, read_dir};
+use tokio::task::spawn_blocking;
+use tokio::io::ErrorKind;
+use tokio::stream::StreamExt;
+use tokio::time::{Duration, Instant};
+use log::{debug, info, warn, error};
+
+use mc_worlds::classic::ClassicWorld;
+use mc_worlds::crs::CrsError;
+use mc_worlds::formats::Format;
+use mc_worlds::generation::{self, WorldGenerator};
+use mc_worlds::render;
+use mc_worlds::save;
+
+use crate::config::Config;
+
+/// Extension of the files worlds are saved in
+const WORLD_EXTENSION: &str = "crs";
+
+struct LoadedWorld {
+    world: Arc<Mutex<ClassicWorld>>,
+    /// When the last player left, None while anyone is in the world
+    empty_since: Option<Instant>,
+}
+
+/// Holds the loaded worlds by name, players start in the default world and move between them with /goto.
+/// Worlds are loaded when a player first enters them and unloaded once nobody has been in them for a while,
+/// except the default world and those in `map.preload` which always stay loaded
+pub struct WorldManager {
+    directory: PathBuf,
+    default_world: String,
+    worlds: HashMap<String, LoadedWorld>,
+}
+
+impl WorldManager {
+    /// Load the default world and every world in `map.preload`
+    pub async fn load(config: &Config) -> Result<Self, CrsError> {
+        let mut manager = Self {
+            directory: PathBuf::from(&config.map.directory),
+            default_world: config.map.name.clone(),
+            worlds: HashMap::new(),
+        };
+        manager.load_world(&config.map.name, config).await?;
+        manager.preload(config).await;
+        Ok(manager)
+    }
+
+    /// Load every world in `map.preload` that isn't loaded yet
+    pub async fn preload(&mut self, config: &Config) {
+        for name in &config.map.preload {
+            match self.get_or_load(name, config).await {
+                Ok(Some(_)) => (),
+                Ok(None) => warn!("Can't preload {}, there is no world with that name", name),
+                Err(e) => error!("Failed to load world {}: {}", name, e),
+            }
+        }
+    }
+
+    async fn load_world(&mut self, name: &str, config: &Config) -> Result<Arc<Mutex<ClassicWorld>>, CrsError> {
+        let start = Instant::now();
+        let [x, y, z] = config.world_size(name);
+        let settings = config.world_generator(name);
+        let generator: Arc<dyn WorldGenerator> = generation::from_settings(settings)
+            .expect("Generator settings are checked with the config")
+            .into();
+        let seed = if settings.seed == 0 { rand::random() } else { settings.seed };
+        let mut world = ClassicWorld::get_or_create(&self.directory, name, &config.map.creator_username, x, y, z,
+                                                    generator, seed).await?;
+        world.set_physics(config.world_physics(name));
+        world.set_tnt(config.world_tnt(name));
+        let world = Arc::new(Mutex::new(world));
+        self.worlds.insert(name.to_string(), LoadedWorld { world: world.clone(), empty_since: None });
+        info!("Loaded world {} in {:?}", name, start.elapsed());
+        Ok(world)
+    }
+
+    pub fn default_world(&self) -> &str {
+        &self.default_world
+    }
+
+    /// Find a loaded world ignoring case, returning its actual name alongside it
+    pub fn get(&self, name: &str) -> Option<(&str, Arc<Mutex<ClassicWorld>>)> {
+        self.worlds.iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(n, w)| (n.as_str(), w.world.clone()))
+    }
+
+    /// Every loaded world with its name
+    pub fn loaded(&self) -> impl Iterator<Item = (&str, Arc<Mutex<ClassicWorld>>)> {
+        self.worlds.iter().map(|(n, w)| (n.as_str(), w.world.clone()))
+    }
+
+    /// Apply the physics and TNT settings that changed between `old` and `config` to the loaded worlds,
+    /// leaving worlds switched with /physics alone otherwise
+    pub async fn apply_rules(&self, old: &Config, config: &Config) {
+        for (name, loaded) in &self.worlds {
+            let physics = config.world_physics(name);
+            if physics != old.world_physics(name) {
+                loaded.world.lock().await.set_physics(physics);
+                info!("Turned physics {} in {}", if physics { "on" } else { "off" }, name);
+            }
+            let tnt = config.world_tnt(name);
+            if tnt != old.world_tnt(name) {
+                loaded.world.lock().await.set_tnt(tnt);
+                match tnt {
+                    Some(radius) => info!("TNT in {} now explodes {} blocks out", name, radius),
+                    None => info!("Turned TNT off in {}", name),
+                }
+            }
+        }
+    }
+
+    /// Find a world ignoring case, loading it if it is saved in the world directory or listed in `[worlds]`.
+    /// None if there is no world with that name
+    pub async fn get_or_load(&mut self, name: &str, config: &Config)
+                             -> Result<Option<(String, Arc<Mutex<ClassicWorld>>)>, CrsError> {
+        if let Some((name, world)) = self.get(name) {
+            return Ok(Some((name.to_string(), world)));
+        }
+        let name = match self.available(config).await.into_iter().find(|n| n.eq_ignore_ascii_case(name)) {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+        let world = self.load_world(&name, config).await?;
+        Ok(Some((name, world)))
+    }
+
+    pub fn is_loaded(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Names of every world that can be entered, loaded or not, sorted
+    pub async fn available(&self, config: &Config) -> Vec<String> {
+        let mut names: Vec<String> = self.worlds.keys().cloned().collect();
+        names.extend(config.worlds.keys().cloned());
+        names.extend(saved_worlds(&self.directory).await);
+        names.sort_unstable_by_key(|n| n.to_ascii_lowercase());
+        names.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+        names
+    }
+
+    /// Save and drop worlds nobody has been in for `map.unload_after` minutes.
+    /// `occupied` holds the names of the worlds players are in
+    pub async fn unload_idle(&mut self, occupied: &[&str], config: &Config) {
+        if config.map.unload_after == 0 {
+            return;
+        }
+        let unload_after = Duration::from_secs(config.map.unload_after * 60);
+        let mut idle: Vec<String> = vec![];
+        for (name, loaded) in &mut self.worlds {
+            if occupied.contains(&name.as_str()) {
+                loaded.empty_since = None;
+                continue;
+            }
+            let empty_since = *loaded.empty_since.get_or_insert_with(Instant::now);
+            let kept = name.eq_ignore_ascii_case(&self.default_world) ||
+                config.map.preload.iter().any(|p| p.eq_ignore_ascii_case(name));
+            if !kept && empty_since.elapsed() >= unload_after {
+                idle.push(name.clone());
+            }
+        }
+        for name in idle {
+            if let Some(loaded) = self.worlds.remove(&name) {
+                if let Err(e) = loaded.world.lock().await.save_crs_file().await {
+                    error!("Failed to save world {}: {}", name, e);
+                }
+                info!("Unloaded world {}, nobody has been in it for {} minute(s)", name, config.map.unload_after);
+            }
+        }
+    }
+
+    pub async fn save_all(&self) {
+        for (name, loaded) in &self.worlds {
+            if let Err(e) = loaded.world.lock().await.save_crs_file().await {
+                error!("Failed to save world {}: {}", name, e);
+            }
+        }
+    }
+
+    /// Write a timestamped copy of a loaded world to its directory in `backup.directory`,
+    /// then delete its backups past `backup.keep` or older than `backup.max_age`
+    pub async fn backup(&self, name: &str, config: &Config) -> std::io::Result<PathBuf> {
+        let (name, world) = self.get(name)
+            .ok_or_else(|| std::io::Error::new(ErrorKind::NotFound, format!("{} is not loaded", name)))?;
+        let directory = Path::new(&config.backup.directory).join(name);
+        let path = world.lock().await.backup(&directory).await?;
+        let max_age = Some(Duration::from_secs(config.backup.max_age * 60 * 60)).filter(|a| !a.is_zero());
+        for pruned in save::prune_backups(&directory, name, config.backup.keep, max_age).await? {
+            debug!("Deleted old backup {}", pruned.display());
+        }
+        info!("Backed up {} to {}", name, path.display());
+        Ok(path)
+    }
+
+    /// Draw a loaded world from above and isometrically to PNG images in `render.directory`.
+    /// The blocks are copied so players can keep building while it's drawn
+    pub async fn render(&self, name: &str, config: &Config) -> std::io::Result<Vec<PathBuf>> {
+        let (name, world) = self.get(name)
+            .ok_or_else(|| std::io::Error::new(ErrorKind::NotFound, format!("{} is not loaded", name)))?;
+        let snapshot = {
+            let world = world.lock().await;
+            ClassicWorld::from_header(name, "", &world.get_header(), world.get_blocks().to_vec())
+                .expect("The blocks are the world's own")
+        };
+        let scale = config.render.scale;
+        let images = spawn_blocking(move || {
+            Some((render::top_down(&snapshot)?.to_png(), render::isometric(&snapshot, scale)?.to_png()))
+        }).await.expect("Rendering panicked");
+        let (top_down, isometric) = images
+            .ok_or_else(|| std::io::Error::new(ErrorKind::InvalidData, format!("{} is too big to render", name)))?;
+
+        let directory = Path::new(&config.render.directory);
+        create_dir_all(directory).await?;
+        let paths = vec![directory.join(format!("{}.png", name)), directory.join(format!("{}-isometric.png", name))];
+        for (path, png) in paths.iter().zip([top_down, isometric]) {
+            save::write_atomic(path, &png).await?;
+        }
+        info!("Rendered {} to {}", name, directory.display());
+        Ok(paths)
+    }
+
+    pub async fn backup_all(&self, config: &Config) {
+        for name in self.worlds.keys() {
+            if let Err(e) = self.backup(name, config).await {
+                error!("Failed to back up world {}: {}", name, e);
+            }
+        }
+    }
+}
+
+/// Names of the worlds saved in `directory`, including maps from other servers that are imported on load
+async fn saved_worlds(directory: &Path) -> Vec<String> {
+    let entries = match read_dir(directory).await {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Failed to read world directory {}: {}", directory.display(), e);
+            return vec![];
+        }
+    };
+    let paths: Vec<PathBuf> = entries.filter_map(|e| e.ok().map(|e| e.path())).collect().await;
+    paths.iter()
+        .filter(|p| p.extension().is_some_and(|e| e == WORLD_EXTENSION) || Format::from_path(p).is_some())
+        .filter_map(|p| p.file_stem()?.to_str().map(str::to_string))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::World;
+
+    fn config(directory: &Path) -> Config {
+        let mut config = Config::default();
+        config.map.directory = directory.display().to_string();
+        config.map.preload = vec!["Lobby".to_string()];
+        for name in ["lobby", "arena"] {
+            config.worlds.insert(name.to_string(), World::default());
+        }
+        config
+    }
+
+    #[tokio::test]
+    async fn worlds_load_when_entered_and_preloads_stay() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut config = config(directory.path());
+        let mut worlds = WorldManager::load(&config).await.unwrap();
+        assert!(worlds.is_loaded("world") && worlds.is_loaded("lobby"));
+        assert!(!worlds.is_loaded("arena"));
+
+        let (name, _) = worlds.get_or_load("ARENA", &config).await.unwrap().expect("arena is in [worlds]");
+        assert_eq!(name, "arena");
+        assert!(worlds.is_loaded("arena"));
+        assert!(worlds.get_or_load("missing", &config).await.unwrap().is_none());
+        assert_eq!(worlds.available(&config).await, vec!["arena", "lobby", "world"]);
+
+        // Worlds only unload once they have been empty for a while
+        worlds.unload_idle(&[], &config).await;
+        assert!(worlds.is_loaded("arena"));
+        let unload_after = config.map.unload_after;
+        config.map.unload_after = 0;
+        worlds.unload_idle(&[], &config).await;
+        assert!(worlds.is_loaded("arena") && worlds.is_loaded("lobby"));
+        config.map.unload_after = unload_after;
+
+        // Once they have, only the default and preloaded worlds stay, and the rest are saved as they go
+        let saved = directory.path().join("arena.crs");
+        let _ = std::fs::remove_file(&saved);
+        let idle_since = Instant::now() - Duration::from_secs(unload_after * 60 + 1);
+        for loaded in worlds.worlds.values_mut() {
+            loaded.empty_since = Some(idle_since);
+        }
+        worlds.unload_idle(&["lobby"], &config).await;
+        assert!(!worlds.is_loaded("arena"));
+        assert!(saved.exists());
+        assert!(worlds.is_loaded("world") && worlds.is_loaded("lobby"));
+        worlds.unload_idle(&[], &config).await;
+        assert!(worlds.is_loaded("world") && worlds.is_loaded("lobby"));
+    }
+}
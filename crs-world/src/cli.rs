@@ -0,0 +1,127 @@
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// Command line arguments
+#[derive(StructOpt)]
+#[structopt(name = "crs-world", about = "Convert, inspect, resize, render and generate Minecraft Classic maps",
+            after_help = "Maps are read and written by their extension: .crs, .cw, .dat, .fcm, .lvl, .mclevel or .mine")]
+pub enum Args {
+    /// Convert a map to another format
+    Convert {
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+        #[structopt(parse(from_os_str))]
+        output: PathBuf,
+        #[structopt(flatten)]
+        options: MapOptions,
+    },
+    /// Print a map's size, spawn, creator, timestamps, metadata and how many of each block it has
+    Info {
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+        #[structopt(flatten)]
+        options: MapOptions,
+    },
+    /// Change the size of a map, moving what's kept by the offset. Space that's added is air
+    Resize {
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+        #[structopt(parse(from_os_str))]
+        output: PathBuf,
+        /// New width, height and length
+        #[structopt(long, required = true, number_of_values = 3, value_names = &["x", "y", "z"], parse(try_from_str = size))]
+        size: Vec<usize>,
+        /// Where the map's corner goes in the new map, 0 0 0 if not given.
+        /// Negative offsets cut off the start of the map
+        #[structopt(long, number_of_values = 3, value_names = &["x", "y", "z"], allow_hyphen_values = true)]
+        offset: Vec<i64>,
+        #[structopt(flatten)]
+        options: MapOptions,
+    },
+    /// Cut out the part of a map between two corners, both included
+    Crop {
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+        #[structopt(parse(from_os_str))]
+        output: PathBuf,
+        #[structopt(long, required = true, number_of_values = 3, value_names = &["x", "y", "z"])]
+        from: Vec<usize>,
+        #[structopt(long, required = true, number_of_values = 3, value_names = &["x", "y", "z"])]
+        to: Vec<usize>,
+        #[structopt(flatten)]
+        options: MapOptions,
+    },
+    /// Draw a map as a PNG image
+    Render {
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+        /// The .png file to write
+        #[structopt(parse(from_os_str))]
+        output: PathBuf,
+        /// Draw the map from above its corner rather than straight down
+        #[structopt(short, long)]
+        isometric: bool,
+        /// How many times bigger than 4 pixels wide to draw each block in isometric images
+        #[structopt(long, default_value = "1")]
+        scale: usize,
+        #[structopt(flatten)]
+        options: MapOptions,
+    },
+    /// Generate a new map
+    Generate {
+        #[structopt(parse(from_os_str))]
+        output: PathBuf,
+        /// Width, height and length, 256 64 256 if not given
+        #[structopt(long, number_of_values = 3, value_names = &["x", "y", "z"], parse(try_from_str = size))]
+        size: Vec<usize>,
+        /// Generator to use, overrides the name in the settings
+        #[structopt(short, long)]
+        generator: Option<String>,
+        /// Seed for the generator, a random one is picked if it's 0 or not given
+        #[structopt(short, long, allow_hyphen_values = true)]
+        seed: Option<i64>,
+        /// TOML file of generator settings, the same as the [map.generator] table of the server config
+        #[structopt(long, parse(from_os_str))]
+        settings: Option<PathBuf>,
+        /// Who created the map
+        #[structopt(short, long, default_value = "crs-world")]
+        author: String,
+    },
+}
+
+/// Options for reading maps
+#[derive(StructOpt)]
+pub struct MapOptions {
+    /// Size CRS files from before they had a header are read as, 256 64 256 if not given
+    #[structopt(long, number_of_values = 3, value_names = &["x", "y", "z"], parse(try_from_str = size))]
+    pub legacy_size: Vec<usize>,
+    /// Who created the map, for formats that don't say
+    #[structopt(short, long, default_value = "crs-world")]
+    pub author: String,
+}
+
+impl MapOptions {
+    pub fn legacy_size(&self) -> [usize; 3] {
+        triple(&self.legacy_size, DEFAULT_SIZE)
+    }
+}
+
+/// Size of maps when none is given
+pub const DEFAULT_SIZE: [usize; 3] = [256, 64, 256];
+
+/// The three values given to an option taking `number_of_values = 3`, or `default` if it wasn't given
+pub fn triple<T: Copy>(values: &[T], default: [T; 3]) -> [T; 3] {
+    match values {
+        [x, y, z] => [*x, *y, *z],
+        _ => default,
+    }
+}
+
+/// Parse one side of a map's size, which can't be empty
+fn size(text: &str) -> Result<usize, String> {
+    match text.parse::<usize>() {
+        Ok(0) => Err("sizes must be at least 1".to_string()),
+        Ok(size) => Ok(size),
+        Err(e) => Err(e.to_string()),
+    }
+}
@@ -0,0 +1,258 @@
+//! # crs-world
+//! Convert maps between the formats mc_worlds can read, print what they hold, resize or crop them,
+//! draw them and generate new ones, all without running a server
+
+use std::fs;
+use std::path::Path;
+use std::process::exit;
+use std::sync::Arc;
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+use chrono::{TimeZone, Utc};
+use structopt::StructOpt;
+
+use mc_worlds::classic::{Block, ClassicWorld, Spawn};
+use mc_worlds::crs::Header;
+use mc_worlds::generation::{self, GeneratorSettings, surface_spawn};
+use mc_worlds::progress::Progress;
+use mc_worlds::render;
+
+mod cli;
+mod map;
+
+use cli::{Args, MapOptions, DEFAULT_SIZE, triple};
+
+fn main() {
+    if let Err(e) = run(Args::from_args()) {
+        eprintln!("error: {}", e);
+        exit(1);
+    }
+}
+
+fn run(args: Args) -> Result<(), Box<dyn std::error::Error>> {
+    match args {
+        Args::Convert { input, output, options } => {
+            let world = load(&input, &options)?;
+            map::save(&world, &output)?;
+        }
+        Args::Info { input, options } => print_info(&load(&input, &options)?),
+        Args::Resize { input, output, size, offset, options } => {
+            let world = load(&input, &options)?;
+            let resized = resize(&world, &map::name(&output), triple(&size, DEFAULT_SIZE), triple(&offset, [0; 3]));
+            map::save(&resized, &output)?;
+        }
+        Args::Crop { input, output, from, to, options } => {
+            let world = load(&input, &options)?;
+            let cropped = crop(&world, &map::name(&output), triple(&from, [0; 3]), triple(&to, [0; 3]))?;
+            map::save(&cropped, &output)?;
+        }
+        Args::Render { input, output, isometric, scale, options } => {
+            let world = load(&input, &options)?;
+            let image = if isometric { render::isometric(&world, scale) } else { render::top_down(&world) }
+                .ok_or_else(|| format!("{} is too big to render", input.display()))?;
+            fs::write(&output, image.to_png()).map_err(|e| map::MapError::Io(output.clone(), e))?;
+        }
+        Args::Generate { output, size, generator, seed, settings, author } => {
+            let mut settings: GeneratorSettings = match settings {
+                Some(path) => toml::from_str(&fs::read_to_string(&path)?)?,
+                None => GeneratorSettings::default(),
+            };
+            if let Some(generator) = generator {
+                settings.name = generator;
+            }
+            if let Some(seed) = seed {
+                settings.seed = seed;
+            }
+            let world = generate(&map::name(&output), &author, triple(&size, DEFAULT_SIZE), &settings)?;
+            map::save(&world, &output)?;
+        }
+    }
+    Ok(())
+}
+
+fn load(path: &Path, options: &MapOptions) -> Result<ClassicWorld, map::MapError> {
+    map::load(path, &options.author, options.legacy_size())
+}
+
+fn print_info(world: &ClassicWorld) {
+    let header = world.get_header();
+    let [x, y, z] = world.get_size();
+    println!("Name:      {}", world.get_name());
+    println!("Size:      {} x {} x {} ({} blocks)", x, y, z, x * y * z);
+    let spawn = world.get_spawn();
+    println!("Spawn:     {:.2} {:.2} {:.2}, heading {} pitch {}", spawn.x as f32 / 32.0, spawn.y as f32 / 32.0,
+             spawn.z as f32 / 32.0, spawn.h, spawn.p);
+    println!("UUID:      {}", header.uuid);
+    println!("Creator:   {}", world.get_author().unwrap_or("unknown"));
+    match world.get_map_generator() {
+        Some(generator) => match generator.seed {
+            Some(seed) => println!("Generator: {} from {}, seed {}", generator.name, generator.service, seed),
+            None => println!("Generator: {} from {}", generator.name, generator.service),
+        },
+        None => println!("Generator: unknown"),
+    }
+    for (name, time) in [("Created", header.time_created), ("Accessed", header.last_accessed),
+                         ("Modified", header.last_modified)] {
+        match Utc.timestamp_opt(time as i64, 0).single() {
+            Some(time) => println!("{:<10} {}", format!("{}:", name), time.format("%Y-%m-%d %H:%M:%S UTC")),
+            None => println!("{:<10} {}", format!("{}:", name), time),
+        }
+    }
+    if !world.get_metadata().is_empty() {
+        println!("Metadata:");
+        for (group, key, value) in world.get_metadata().iter() {
+            println!("  {}.{} = {}", group, key, value);
+        }
+    }
+
+    let mut counts = [0usize; Block::MAX_ID as usize + 1];
+    for block in world.get_blocks() {
+        counts[*block as usize] += 1;
+    }
+    let mut counts: Vec<(Block, usize)> = counts.iter().enumerate()
+        .filter(|(_, count)| **count > 0)
+        .map(|(id, count)| (Block::from(id as u8), *count))
+        .collect();
+    counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    println!("Blocks:");
+    let total = world.get_blocks().len().max(1);
+    for (block, count) in counts {
+        println!("  {:<18} {:>12} {:>7.3}%", format!("{:?}", block), count, count as f64 * 100.0 / total as f64);
+    }
+}
+
+/// A copy of `world` called `name` that is `size` big, with the corner of `world` at `offset` in it.
+/// Blocks that end up outside the new map are dropped and space that wasn't in `world` is air.
+/// The spawn moves with the blocks, going to the surface if it's no longer inside the map
+fn resize(world: &ClassicWorld, name: &str, size: [usize; 3], offset: [i64; 3]) -> ClassicWorld {
+    let old = world.get_size();
+    let [x, y, z] = size;
+    let mut blocks = vec![u8::from(Block::Air); x * y * z];
+    // The range of each axis of the old map that's kept, and where it starts in the new one
+    let kept: Vec<(usize, usize, usize)> = (0..3).map(|i| {
+        let start = (-offset[i]).max(0) as usize;
+        let end = (size[i] as i64 - offset[i]).clamp(0, old[i] as i64) as usize;
+        (start, end.max(start), (offset[i] + start as i64) as usize)
+    }).collect();
+    let (kept_x, kept_y, kept_z) = (kept[0], kept[1], kept[2]);
+    let length = kept_x.1 - kept_x.0;
+    let old_blocks = world.get_blocks();
+    for old_y in kept_y.0..kept_y.1 {
+        for old_z in kept_z.0..kept_z.1 {
+            let (new_y, new_z) = (old_y - kept_y.0 + kept_y.2, old_z - kept_z.0 + kept_z.2);
+            let from = kept_x.0 + old[0] * (old_z + old[2] * old_y);
+            let to = kept_x.2 + x * (new_z + z * new_y);
+            blocks[to..to + length].copy_from_slice(&old_blocks[from..from + length]);
+        }
+    }
+
+    let old_spawn = world.get_spawn();
+    let moved = [old_spawn.x, old_spawn.y, old_spawn.z].iter().zip(&offset)
+        .map(|(position, offset)| *position as i64 + offset * 32)
+        .collect::<Vec<_>>();
+    let inside = moved.iter().zip(&size).all(|(position, size)| *position >= 0 && *position < *size as i64 * 32);
+    let spawn = if inside {
+        Spawn { x: moved[0] as i16, y: moved[1] as i16, z: moved[2] as i16, ..old_spawn }
+    } else {
+        surface_spawn(&blocks, x, y, z)
+    };
+    let header = Header { x, y, z, spawn, ..world.get_header() };
+    let mut resized = ClassicWorld::from_header(name, world.get_author().unwrap_or(""), &header, blocks)
+        .expect("The blocks are made to fit");
+    for (group, key, value) in world.get_metadata().iter() {
+        resized.metadata_mut().set(group, key, value);
+    }
+    resized
+}
+
+/// A copy of `world` called `name` holding the blocks from `from` to `to`, both included
+fn crop(world: &ClassicWorld, name: &str, from: [usize; 3], to: [usize; 3]) -> Result<ClassicWorld, String> {
+    let size = world.get_size();
+    if (0..3).any(|i| from[i] > to[i] || to[i] >= size[i]) {
+        return Err(format!("{:?} to {:?} isn't inside the {:?} map", from, to, size));
+    }
+    Ok(resize(world, name, [0, 1, 2].map(|i| to[i] - from[i] + 1), from.map(|f| -(f as i64))))
+}
+
+/// Generate a map with the generator named in `settings`, printing how far along it is every second
+fn generate(name: &str, author: &str, [x, y, z]: [usize; 3], settings: &GeneratorSettings)
+            -> Result<ClassicWorld, String> {
+    let generator = generation::from_settings(settings)?;
+    let seed = if settings.seed == 0 { rand::random() } else { settings.seed };
+    let progress = Arc::new(Progress::new());
+    let (tx, rx) = channel();
+    {
+        let (name, author, progress) = (name.to_string(), author.to_string(), progress.clone());
+        thread::spawn(move || {
+            let world = ClassicWorld::generate_with_progress(&name, &author, x, y, z, generator.as_ref(), seed,
+                                                             &progress);
+            tx.send(world).ok();
+        });
+    }
+    loop {
+        match rx.recv_timeout(Duration::from_secs(1)) {
+            Ok(world) => return Ok(world),
+            Err(RecvTimeoutError::Timeout) => eprintln!("{}: {}", name, progress),
+            Err(RecvTimeoutError::Disconnected) => return Err("Generation panicked".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_worlds::generation::empty::Empty;
+
+    /// A 4x4x4 map with a different block in each corner
+    fn world() -> ClassicWorld {
+        let mut world = ClassicWorld::generate("test", "tester", 4, 4, 4, &Empty, 0);
+        world.set_block(0, 0, 0, Block::Stone);
+        world.set_block(3, 0, 0, Block::Dirt);
+        world.set_block(0, 3, 0, Block::Glass);
+        world.set_block(3, 3, 3, Block::Obsidian);
+        world.set_spawn(Spawn { x: 32 + 16, y: 2 * 32, z: 32 + 16, h: 0, p: 0 });
+        world
+    }
+
+    #[test]
+    fn resize_moves_blocks_and_spawn_by_the_offset() {
+        let mut grown = resize(&world(), "grown", [8, 6, 5], [2, 1, 0]);
+        assert_eq!(grown.get_size(), [8, 6, 5]);
+        assert_eq!(grown.get_block(2, 1, 0), Block::Stone);
+        assert_eq!(grown.get_block(5, 1, 0), Block::Dirt);
+        assert_eq!(grown.get_block(2, 4, 0), Block::Glass);
+        assert_eq!(grown.get_block(5, 4, 3), Block::Obsidian);
+        assert_eq!(grown.get_block(0, 0, 0), Block::Air);
+        assert_eq!(grown.get_spawn(), Spawn { x: 3 * 32 + 16, y: 3 * 32, z: 32 + 16, h: 0, p: 0 });
+
+        // A negative offset cuts off the start
+        let mut shrunk = resize(&world(), "shrunk", [2, 4, 4], [-2, 0, 0]);
+        assert_eq!(shrunk.get_block(1, 0, 0), Block::Dirt);
+        assert_eq!(shrunk.get_block(0, 0, 0), Block::Air);
+        assert_eq!(shrunk.get_block(1, 3, 3), Block::Obsidian);
+        // The spawn was cut off so it's found on the surface instead
+        assert!(shrunk.get_spawn().x < 2 * 32);
+    }
+
+    #[test]
+    fn crop_keeps_both_corners() {
+        let mut cropped = crop(&world(), "cropped", [1, 0, 0], [3, 3, 3]).unwrap();
+        assert_eq!(cropped.get_size(), [3, 4, 4]);
+        assert_eq!(cropped.get_block(2, 0, 0), Block::Dirt);
+        assert_eq!(cropped.get_block(2, 3, 3), Block::Obsidian);
+        assert_eq!(crop(&world(), "one", [3, 3, 3], [3, 3, 3]).unwrap().get_size(), [1, 1, 1]);
+        assert!(crop(&world(), "outside", [0, 0, 0], [4, 3, 3]).is_err());
+        assert!(crop(&world(), "backwards", [2, 0, 0], [1, 3, 3]).is_err());
+    }
+
+    #[test]
+    fn empty_sizes_are_refused() {
+        let args = |args: &[&str]| Args::from_iter_safe(["crs-world"].iter().chain(args));
+        assert!(args(&["generate", "out.crs", "--size", "0", "64", "256"]).is_err());
+        assert!(args(&["resize", "in.crs", "out.crs", "--size", "16", "0", "16"]).is_err());
+        assert!(args(&["info", "in.crs", "--legacy-size", "16", "16", "0"]).is_err());
+        assert!(args(&["generate", "out.crs", "--size", "16", "16", "16"]).is_ok());
+        assert!(args(&["info", "in.crs", "--legacy-size", "16", "16", "16"]).is_ok());
+    }
+}
@@ -0,0 +1,86 @@
+//! Reading and writing maps in whichever format their extension says
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use mc_worlds::classic::ClassicWorld;
+use mc_worlds::crs::CrsError;
+use mc_worlds::formats::{Format, FormatError};
+
+/// Extension of the server's own format
+const CRS: &str = "crs";
+
+#[derive(Debug)]
+pub enum MapError {
+    /// Reading or writing the file failed
+    Io(PathBuf, io::Error),
+    /// The file isn't a CRS file this version can read
+    Crs(PathBuf, CrsError),
+    /// The file isn't a map of the format its extension says
+    Format(PathBuf, FormatError),
+    /// The extension isn't one of a supported format
+    Extension(PathBuf),
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            MapError::Crs(path, e) => write!(f, "{}: {}", path.display(), e),
+            MapError::Format(path, e) => write!(f, "{}: {}", path.display(), e),
+            MapError::Extension(path) => {
+                let extensions: Vec<String> = Format::ALL.iter().map(|f| f.to_string()).collect();
+                write!(f, "{}: expected a .{} file or one of {}", path.display(), CRS, extensions.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for MapError {}
+
+/// Name of the world a map at `path` holds, its file name without the extension
+pub fn name(path: &Path) -> String {
+    path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+fn is_crs(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case(CRS))
+}
+
+/// Read the map at `path`. CRS files without a header are read as `legacy_size` and
+/// maps that don't say who made them are credited to `author`.
+/// Blocks that were replaced with classic ones are listed on stderr
+pub fn load(path: &Path, author: &str, legacy_size: [usize; 3]) -> Result<ClassicWorld, MapError> {
+    let format = match Format::from_path(path) {
+        Some(format) => Some(format),
+        None if is_crs(path) => None,
+        None => return Err(MapError::Extension(path.to_path_buf())),
+    };
+    let bytes = fs::read(path).map_err(|e| MapError::Io(path.to_path_buf(), e))?;
+    let name = name(path);
+    match format {
+        Some(format) => {
+            let (world, remapped) = format.read(&bytes, &name, author)
+                .map_err(|e| MapError::Format(path.to_path_buf(), e))?;
+            if !remapped.is_empty() {
+                eprintln!("Replaced {} blocks classic clients don't have: {}", remapped.total(), remapped);
+            }
+            Ok(world)
+        }
+        None => ClassicWorld::from_crs_bytes(&name, author, &bytes, legacy_size)
+            .map_err(|e| MapError::Crs(path.to_path_buf(), e)),
+    }
+}
+
+/// Write `world` to `path` in the format its extension says
+pub fn save(world: &ClassicWorld, path: &Path) -> Result<(), MapError> {
+    let bytes = if is_crs(path) {
+        world.to_crs_bytes()
+    } else {
+        let format = Format::from_path(path).ok_or_else(|| MapError::Extension(path.to_path_buf()))?;
+        format.write(world).map_err(|e| MapError::Format(path.to_path_buf(), e))?
+    };
+    fs::write(path, bytes).map_err(|e| MapError::Io(path.to_path_buf(), e))
+}
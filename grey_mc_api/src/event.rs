@@ -3,6 +3,7 @@ pub trait Event {
     fn is_cancelled(&self) -> bool;
 }
 
+#[allow(dead_code)]
 enum Events {
     /// Dispatched when a user interacts in some way
     /// [false] for right click, [true] for left click
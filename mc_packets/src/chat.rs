@@ -0,0 +1,193 @@
+//! # Chat
+//! Formatting of chat messages into the fixed length strings used by the classic protocol
+
+/// Byte length of a string in the classic protocol
+pub const LINE_LENGTH: usize = 64;
+/// Prefix placed at the start of every wrapped line after the first
+pub const CONTINUATION: &str = "> ";
+
+/// Splits a message into lines of at most [LINE_LENGTH] bytes.
+///
+/// Lines are broken on whitespace, words too long to fit on a line of their own are broken
+/// wherever they run out of space, and every line after the first starts with [CONTINUATION]
+/// followed by the colour code that was active at the end of the previous line.
+pub fn wrap_message(message: &str) -> Vec<String> {
+    let mut wrapper = Wrapper::new();
+    for word in message.split_whitespace() {
+        wrapper.push_word(word);
+    }
+    wrapper.finish()
+}
+
+/// Returns true if `code` is a valid colour code character, `0-9` or `a-f`
+pub fn is_color_code(code: char) -> bool {
+    code.is_ascii_hexdigit()
+}
+
+/// Turns `%x` colour codes into `&x` colour codes
+pub fn convert_color_codes(message: &str) -> String {
+    let mut converted = String::with_capacity(message.len());
+    let mut chars = message.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '%' && chars.peek().is_some_and(|c| is_color_code(*c)) {
+            converted.push('&');
+        } else {
+            converted.push(c);
+        }
+    }
+    converted
+}
+
+/// Removes every valid `&x` colour code from a message
+pub fn strip_color_codes(message: &str) -> String {
+    let mut stripped = String::with_capacity(message.len());
+    let mut chars = message.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '&' && chars.peek().is_some_and(|c| is_color_code(*c)) {
+            chars.next();
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+/// Makes a message safe to send to a client.
+///
+/// The classic client crashes on an `&` that isn't followed by a colour code, so those are
+/// removed along with control characters and any colour codes left dangling at the end.
+pub fn sanitize(message: &str) -> String {
+    let mut sanitized = String::with_capacity(message.len());
+    let mut chars = message.chars().filter(|c| !c.is_control()).peekable();
+    while let Some(c) = chars.next() {
+        if c == '&' {
+            match chars.peek() {
+                Some(code) if is_color_code(*code) => {
+                    sanitized.push(c);
+                    sanitized.push(chars.next().unwrap().to_ascii_lowercase());
+                }
+                _ => {}
+            }
+        } else {
+            sanitized.push(c);
+        }
+    }
+    loop {
+        sanitized.truncate(sanitized.trim_end().len());
+        let mut tail = sanitized.chars().rev();
+        match (tail.next(), tail.next()) {
+            (Some(code), Some('&')) if is_color_code(code) => sanitized.truncate(sanitized.len() - 2),
+            _ => break,
+        }
+    }
+    sanitized
+}
+
+struct Wrapper {
+    lines: Vec<String>,
+    current: String,
+    /// Byte index where the text of the current line begins, after any continuation prefix
+    content_start: usize,
+    /// The last colour code written
+    color: Option<char>,
+}
+
+impl Wrapper {
+    fn new() -> Self {
+        Self {
+            lines: vec![],
+            current: String::new(),
+            content_start: 0,
+            color: None,
+        }
+    }
+
+    fn has_content(&self) -> bool {
+        self.current.len() > self.content_start
+    }
+
+    fn remaining(&self) -> usize {
+        LINE_LENGTH - self.current.len()
+    }
+
+    /// The space left for text on a freshly started continuation line
+    fn continuation_space(&self) -> usize {
+        LINE_LENGTH - CONTINUATION.len() - self.color.map_or(0, |_| 2)
+    }
+
+    fn new_line(&mut self) {
+        let mut line = String::from(CONTINUATION);
+        if let Some(color) = self.color {
+            line.push('&');
+            line.push(color);
+        }
+        self.content_start = line.len();
+        self.lines.push(std::mem::replace(&mut self.current, line));
+    }
+
+    fn push_word(&mut self, word: &str) {
+        let separator = if self.has_content() { 1 } else { 0 };
+        if separator + word.len() <= self.remaining() {
+            if separator > 0 {
+                self.push_str(" ");
+            }
+            self.push_str(word);
+        } else if word.len() <= self.continuation_space() {
+            self.new_line();
+            self.push_str(word);
+        } else {
+            if self.has_content() {
+                if self.remaining() > 1 {
+                    self.push_str(" ");
+                } else {
+                    self.new_line();
+                }
+            }
+            for unit in units(word) {
+                if unit.len() > self.remaining() {
+                    self.new_line();
+                }
+                self.push_str(unit);
+            }
+        }
+    }
+
+    fn push_str(&mut self, text: &str) {
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c == '&' {
+                if let Some(code) = chars.clone().next().filter(|c| is_color_code(*c)) {
+                    self.color = Some(code.to_ascii_lowercase());
+                    chars.next();
+                }
+            }
+        }
+        self.current.push_str(text);
+    }
+
+    fn finish(mut self) -> Vec<String> {
+        if self.has_content() {
+            self.lines.push(self.current);
+        }
+        self.lines
+    }
+}
+
+/// Splits a word into the pieces it can be broken between, keeping colour codes together
+fn units(word: &str) -> Vec<&str> {
+    let mut units = vec![];
+    let mut indices = word.char_indices().peekable();
+    while let Some((start, c)) = indices.next() {
+        let mut end = start + c.len_utf8();
+        if c == '&' {
+            if let Some(&(_, code)) = indices.peek() {
+                if is_color_code(code) {
+                    end += code.len_utf8();
+                    indices.next();
+                }
+            }
+        }
+        units.push(&word[start..end]);
+    }
+    units
+}
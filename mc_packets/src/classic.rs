@@ -3,8 +3,6 @@
 
 use byteorder::{BigEndian, ReadBytesExt};
 use std::io::{Cursor};
-use std::convert::TryInto;
-use log::{debug};
 
 use crate::Packet;
 
@@ -13,6 +11,7 @@ type ByteArray = [u8; 1024];
 type MString = [u8; 64];
 
 #[derive(Debug, Clone, Copy)]
+#[allow(clippy::large_enum_variant)]
 /// Packets to be sent to the clients
 pub enum ClientBound {
     ServerIdentification(u8, MString, MString, u8),
@@ -33,7 +32,7 @@ pub enum ClientBound {
 }
 
 impl Packet<&[u8]> for ClientBound {
-    fn from(buffer: &[u8]) -> Self {
+    fn from(_buffer: &[u8]) -> Self {
         unimplemented!()
     }
 
@@ -42,12 +41,8 @@ impl Packet<&[u8]> for ClientBound {
             ClientBound::ServerIdentification(prot_v, server_name, server_motd, u_type) => {
                 let mut s_identification: Vec<u8> = vec![0x00];
                 s_identification.push(*prot_v);
-                for x in 0..server_name.len() {
-                    s_identification.push(server_name[x]);
-                }
-                for x in 0..server_motd.len() {
-                    s_identification.push(server_motd[x]);
-                }
+                s_identification.extend_from_slice(server_name);
+                s_identification.extend_from_slice(server_motd);
                 s_identification.push(*u_type);
                 s_identification
             },
@@ -61,9 +56,7 @@ impl Packet<&[u8]> for ClientBound {
                 let mut level_data_chunk: Vec<u8> = vec![0x03];
                 level_data_chunk.push((chunk_length >> 8) as u8);
                 level_data_chunk.push(*chunk_length as u8);
-                for x in chunk_data.to_vec() {
-                    level_data_chunk.push(x);
-                }
+                level_data_chunk.extend_from_slice(chunk_data);
                 level_data_chunk.push(*p_complete);
                 level_data_chunk
             },
@@ -91,10 +84,8 @@ impl Packet<&[u8]> for ClientBound {
             ClientBound::SpawnPlayer(
                 origin_p_id, origin_p_name, x, y, z, yaw, pitch) => {
                 let mut spawn_player: Vec<u8> = vec![0x07];
-                spawn_player.push(*origin_p_id as u8);
-                for x in 0..origin_p_name.len() {
-                    spawn_player.push(origin_p_name[x])
-                }
+                spawn_player.push(*origin_p_id);
+                spawn_player.extend_from_slice(origin_p_name);
                 spawn_player.push((x >> 8) as u8);
                 spawn_player.push(*x as u8);
                 spawn_player.push((y >> 8) as u8);
@@ -107,7 +98,7 @@ impl Packet<&[u8]> for ClientBound {
             },
             ClientBound::PlayerTeleport(origin_p_id, x, y, z, yaw, pitch) => {
                 let mut player_teleport: Vec<u8> = vec![0x08];
-                player_teleport.push(*origin_p_id as u8);
+                player_teleport.push(*origin_p_id);
                 player_teleport.push((x >> 8) as u8);
                 player_teleport.push(*x as u8);
                 player_teleport.push((y >> 8) as u8);
@@ -121,7 +112,7 @@ impl Packet<&[u8]> for ClientBound {
             ClientBound::PositionAndOrientationUpdate(
                 origin_p_id, x_change, y_change, z_change, yaw, pitch)=> {
                 let mut pos_orient_update: Vec<u8> = vec![0x09];
-                pos_orient_update.push(*origin_p_id as u8);
+                pos_orient_update.push(*origin_p_id);
                 pos_orient_update.push(*x_change  as u8);
                 pos_orient_update.push(*y_change as u8);
                 pos_orient_update.push(*z_change as u8);
@@ -131,7 +122,7 @@ impl Packet<&[u8]> for ClientBound {
             },
             ClientBound::PositionUpdate(origin_p_id, x_change, y_change, z_change) => {
                 let mut pos_update: Vec<u8> = vec![0x0A];
-                pos_update.push(*origin_p_id as u8);
+                pos_update.push(*origin_p_id);
                 pos_update.push(*x_change as u8);
                 pos_update.push(*y_change as u8);
                 pos_update.push(*z_change as u8);
@@ -139,27 +130,23 @@ impl Packet<&[u8]> for ClientBound {
             },
             ClientBound::OrientationUpdate(origin_p_id, yaw, pitch) => {
                 let mut orient_update: Vec<u8> = vec![0x0B];
-                orient_update.push(*origin_p_id as u8);
+                orient_update.push(*origin_p_id);
                 orient_update.push(*yaw);
                 orient_update.push(*pitch);
                 orient_update
             },
             ClientBound::DespawnPlayer(origin_p_id) => {
-                vec![0x0C, *origin_p_id as u8]
+                vec![0x0C, *origin_p_id]
             },
             ClientBound::Message(origin_p_id, msg) => {
                 let mut message: Vec<u8> = vec![0x0D];
-                message.push(*origin_p_id as u8);
-                for x in 0..msg.len(){
-                    message.push(msg[x]);
-                }
+                message.push(*origin_p_id);
+                message.extend_from_slice(msg);
                 message
             },
             ClientBound::DisconnectPlayer(reason) => {
                 let mut disconnect_player: Vec<u8> = vec![0x0E];
-                for x in 0..reason.len() {
-                    disconnect_player.push(reason[x]);
-                }
+                disconnect_player.extend_from_slice(reason);
                 disconnect_player
             },
             ClientBound::UpdateUserType(u_type) => {
@@ -168,7 +155,7 @@ impl Packet<&[u8]> for ClientBound {
         }
     }
 
-    fn size(id: u8) -> usize {
+    fn size(_id: u8) -> usize {
         unimplemented!()
     }
 }
@@ -193,14 +180,14 @@ impl Packet<&[u8]> for ServerBound {
             0x00 => {
                 let protocol = cursor.read_u8().unwrap();
                 let name = buffer[cursor.position() as usize..
-                    cursor.position() as usize + 64].to_vec().into_iter()
-                    .take_while(|&x| x != (0 as u8)).collect::<Vec<_>>();
+                    cursor.position() as usize + 64].iter().copied()
+                    .take_while(|&x| x != 0_u8).collect::<Vec<_>>();
                 let name = String::from_utf8(name)
                     .expect("Invalid utf8 Message").replace("\u{20}", "");
                 cursor.set_position(cursor.position() + 64);
                 let key = buffer[cursor.position() as usize..
-                    cursor.position() as usize + 64].to_vec().into_iter()
-                    .take_while(|&x| x != (0 as u8)).collect::<Vec<_>>();
+                    cursor.position() as usize + 64].iter().copied()
+                    .take_while(|&x| x != 0_u8).collect::<Vec<_>>();
                 let key = String::from_utf8(key)
                     .expect("Invalid utf8 Message").replace("\u{20}", "");
                 cursor.set_position(cursor.position() + 64);
@@ -225,8 +212,8 @@ impl Packet<&[u8]> for ServerBound {
             }
             0x0d => {
                 let unused: u8 = cursor.read_u8().unwrap();
-                let msg = buffer[cursor.position() as usize..].to_vec().into_iter()
-                    .take_while(|&x| x != (0 as u8)).collect::<Vec<_>>();
+                let msg = buffer[cursor.position() as usize..].iter().copied()
+                    .take_while(|&x| x != 0_u8).collect::<Vec<_>>();
                 let msg = String::from_utf8(msg)
                     .expect("Invalid utf8 Message");
                 ServerBound::Message(unused, msg)
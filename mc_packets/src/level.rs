@@ -0,0 +1,172 @@
+//! # Level
+//! Splitting a gzipped level into the LevelDataChunk packets sent after LevelInitialize, and
+//! putting them back together on the other end
+
+use std::fmt;
+use std::io::Read;
+use flate2::read::GzDecoder;
+
+use crate::classic::ClientBound;
+
+/// Most bytes of the gzipped level a single LevelDataChunk can carry
+pub const CHUNK_SIZE: usize = 1024;
+
+/// Iterator over the LevelDataChunk packets for a gzipped level.
+///
+/// Every chunk is [CHUNK_SIZE] bytes except the last, which holds only what is left and is zero padded,
+/// and each carries how much of the level has been sent once it arrives as a percentage from 0 to 100
+pub struct LevelStreamer<'a> {
+    level: &'a [u8],
+    sent: usize,
+}
+
+impl<'a> LevelStreamer<'a> {
+    pub fn new(level: &'a [u8]) -> Self {
+        Self::resume(level, 0)
+    }
+
+    /// Carry on streaming `level` after the first `sent` bytes
+    pub fn resume(level: &'a [u8], sent: usize) -> Self {
+        Self { level, sent: sent.min(level.len()) }
+    }
+
+    /// How many bytes of the level the packets returned so far hold
+    pub fn sent(&self) -> usize {
+        self.sent
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.sent == self.level.len()
+    }
+}
+
+impl Iterator for LevelStreamer<'_> {
+    type Item = ClientBound;
+
+    fn next(&mut self) -> Option<ClientBound> {
+        if self.is_done() {
+            return None;
+        }
+        let chunk = &self.level[self.sent..self.level.len().min(self.sent + CHUNK_SIZE)];
+        let mut data = [0x00; CHUNK_SIZE];
+        data[..chunk.len()].copy_from_slice(chunk);
+        self.sent += chunk.len();
+        Some(ClientBound::LevelDataChunk(chunk.len() as i16, data, percent(self.sent, self.level.len())))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let chunks = (self.level.len() - self.sent).div_ceil(CHUNK_SIZE);
+        (chunks, Some(chunks))
+    }
+}
+
+impl ExactSizeIterator for LevelStreamer<'_> {}
+
+/// How far through `total` bytes `sent` is, rounded down so 100 is only sent with the last chunk
+fn percent(sent: usize, total: usize) -> u8 {
+    if total == 0 {
+        return 100;
+    }
+    (sent as u64 * 100 / total as u64) as u8
+}
+
+#[derive(Debug)]
+pub enum LevelError {
+    /// A LevelDataChunk said it held a number of bytes outside 1 to [CHUNK_SIZE]
+    ChunkLength(i16),
+    /// A LevelDataChunk's percentage went backwards or past 100, holds the previous and new percentage
+    Progress(u8, u8),
+    /// LevelFinalize arrived before the last chunk, holds the percentage reached
+    Incomplete(u8),
+    /// Level data arrived without a LevelInitialize first
+    NotStarted,
+    Gzip(std::io::Error),
+    /// The level's length prefix didn't match the blocks after it, holds the prefix and the number of blocks
+    Length(usize, usize),
+    /// The level didn't fill the size given in LevelFinalize, holds the size and the length of the level
+    Size((usize, usize, usize), usize),
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelError::ChunkLength(length) => write!(f, "Level chunk claims to hold {} bytes", length),
+            LevelError::Progress(from, to) => write!(f, "Level progress went from {}% to {}%", from, to),
+            LevelError::Incomplete(percent) => write!(f, "Level finalized at {}%", percent),
+            LevelError::NotStarted => write!(f, "Level data sent before the level was initialized"),
+            LevelError::Gzip(e) => write!(f, "Failed to decompress level: {}", e),
+            LevelError::Length(expected, actual) => write!(f, "Level should hold {} blocks but holds {}", expected, actual),
+            LevelError::Size((x, y, z), length) => write!(f, "A {}x{}x{} level can't hold {} blocks", x, y, z, length),
+        }
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+/// Rebuilds a level from the packets a client receives while joining a world
+#[derive(Default)]
+pub struct LevelDecoder {
+    gzipped: Option<Vec<u8>>,
+    percent: u8,
+}
+
+impl LevelDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The percentage sent with the last chunk
+    pub fn percent(&self) -> u8 {
+        self.percent
+    }
+
+    /// Feed in a packet from the server, returning the blocks of the level once it is finalized.
+    /// Packets that aren't part of sending a level are ignored
+    pub fn push(&mut self, packet: &ClientBound) -> Result<Option<Vec<u8>>, LevelError> {
+        match packet {
+            ClientBound::LevelInitialize => {
+                self.gzipped = Some(vec![]);
+                self.percent = 0;
+            },
+            ClientBound::LevelDataChunk(length, data, percent) => {
+                let gzipped = self.gzipped.as_mut().ok_or(LevelError::NotStarted)?;
+                if *length < 1 || *length as usize > CHUNK_SIZE {
+                    return Err(LevelError::ChunkLength(*length));
+                }
+                if *percent < self.percent || *percent > 100 {
+                    return Err(LevelError::Progress(self.percent, *percent));
+                }
+                gzipped.extend_from_slice(&data[..*length as usize]);
+                self.percent = *percent;
+            },
+            ClientBound::LevelFinalize(x, y, z) => {
+                let gzipped = self.gzipped.take().ok_or(LevelError::NotStarted)?;
+                if self.percent != 100 {
+                    return Err(LevelError::Incomplete(self.percent));
+                }
+                let blocks = gunzip(&gzipped)?;
+                if blocks.len() != x * y * z {
+                    return Err(LevelError::Size((*x, *y, *z), blocks.len()));
+                }
+                return Ok(Some(blocks));
+            },
+            _ => (),
+        }
+        Ok(None)
+    }
+}
+
+/// Decompress a gzipped level and strip its length prefix
+fn gunzip(gzipped: &[u8]) -> Result<Vec<u8>, LevelError> {
+    let mut level = vec![];
+    GzDecoder::new(gzipped).read_to_end(&mut level).map_err(LevelError::Gzip)?;
+    if level.len() < 4 {
+        return Err(LevelError::Gzip(std::io::ErrorKind::UnexpectedEof.into()));
+    }
+    let blocks = level.split_off(4);
+    let length = u32::from_be_bytes([level[0], level[1], level[2], level[3]]) as usize;
+    if blocks.len() != length {
+        return Err(LevelError::Length(length, blocks.len()));
+    }
+    Ok(blocks)
+}
@@ -1,9 +1,9 @@
 //! # MC Packets
 //! Packets used by minecraft for communicating between server and client
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
-use std::io::{Cursor, BufRead};
 
+pub mod chat;
 pub mod classic;
+pub mod level;
 
 /// # Packet
 pub trait Packet<T> {
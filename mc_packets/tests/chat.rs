@@ -0,0 +1,109 @@
+use mc_packets::chat::{wrap_message, convert_color_codes, strip_color_codes, sanitize, LINE_LENGTH, CONTINUATION};
+
+
+#[test]
+fn short_message() {
+    assert_eq!(wrap_message("<Player>: Hello   World"), vec!["<Player>: Hello World"]);
+}
+
+#[test]
+fn empty_message() {
+    assert!(wrap_message("   ").is_empty());
+}
+
+#[test]
+fn wraps_on_words() {
+    let message = "the quick brown fox jumps over the lazy dog ".repeat(10);
+    let lines = wrap_message(&message);
+
+    assert!(lines.len() > 1);
+    for line in &lines {
+        assert!(line.len() <= LINE_LENGTH, "{:?} is too long", line);
+    }
+    for line in &lines[1..] {
+        assert!(line.starts_with(CONTINUATION));
+    }
+    let rebuilt: Vec<&str> = lines.iter()
+        .map(|l| l.strip_prefix(CONTINUATION).unwrap_or(l))
+        .flat_map(|l| l.split(' '))
+        .collect();
+    let original: Vec<&str> = message.split_whitespace().collect();
+    assert_eq!(rebuilt, original);
+}
+
+#[test]
+fn breaks_long_words() {
+    let word = "x".repeat(200);
+    let lines = wrap_message(&format!("start {} end", word));
+
+    for line in &lines {
+        assert!(line.len() <= LINE_LENGTH, "{:?} is too long", line);
+    }
+    let rebuilt: String = lines.iter()
+        .map(|l| l.strip_prefix(CONTINUATION).unwrap_or(l))
+        .collect::<Vec<&str>>().join("");
+    assert_eq!(rebuilt.matches('x').count(), 200);
+    assert!(lines[0].starts_with("start x"));
+    assert!(lines.last().unwrap().ends_with(" end"));
+}
+
+#[test]
+fn breaks_multibyte_words_on_char_boundaries() {
+    let word = "é".repeat(100);
+    let lines = wrap_message(&word);
+
+    for line in &lines {
+        assert!(line.len() <= LINE_LENGTH);
+    }
+    let count: usize = lines.iter().map(|l| l.matches('é').count()).sum();
+    assert_eq!(count, 100);
+}
+
+#[test]
+fn carries_color_codes() {
+    let message = format!("&cred {}", "word ".repeat(30));
+    let lines = wrap_message(&message);
+
+    assert!(lines.len() > 1);
+    for line in &lines[1..] {
+        assert!(line.starts_with(&format!("{}&c", CONTINUATION)), "{:?} lost its colour", line);
+    }
+}
+
+#[test]
+fn carries_latest_color_code() {
+    let message = format!("&cred {} &afinal {}", "word ".repeat(12), "word ".repeat(12));
+    let lines = wrap_message(&message);
+
+    assert!(lines.last().unwrap().starts_with(&format!("{}&a", CONTINUATION)));
+}
+
+#[test]
+fn keeps_color_codes_together() {
+    let word = "&e".repeat(100);
+    let lines = wrap_message(&word);
+
+    for line in &lines {
+        let text = line.strip_prefix(CONTINUATION).unwrap_or(line);
+        assert_eq!(text.len() % 2, 0);
+        assert!(!text.ends_with('&'));
+    }
+}
+
+#[test]
+fn converts_percent_codes() {
+    assert_eq!(convert_color_codes("%ared %zfifty% &b"), "&ared %zfifty% &b");
+}
+
+#[test]
+fn strips_color_codes() {
+    assert_eq!(strip_color_codes("&ahello &zworld&"), "hello &zworld&");
+}
+
+#[test]
+fn sanitizes_invalid_codes() {
+    assert_eq!(sanitize("rock & roll &zok &Afine"), "rock  roll zok &afine");
+    assert_eq!(sanitize("ends with &"), "ends with");
+    assert_eq!(sanitize("dangling &a &b"), "dangling");
+    assert_eq!(sanitize("tab\there"), "tabhere");
+}
@@ -0,0 +1,133 @@
+use std::io::Write;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+
+use mc_packets::Packet;
+use mc_packets::classic::ClientBound;
+use mc_packets::level::{LevelStreamer, LevelDecoder, LevelError, CHUNK_SIZE};
+
+fn gzip(blocks: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder.write_all(&(blocks.len() as u32).to_be_bytes()).unwrap();
+    encoder.write_all(blocks).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// Blocks that don't compress well so the level takes plenty of chunks
+fn blocks(length: usize) -> Vec<u8> {
+    let mut state = 0x1234_5678u32;
+    (0..length).map(|_| {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        (state % 50) as u8
+    }).collect()
+}
+
+fn decode(packets: &[ClientBound], x: usize, y: usize, z: usize) -> Result<Option<Vec<u8>>, LevelError> {
+    let mut decoder = LevelDecoder::new();
+    decoder.push(&ClientBound::LevelInitialize)?;
+    for packet in packets {
+        decoder.push(packet)?;
+    }
+    decoder.push(&ClientBound::LevelFinalize(x, y, z))
+}
+
+#[test]
+fn chunks_hold_exactly_the_level() {
+    let level = gzip(&blocks(32 * 32 * 32));
+    let packets: Vec<ClientBound> = LevelStreamer::new(&level).collect();
+    assert_eq!(packets.len(), level.len().div_ceil(CHUNK_SIZE));
+    assert_eq!(packets.len(), LevelStreamer::new(&level).len());
+
+    let mut sent = 0;
+    for (i, packet) in packets.iter().enumerate() {
+        match packet {
+            ClientBound::LevelDataChunk(length, data, percent) => {
+                let length = *length as usize;
+                if i + 1 < packets.len() {
+                    assert_eq!(length, CHUNK_SIZE);
+                } else {
+                    assert_eq!(length, level.len() - sent, "Last chunk has the wrong length");
+                    assert!(data[length..].iter().all(|b| *b == 0));
+                }
+                assert_eq!(&data[..length], &level[sent..sent + length]);
+                sent += length;
+                assert_eq!(*percent as usize, sent * 100 / level.len());
+            },
+            _ => panic!("Streamer sent {:?}", packet),
+        }
+    }
+    assert_eq!(sent, level.len());
+}
+
+#[test]
+fn progress_climbs_to_100() {
+    let level = gzip(&blocks(64 * 64 * 64));
+    let percents: Vec<u8> = LevelStreamer::new(&level).map(|packet| match packet {
+        ClientBound::LevelDataChunk(_, _, percent) => percent,
+        _ => unreachable!(),
+    }).collect();
+
+    assert!(percents.windows(2).all(|w| w[0] <= w[1]));
+    assert!(percents[0] < 5);
+    assert!(percents.contains(&50));
+    assert_eq!(percents.iter().filter(|p| **p == 100).count(), 1);
+    assert_eq!(*percents.last().unwrap(), 100);
+}
+
+#[test]
+fn decoder_rebuilds_level() {
+    for (x, y, z) in [(16, 16, 16), (32, 64, 32), (1, 1, 1)] {
+        let blocks = blocks(x * y * z);
+        let level = gzip(&blocks);
+        let packets: Vec<ClientBound> = LevelStreamer::new(&level).collect();
+        assert_eq!(decode(&packets, x, y, z).unwrap(), Some(blocks));
+    }
+}
+
+#[test]
+fn resumed_stream_matches_whole_stream() {
+    let level = gzip(&blocks(32 * 32 * 32));
+    let whole: Vec<Vec<u8>> = LevelStreamer::new(&level).map(|p| Packet::into(&p)).collect();
+
+    let mut streamer = LevelStreamer::new(&level);
+    let mut parts: Vec<Vec<u8>> = streamer.by_ref().take(3).map(|p| Packet::into(&p)).collect();
+    assert_eq!(streamer.sent(), 3 * CHUNK_SIZE);
+    parts.extend(LevelStreamer::resume(&level, streamer.sent()).map(|p| Packet::into(&p)));
+    assert_eq!(parts, whole);
+}
+
+#[test]
+fn decoder_rejects_bad_streams() {
+    let blocks = blocks(32 * 32 * 32);
+    let level = gzip(&blocks);
+    let packets: Vec<ClientBound> = LevelStreamer::new(&level).collect();
+
+    assert!(matches!(decode(&packets[..packets.len() - 1], 32, 32, 32), Err(LevelError::Incomplete(_))));
+    assert!(matches!(decode(&packets, 16, 16, 16), Err(LevelError::Size(..))));
+
+    let mut backwards = packets.clone();
+    backwards.swap(0, packets.len() - 1);
+    assert!(matches!(decode(&backwards, 32, 32, 32), Err(LevelError::Progress(..))));
+
+    let oversized = ClientBound::LevelDataChunk(CHUNK_SIZE as i16 + 1, [0; CHUNK_SIZE], 100);
+    assert!(matches!(decode(&[oversized], 32, 32, 32), Err(LevelError::ChunkLength(_))));
+
+    let mut decoder = LevelDecoder::new();
+    assert!(matches!(decoder.push(&packets[0]), Err(LevelError::NotStarted)));
+
+    // A level cut off before its gzip footer
+    let mut short = gzip(&blocks);
+    short.truncate(short.len() - 8);
+    let packets: Vec<ClientBound> = LevelStreamer::new(&short).collect();
+    assert!(decode(&packets, 32, 32, 32).is_err());
+}
+
+#[test]
+fn empty_level_is_complete() {
+    let level = gzip(&[]);
+    let packets: Vec<ClientBound> = LevelStreamer::new(&level).collect();
+    assert_eq!(packets.len(), 1);
+    assert_eq!(decode(&packets, 0, 0, 0).unwrap(), Some(vec![]));
+}
@@ -0,0 +1,42 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use mc_worlds::compression::{gzip_level, gzip_level_sequential};
+use mc_worlds::generation::{from_settings, GeneratorSettings};
+use mc_worlds::progress::Progress;
+
+const SIZES: [usize; 2] = [256, 512];
+
+fn generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("generate");
+    group.sample_size(10);
+    for name in ["flatgrass", "islands"] {
+        let settings = GeneratorSettings { name: name.to_string(), ..GeneratorSettings::default() };
+        let generator = from_settings(&settings).unwrap();
+        for size in SIZES {
+            group.bench_with_input(BenchmarkId::new(name, size), &size, |b, &size| {
+                b.iter(|| generator.generate(1234, size, size, size, &Progress::new()))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn compression(c: &mut Criterion) {
+    let settings = GeneratorSettings { name: "islands".to_string(), ..GeneratorSettings::default() };
+    let generator = from_settings(&settings).unwrap();
+    let mut group = c.benchmark_group("gzip");
+    group.sample_size(10);
+    for size in SIZES {
+        let blocks = generator.generate(1234, size, size, size, &Progress::new());
+        group.bench_with_input(BenchmarkId::new("parallel", size), &blocks, |b, blocks| {
+            b.iter(|| gzip_level(blocks, size * size, &Progress::new()))
+        });
+        group.bench_with_input(BenchmarkId::new("sequential", size), &blocks, |b, blocks| {
+            b.iter(|| gzip_level_sequential(blocks))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, generation, compression);
+criterion_main!(benches);
@@ -1,18 +1,32 @@
-use byteorder::{LittleEndian};
 use std::time::{SystemTime, UNIX_EPOCH};
-use flate2::write::GzEncoder;
-use flate2::read::GzDecoder;
-use flate2::Compression;
-use std::path::PathBuf;
-use std::io::{Write};
-use tokio::io::{Error, ErrorKind, BufWriter, AsyncWriteExt, BufReader, AsyncReadExt};
-use tokio::fs::{File, read_dir, create_dir, DirEntry, OpenOptions};
+use std::convert::TryInto;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::collections::BTreeMap;
+use tokio::io::ErrorKind;
+use tokio::fs::{read, read_dir, create_dir, create_dir_all, copy, DirEntry};
 use tokio::stream::StreamExt;
+use tokio::task::spawn_blocking;
+use tokio::time::{timeout, Duration};
 use uuid;
 use uuid::Uuid;
-use log::{debug, info};
-use nbt::from_gzip_reader;
-use serde::{Serialize, Deserialize};
+use log::{info, warn, error};
+
+use crate::crs::{self, CrsError, Header};
+use crate::formats::Format;
+use crate::generation::WorldGenerator;
+use crate::generation::flatgrass::Flatgrass;
+use crate::level::LevelStream;
+use crate::progress::Progress;
+use crate::save::{write_atomic, backup_name};
+
+pub mod physics;
+pub mod scheduler;
+pub mod light;
+pub mod growth;
+pub mod explosion;
+
+use physics::Physics;
 
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -184,8 +198,95 @@ impl From<Block> for u8 {
     }
 }
 
-pub trait Metadata {
+impl Block {
+    /// The highest block id in Minecraft Classic
+    pub const MAX_ID: u8 = 0x31;
 
+    /// Find a block by its id or its name, ignoring case, spaces and underscores, so `white_cloth`,
+    /// `White Cloth` and `36` are all [Block::WhiteCloth]
+    pub fn from_name(name: &str) -> Option<Block> {
+        if let Ok(id) = name.parse::<u8>() {
+            return if id <= Block::MAX_ID { Some(id.into()) } else { None };
+        }
+        let name: String = name.chars().filter(|c| !matches!(c, '_' | ' ' | '-')).collect();
+        (0..=Block::MAX_ID).map(Block::from).find(|block| format!("{:?}", block).eq_ignore_ascii_case(&name))
+    }
+}
+
+/// Directory worlds are saved to unless told otherwise
+pub const DEFAULT_DIRECTORY: &str = "./world";
+
+/// Most block changes kept for players downloading a level,
+/// once more pile up the level is rebuilt and downloads still running start again
+pub const MAX_CHANGES: usize = 8192;
+
+/// Extra information about a world as text, in groups of keys and values.
+/// Used for whatever maps imported from other servers store that a world has no field for
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Metadata {
+    groups: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Metadata {
+    /// Marks the metadata written after the blocks in a CRS file
+    const MAGIC: [u8; 4] = *b"META";
+
+    pub fn get(&self, group: &str, key: &str) -> Option<&str> {
+        self.groups.get(group)?.get(key).map(String::as_str)
+    }
+
+    pub fn set(&mut self, group: &str, key: &str, value: &str) {
+        self.groups.entry(group.to_string()).or_default().insert(key.to_string(), value.to_string());
+    }
+
+    /// Every group, key and value, sorted by group then key
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str, &str)> {
+        self.groups.iter().flat_map(|(group, values)| {
+            values.iter().map(move |(key, value)| (group.as_str(), key.as_str(), value.as_str()))
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.groups.values().map(BTreeMap::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Metadata::MAGIC.to_vec();
+        bytes.extend_from_slice(&(self.len() as u32).to_be_bytes());
+        for (group, key, value) in self.iter() {
+            for text in [group, key, value] {
+                let text = &text.as_bytes()[..text.len().min(u16::MAX as usize)];
+                bytes.extend_from_slice(&(text.len() as u16).to_be_bytes());
+                bytes.extend_from_slice(text);
+            }
+        }
+        bytes
+    }
+
+    /// Read metadata written by [Metadata::to_bytes] along with how many bytes it took up
+    fn from_bytes(bytes: &[u8]) -> Option<(Self, usize)> {
+        if bytes.len() < 8 || bytes[..4] != Metadata::MAGIC {
+            return None;
+        }
+        let count = u32::from_be_bytes(bytes[4..8].try_into().ok()?);
+        let mut position = 8;
+        let mut text = || {
+            let length = u16::from_be_bytes(bytes.get(position..position + 2)?.try_into().ok()?) as usize;
+            let text = bytes.get(position + 2..position + 2 + length)?;
+            position += 2 + length;
+            Some(String::from_utf8_lossy(text).into_owned())
+        };
+        let mut metadata = Metadata::default();
+        for _ in 0..count {
+            let (group, key, value) = (text()?, text()?, text()?);
+            metadata.set(&group, &key, &value);
+        }
+        Some((metadata, position))
+    }
 }
 
 #[allow(dead_code)]
@@ -231,6 +332,7 @@ impl MineWorld {
 */
 /// # ClassicWorld File Format
 /// A file format for custom classic minecraft worlds defined by https://wiki.vg/ClassicWorld_file_format
+#[allow(dead_code)]
 pub struct ClassicWorld {
     /// Constant, always at 1
     format_version: u8,
@@ -258,65 +360,80 @@ pub struct ClassicWorld {
     spawn: Spawn,
     /// The block data, 1 byte per block, same order as LevelDataChunk Packet
     blocks: Vec<u8>,
-    /// A GzipEncoded version of the blocks Vec
-    gzipped: Vec<u8>,
-    // metadata: Vec<Metadata>
+    /// Directory the world is saved to
+    directory: PathBuf,
+    /// Counts block changes, bumped every time one is set
+    revision: u64,
+    /// The latest gzipped level, rebuilt when it is asked for after the blocks change
+    gzipped: Option<LevelStream>,
+    /// Blocks changed since a level stream was taken that could still be downloading, with the revision of each
+    changes: Vec<(u64, (usize, usize, usize, Block))>,
+    /// Changes made at or before this revision have been dropped
+    changes_from: u64,
+    metadata: Metadata,
+    /// Blocks waiting for physics to update them
+    physics: Physics,
+    /// Lowest lit y of every column, see [light]
+    light: Vec<u32>,
 }
 
 impl ClassicWorld {
-    //! Create a new empty world with a name aswell as dimensions
+    //! Create a new flat world with a name aswell as dimensions
     pub fn new(name: &str, author: &str, x: usize, y: usize, z: usize) -> Self {
+        Self::generate(name, author, x, y, z, &Flatgrass::default(), 0)
+    }
 
-        let mut blocks: Vec<u8> =
-            (0..(x * y * z)).map(|k| Block::Air.into()).collect();
-        for i in 0..x * z {
-            blocks[i] = Block::Bedrock.into();
-        }
-        // TODO: Split this into multiple threads handling multiple chunks at once and combine at the end
-        for i in 0+(x*z)..x * z * (y/2) - 1 {
-            blocks[i] = Block::Dirt.into();
-        }
-        for i in x * z * ((y/2) - 1)..x * z * (y/2) {
-            blocks[i] = Block::GrassBlock.into();
-        }
+    /// Create a new world with `generator`. The same generator, seed and dimensions always make the same map,
+    /// the generator and its seed are kept in the worlds [MapGenerator]
+    pub fn generate(name: &str, author: &str, x: usize, y: usize, z: usize, generator: &dyn WorldGenerator,
+                    seed: i64) -> Self {
+        Self::generate_with_progress(name, author, x, y, z, generator, seed, &Progress::new())
+    }
 
-        let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
-        encoder.write(&(blocks.len() as u32).to_be_bytes()).unwrap();
-        encoder.write_all(blocks.as_slice()).unwrap();
-        let compressed = encoder.finish().expect("Failed to compress data");
+    /// [ClassicWorld::generate], counting finished work in `progress`.
+    /// This can take seconds for large maps so async code should run it with `spawn_blocking`
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate_with_progress(name: &str, author: &str, x: usize, y: usize, z: usize,
+                                  generator: &dyn WorldGenerator, seed: i64, progress: &Progress) -> Self {
+        let blocks = generator.generate(seed, x, y, z, progress);
+        let spawn = generator.spawn(&blocks, x, y, z);
+        let mut world = Self::with_blocks(name, author, x, y, z, blocks);
+        world.map_generator = Some(MapGenerator::new(generator.name(), Some(seed).filter(|_| generator.seeded())));
+        world.spawn = spawn;
+        progress.finish();
+        world
+    }
 
-        Self {
-            format_version: 1,
-            name: name.to_string(),
-            uuid: Uuid::new_v4(),
-            x,
-            y,
-            z,
-            created_by: Some(CreatedBy { service: "Classic-RS".to_string(), username: author.to_string() }),
-            map_generator: Some(MapGenerator { service: "Classic-RS".to_string(), username: author.to_string() }),
-            time_created: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
-            last_accessed: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
-            last_modified: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
-            spawn: Spawn {
-                x: 0,
-                y,
-                z: 0,
-                h: 0,
-                p: 0
-            },
-            blocks,
-            gzipped: compressed,
+    /// Generate a world on the blocking thread pool, logging its progress every second, then save it
+    async fn create(directory: PathBuf, name: &str, author: &str, [x, y, z]: [usize; 3],
+                    generator: Arc<dyn WorldGenerator>, seed: i64) -> ClassicWorld {
+        let progress = Arc::new(Progress::new());
+        let mut task = {
+            let (name, author, progress) = (name.to_string(), author.to_string(), progress.clone());
+            spawn_blocking(move || {
+                ClassicWorld::generate_with_progress(&name, &author, x, y, z, generator.as_ref(), seed, &progress)
+            })
+        };
+        let mut cw = loop {
+            match timeout(Duration::from_secs(1), &mut task).await {
+                Ok(world) => break world.expect("World generation panicked"),
+                Err(_) => info!("{}: {}", name, progress),
+            }
+        };
+        cw.directory = directory;
+        if let Err(e) = cw.save_crs_file().await {
+            error!("Failed to save new world {}: {}", name, e);
         }
+        cw
     }
 
     pub async fn from_buffer(name: &str, author: &str, x: usize, y: usize, z: usize, buffer: &[u8]) ->
                                                                                                     ClassicWorld {
-        let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
-        encoder.write(&(buffer.len() as u32).to_be_bytes()).unwrap();
-        encoder.write_all(buffer).unwrap();
-        let compressed = encoder.finish().expect("Failed to compress data");
-        // let mut buffer = buffer.clone().to_vec();
-        Self {
+        Self::with_blocks(name, author, x, y, z, buffer.to_vec())
+    }
+
+    fn with_blocks(name: &str, author: &str, x: usize, y: usize, z: usize, blocks: Vec<u8>) -> Self {
+        let mut world = Self {
             format_version: 1,
             name: name.to_string(),
             uuid: Uuid::new_v4(),
@@ -324,25 +441,47 @@ impl ClassicWorld {
             y,
             z,
             created_by: Some(CreatedBy { service: "Classic-RS".to_string(), username: author.to_string() }),
-            map_generator: Some(MapGenerator { service: "Classic-RS".to_string(), username: author.to_string() }),
+            map_generator: None,
             time_created: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
             last_accessed: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
             last_modified: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
-            spawn: Spawn {
-                x: 0,
-                y,
-                z: 0,
-                h: 0,
-                p: 0
-            },
-            blocks: buffer.to_vec(),
-            gzipped: compressed,
+            spawn: Spawn::centre(x, y, z),
+            blocks,
+            directory: PathBuf::from(DEFAULT_DIRECTORY),
+            revision: 0,
+            gzipped: None,
+            changes: vec![],
+            changes_from: 0,
+            metadata: Metadata::default(),
+            physics: Physics::default(),
+            light: vec![],
+        };
+        world.light_columns();
+        world
+    }
+
+    /// A world described by `header` holding `blocks`, for worlds read from files
+    pub fn from_header(name: &str, author: &str, header: &Header, blocks: Vec<u8>) -> Result<Self, CrsError> {
+        if blocks.len() != header.volume() {
+            return Err(CrsError::Size([header.x, header.y, header.z], blocks.len()));
         }
+        let mut cw = ClassicWorld::with_blocks(name, author, header.x, header.y, header.z, blocks);
+        cw.uuid = header.uuid;
+        cw.spawn = header.spawn;
+        cw.time_created = header.time_created;
+        cw.last_accessed = header.last_accessed;
+        cw.last_modified = header.last_modified;
+        Ok(cw)
     }
 
-    pub async fn get_or_create(name: &str, author: &str, x: usize, y: usize, z: usize) -> ClassicWorld {
+    /// Load the world called `name` from `directory`, creating the directory if it doesn't exist
+    /// and the world with `generator` and `seed` if it hasn't been saved.
+    /// Worlds saved before CRS files had a header are read as `x` by `y` by `z` and saved again in the new format
+    #[allow(clippy::too_many_arguments)]
+    pub async fn get_or_create(directory: &Path, name: &str, author: &str, x: usize, y: usize, z: usize,
+                               generator: Arc<dyn WorldGenerator>, seed: i64) -> Result<ClassicWorld, CrsError> {
         let start = std::time::Instant::now();
-        let world_dir_path: PathBuf = PathBuf::from("./world");
+        let world_dir_path: PathBuf = directory.to_path_buf();
         let world_dir = match read_dir(&world_dir_path).await {
             Ok(dir) => Some(dir),
             Err(e) => {
@@ -357,125 +496,223 @@ impl ClassicWorld {
         let contents: Vec<DirEntry> =
             world_dir.unwrap().map(|f| f.expect("Failed to read entry")).collect().await;
         if contents.is_empty() {
-            let cw = ClassicWorld::new(name, author, x, y, z);
-            cw.save_crs_file().await;
+            let cw = ClassicWorld::create(world_dir_path, name, author, [x, y, z], generator, seed).await;
             info!("Took {:?} to load World", std::time::Instant::now()
                 .duration_since(start));
-            return cw;
+            return Ok(cw);
         } else {
-            let cw_file: Option<&DirEntry> =
+            let crs_entry: Option<&DirEntry> =
                 contents.iter().find(|e|
-                    e.file_name().to_str().unwrap()[e.file_name().len()-3..] == *".cw"
+                    e.file_name().to_str().unwrap() == format!("{}.crs", name)
                 );
-
-            if let Some(cw) = cw_file {
-                // ClassicWorld::load_classic_world(File::open(cw_file.unwrap().path()).await
-                //     .expect("Failed to open File")).await;
-                let cw = ClassicWorld::new(name, author, x, y, z);
-                cw.save_crs_file().await;
+            if let Some(crs) = crs_entry {
+                let path = crs.path();
+                let bytes = read(&path).await.map_err(|e| CrsError::Io(path.clone(), e))?;
+                let legacy = !crs::is_crs(&bytes);
+                let (world_name, author) = (name.to_string(), author.to_string());
+                let mut cw = spawn_blocking(move || {
+                    ClassicWorld::from_crs_bytes(&world_name, &author, &bytes, [x, y, z])
+                }).await.expect("Reading world panicked")?;
+                cw.directory = world_dir_path;
+                if legacy {
+                    cw.migrate(&path).await?;
+                }
                 info!("Took {:?} to load World", std::time::Instant::now()
                     .duration_since(start));
-                return cw;
-            } else {
-                let crs_entry: Option<&DirEntry> =
-                    contents.iter().find(|e|
-                        e.file_name().to_str().unwrap() == format!("{}.crs", name)
-                    );
-                if let Some(crs) = crs_entry {
-                    let f = File::open(crs.path()).await.expect("Failed to open CRS file");
-                    let cw = ClassicWorld::from_buffer(name, author, x, y, z,
-                        ClassicWorld::load_crs_world(f, x*y*z).await.as_slice()).await;
-                    info!("Took {:?} to load World", std::time::Instant::now()
-                        .duration_since(start));
-                    return cw;
-                }
-            };
+                return Ok(cw);
+            }
+            let import = contents.iter().find_map(|e| {
+                let path = e.path();
+                let format = Format::from_path(&path)?;
+                (path.file_stem()? == name).then_some((path, format))
+            });
+            if let Some((path, format)) = import {
+                let mut cw = ClassicWorld::import(&path, format, name, author).await?;
+                cw.directory = world_dir_path;
+                let crs_path = cw.directory.join(format!("{}.crs", name));
+                cw.save_crs_file().await.map_err(|e| CrsError::Io(crs_path, e))?;
+                info!("Imported {} from {}, the original is left in place", name, path.display());
+                info!("Took {:?} to load World", std::time::Instant::now()
+                    .duration_since(start));
+                return Ok(cw);
+            }
         }
-        let cw = ClassicWorld::new(name, author, x, y, z);
-        cw.save_crs_file().await;
+        let cw = ClassicWorld::create(world_dir_path, name, author, [x, y, z], generator, seed).await;
         info!("Took {:?} to load World", std::time::Instant::now()
             .duration_since(start));
-        return cw;
-    }
-
-    pub async fn load_crs_world(file: File, size: usize) -> Vec<u8> {
-        let mut buffer: Vec<u8> = Vec::with_capacity(size);
-        let mut reader: BufReader<File> = BufReader::new(file);
-        let mut percentage: usize = 0;
-        while buffer.len() < size {
-            let mut temp_buf: Vec<u8> = Vec::with_capacity(16384);
-            reader.read_buf(&mut temp_buf).await.expect("Failed to read buffer");
-            buffer.append(&mut temp_buf);
-            let c_percent = ((buffer.len() as f32/(size) as f32)*100.0) as usize;
-            if c_percent > percentage {
-                debug!("World Percentage Loaded: {:#}%", c_percent);
+        Ok(cw)
+    }
+
+    /// Read a world from the contents of a CRS file. Files from before CRS had a header don't say how big
+    /// the world is so they are read as `legacy_size`
+    pub fn from_crs_bytes(name: &str, author: &str, bytes: &[u8], legacy_size: [usize; 3])
+                          -> Result<ClassicWorld, CrsError> {
+        if !crs::is_crs(bytes) {
+            let [x, y, z] = legacy_size;
+            if bytes.len() < x * y * z {
+                return Err(CrsError::Length(x * y * z, bytes.len()));
             }
-            percentage = c_percent;
+            let (blocks, trailer) = bytes.split_at(x * y * z);
+            let mut cw = ClassicWorld::with_blocks(name, author, x, y, z, blocks.to_vec());
+            cw.read_trailer(trailer)?;
+            return Ok(cw);
+        }
+        let (header, mut body) = crs::decode(bytes)?;
+        let trailer = body.split_off(header.volume());
+        let mut cw = ClassicWorld::from_header(name, author, &header, body)?;
+        cw.read_trailer(&trailer)?;
+        Ok(cw)
+    }
+
+    /// Save a world loaded from a file without a header in the current format,
+    /// keeping the old file next to it in case it was read with the wrong size
+    async fn migrate(&self, path: &Path) -> Result<(), CrsError> {
+        let old_path = path.with_extension(format!("crs.v{}", crs::VERSION - 1));
+        warn!("{} has no CRS header, saving it as CRS version {} and keeping the old file as {}",
+              path.display(), crs::VERSION, old_path.display());
+        copy(path, &old_path).await.map_err(|e| CrsError::Io(old_path, e))?;
+        self.save_crs_file().await.map_err(|e| CrsError::Io(path.to_path_buf(), e))
+    }
+
+    /// Read a map saved by another server in `format`, logging any blocks that had to be remapped
+    pub async fn import(path: &Path, format: Format, name: &str, author: &str) -> Result<ClassicWorld, CrsError> {
+        let bytes = read(path).await.map_err(|e| CrsError::Io(path.to_path_buf(), e))?;
+        let (world_name, author) = (name.to_string(), author.to_string());
+        let (world, remapped) = spawn_blocking(move || format.read(&bytes, &world_name, &author)).await
+            .expect("Importing world panicked")
+            .map_err(|e| CrsError::Import(path.to_path_buf(), e))?;
+        if !remapped.is_empty() {
+            warn!("Replaced {} blocks classic clients don't have importing {}: {}",
+                  remapped.total(), path.display(), remapped);
         }
-        buffer
-    }
-
-    // pub async fn load_classic_world(file: File) -> ClassicWorld {
-    //     let reader: BufReader<File> = BufReader::new(file);
-    //     #[derive(Serialize, Deserialize, Debug)]
-    //     struct C_By {
-    //         Service: String,
-    //         Username: String
-    //     }
-    //     #[derive(Serialize, Deserialize, Debug)]
-    //     struct M_Gen {
-    //         Service: String,
-    //         Username: String
-    //     }
-    //     #[derive(Serialize, Deserialize, Debug)]
-    //     struct Spwn {
-    //         X: i16,
-    //         Y: i16,
-    //         Z: i16,
-    //         H: u8,
-    //         P: u8
-    //     }
-    //     #[derive(Serialize, Deserialize, Debug)]
-    //     struct CWorld {
-    //         FormatVersion: u8,
-    //         Name: String,
-    //         UUID: [u8; 16],
-    //         X: i16,
-    //         Y: i16,
-    //         Z: i16,
-    //         CreatedBy: C_By,
-    //         MapGenerator: M_Gen,
-    //         TimeCreated: i64,
-    //         LastAccessed: i64,
-    //         LastModified: i64,
-    //         Spawn: Spwn,
-    //         BlockArray: Vec<u8>,
-    //         Metadata: Vec<u8>,
-    //     }
-    //     let nbt: CWorld = from_gzip_reader(reader.buffer()).expect("Failed to read NBT from gZip");
-    //     debug!("{:?}", nbt);
-    //     return ClassicWorld::new("", "", 16, 16, 16);
-    // }
-
-    pub async fn save_crs_file(&self) {
+        Ok(world)
+    }
+
+    /// Read the records saved after the blocks in a CRS file, failing on anything unrecognised
+    fn read_trailer(&mut self, mut trailer: &[u8]) -> Result<(), CrsError> {
+        while !trailer.is_empty() {
+            let read = if trailer.starts_with(&Spawn::MAGIC) {
+                Spawn::from_bytes(trailer).map(|spawn| self.spawn = spawn).map(|_| Spawn::LENGTH)
+            } else if trailer.starts_with(&MapGenerator::MAGIC) {
+                MapGenerator::from_bytes(trailer).map(|(generator, length)| {
+                    self.map_generator = Some(generator);
+                    length
+                })
+            } else if trailer.starts_with(&Metadata::MAGIC) {
+                Metadata::from_bytes(trailer).map(|(metadata, length)| {
+                    self.metadata = metadata;
+                    length
+                })
+            } else {
+                None
+            };
+            match read {
+                Some(length) => trailer = &trailer[length..],
+                None => return Err(CrsError::Trailing(trailer.len())),
+            }
+        }
+        Ok(())
+    }
+
+    /// Save the world to `<name>.crs` in its directory, replacing the last save only once the new one is on disk
+    pub async fn save_crs_file(&self) -> std::io::Result<()> {
         let start_time = std::time::Instant::now();
         info!("Starting World Save");
-        let file_path = PathBuf::from(format!("./world/{}.crs", self.name));
-        let file: File = match OpenOptions::new().write(true).open(file_path.as_path()).await {
-            Ok(f) => {
-                f
-            },
-            Err(e) => {
-                if e.kind() == ErrorKind::NotFound {
-                    File::create(file_path.as_path()).await.expect("Failed to create File")
-                } else{
-                    panic!("Failed to get save file: {:?}", e);
-                }
-            }
-        };
-        let mut writer: BufWriter<File> = BufWriter::new(file);
-        writer.write_all(self.blocks.as_slice()).await.expect("Failed to write to world file");
+        let file_path = self.directory.join(format!("{}.crs", self.name));
+        write_atomic(&file_path, &self.encode_crs().await).await?;
         info!("Saving took {:?}", start_time.elapsed());
+        Ok(())
+    }
+
+    /// Save a timestamped copy of the world in `directory`, creating it if needed, and return its path
+    pub async fn backup(&self, directory: &Path) -> std::io::Result<PathBuf> {
+        create_dir_all(directory).await?;
+        let mut time = chrono::Utc::now();
+        let mut file_path = directory.join(backup_name(&self.name, time));
+        // Backups taken in the same millisecond get the next free one instead of replacing each other
+        while tokio::fs::metadata(&file_path).await.is_ok() {
+            time += chrono::Duration::milliseconds(1);
+            file_path = directory.join(backup_name(&self.name, time));
+        }
+        write_atomic(&file_path, &self.encode_crs().await).await?;
+        Ok(file_path)
+    }
+
+    /// The contents of the world's CRS file, compressed on the blocking thread pool
+    async fn encode_crs(&self) -> Vec<u8> {
+        let (header, body) = (self.get_header(), self.crs_body());
+        spawn_blocking(move || crs::encode(&header, &body)).await.expect("Encoding world panicked")
+    }
+
+    /// The contents of the world's CRS file
+    pub fn to_crs_bytes(&self) -> Vec<u8> {
+        crs::encode(&self.get_header(), &self.crs_body())
+    }
+
+    /// The blocks followed by the records saved with them
+    fn crs_body(&self) -> Vec<u8> {
+        let mut body = Vec::with_capacity(self.blocks.len() + 64);
+        body.extend_from_slice(&self.blocks);
+        if let Some(generator) = &self.map_generator {
+            body.extend_from_slice(&generator.to_bytes());
+        }
+        if !self.metadata.is_empty() {
+            body.extend_from_slice(&self.metadata.to_bytes());
+        }
+        body
+    }
+
+    /// Everything a CRS file stores about the world besides its blocks and records
+    pub fn get_header(&self) -> Header {
+        Header {
+            version: crs::VERSION,
+            x: self.x,
+            y: self.y,
+            z: self.z,
+            spawn: self.spawn,
+            uuid: self.uuid,
+            time_created: self.time_created,
+            last_accessed: self.last_accessed,
+            last_modified: self.last_modified,
+        }
+    }
+
+    pub fn get_directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Name of who created the world
+    pub fn get_author(&self) -> Option<&str> {
+        self.created_by.as_ref().map(|c| c.username.as_str())
+    }
+
+    pub fn set_map_generator(&mut self, generator: Option<MapGenerator>) {
+        self.map_generator = generator;
+    }
+
+    pub fn get_map_generator(&self) -> Option<&MapGenerator> {
+        self.map_generator.as_ref()
+    }
+
+    pub fn get_metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.metadata
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_spawn(&self) -> Spawn {
+        self.spawn
+    }
+
+    pub fn set_spawn(&mut self, spawn: Spawn) {
+        self.spawn = spawn;
+        self.last_modified = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
     }
 
     pub fn get_size(&self) -> [usize; 3] {
@@ -487,61 +724,186 @@ impl ClassicWorld {
     }
 
     pub fn set_block(&mut self, x: usize, mut y: usize, z: usize, block: Block) -> (usize, usize, usize, Block) {
-        let mut pos = x + (self.x as usize * z) + ((self.z as usize * self.x as usize)  * y);
+        let mut pos = x + (self.x * z) + ((self.z * self.x)  * y);
         let mut block = block;
-        if y < self.y {
-            match self.get_block(x, y-1, z) {
-                Block::Slab => {
-                    if block == Block::Slab {
-                        pos = x + (self.x as usize * z) + ((self.z as usize * self.x as usize) * (y - 1));
-                        y = y - 1;
-                        block = Block::DoubleSlab;
-                    }
-                }
-                _ => {},
-            }
+        if y > 0 && y < self.y && block == Block::Slab && self.get_block(x, y - 1, z) == Block::Slab {
+            pos = x + (self.x * z) + ((self.z * self.x) * (y - 1));
+            y -= 1;
+            block = Block::DoubleSlab;
         }
-        self.blocks[pos as usize] = block.into();
+        self.put_block(pos, (x, y, z, block))
+    }
+
+    /// Set a block to exactly `block`, without the slab stacking [ClassicWorld::set_block] does
+    pub fn replace_block(&mut self, x: usize, y: usize, z: usize, block: Block) -> (usize, usize, usize, Block) {
+        let pos = x + (self.x * z) + ((self.z * self.x)  * y);
+        self.put_block(pos, (x, y, z, block))
+    }
+
+    fn put_block(&mut self, pos: usize, change: (usize, usize, usize, Block)) -> (usize, usize, usize, Block) {
+        let old = Block::from(std::mem::replace(&mut self.blocks[pos], change.3.into()));
         self.last_modified = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-        (x, y, z, block)
+        self.revision += 1;
+        if self.gzipped.is_some() {
+            if self.changes.len() < MAX_CHANGES {
+                self.changes.push((self.revision, change));
+            } else {
+                // Nobody needs the old level once it is this far behind, stop recording until it is asked for again
+                self.gzipped = None;
+                self.changes.clear();
+                self.changes_from = self.revision;
+            }
+        }
+        self.light_changed(pos, old, change.3);
+        if self.physics_active() {
+            self.block_changed(pos, old, change.3);
+        }
+        change
+    }
+
+    /// Counts block changes, the level from [ClassicWorld::get_gzipped] is up to date while this is unchanged
+    pub fn get_revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// The gzipped level stream, shared by everyone who asks for it until the blocks change.
+    /// Once they have, the next call starts building a new one in the background.
+    /// Whoever downloads it should then be sent [ClassicWorld::changes_since] its revision
+    pub fn get_gzipped(&mut self) -> LevelStream {
+        if let Some(stream) = &self.gzipped {
+            // A level still being built is shared too, the changes made since it was taken are replayed
+            if stream.revision() == self.revision || !stream.is_ready() {
+                return stream.clone();
+            }
+            // Players still downloading the stream being replaced only need what changed after it
+            let replaced = stream.revision();
+            self.changes.retain(|(revision, _)| *revision > replaced);
+            self.changes_from = replaced;
+        }
+        let stream = LevelStream::build(self.revision, self.blocks.clone(), self.x * self.z);
+        self.gzipped = Some(stream.clone());
+        stream
+    }
+
+    /// Blocks changed after `revision` in the order they were changed,
+    /// None if changes that far back are no longer kept
+    pub fn changes_since(&self, revision: u64) -> Option<Vec<(usize, usize, usize, Block)>> {
+        if revision < self.changes_from {
+            return None;
+        }
+        Some(self.changes.iter()
+            .filter(|(changed, _)| *changed > revision)
+            .map(|(_, change)| *change)
+            .collect())
     }
 
     pub fn get_block(&mut self, x: usize, y: usize, z: usize) -> Block {
-        let pos = x + (self.x as usize * z) + ((self.z as usize * self.x as usize)  * y);
-        self.blocks[pos as usize].into()
-    }
-
-    // pub fn get_gzipped(&mut self) -> &[u8] {
-    //     let current_time: u64 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-    //     if current_time > (self.last_modified + 300) {
-    //         let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
-    //         encoder.write(&(self.blocks.len() as u32).to_be_bytes()).unwrap();
-    //         encoder.write_all(self.blocks.as_slice()).unwrap();
-    //         let compressed = encoder.finish().expect("Failed to compress data");
-    //         let c_clone = compressed.clone();
-    //         self.gzipped = c_clone;
-    //         self.last_modified = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-    //     } else {}
-    //     &self.gzipped.as_slice()
-    // }
+        let pos = x + (self.x * z) + ((self.z * self.x)  * y);
+        self.blocks[pos].into()
+    }
 }
 
 struct CreatedBy {
+    #[allow(dead_code)]
     service: String,
     username: String,
 }
 
-struct MapGenerator {
-    service: String,
-    username: String,
+/// Describes how a map was generated
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct MapGenerator {
+    /// The software that generated the map
+    pub service: String,
+    /// Name of the generator used
+    pub name: String,
+    /// The seed the map was generated from, for generators that use one
+    pub seed: Option<i64>,
 }
 
-struct Spawn {
-    x: usize,
-    y: usize,
-    z: usize,
-    // Heading
-    h: u8,
-    // Pitch
-    p: u8,
+impl MapGenerator {
+    /// Marks the generator written after the blocks in a CRS file
+    const MAGIC: [u8; 4] = *b"GNRT";
+
+    pub fn new(name: &str, seed: Option<i64>) -> Self {
+        Self {
+            service: "Classic-RS".to_string(),
+            name: name.to_string(),
+            seed,
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MapGenerator::MAGIC.to_vec();
+        bytes.push(self.seed.is_some() as u8);
+        bytes.extend_from_slice(&self.seed.unwrap_or(0).to_be_bytes());
+        for text in [&self.service, &self.name] {
+            let text = &text.as_bytes()[..text.len().min(u8::MAX as usize)];
+            bytes.push(text.len() as u8);
+            bytes.extend_from_slice(text);
+        }
+        bytes
+    }
+
+    /// Read a generator written by [MapGenerator::to_bytes] along with how many bytes it took up
+    fn from_bytes(bytes: &[u8]) -> Option<(Self, usize)> {
+        if bytes.len() < 13 || bytes[..4] != MapGenerator::MAGIC {
+            return None;
+        }
+        let seed = i64::from_be_bytes(bytes[5..13].try_into().ok()?);
+        let seed = if bytes[4] == 1 { Some(seed) } else { None };
+        let mut position = 13;
+        let mut text = || {
+            let length = *bytes.get(position)? as usize;
+            let text = bytes.get(position + 1..position + 1 + length)?;
+            position += 1 + length;
+            Some(String::from_utf8_lossy(text).into_owned())
+        };
+        let service = text()?;
+        let name = text()?;
+        Some((Self { service, name, seed }, position))
+    }
+}
+
+/// Where players are placed when they enter a world, in the fixed point units players move in,
+/// 32 to a block
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Spawn {
+    pub x: i16,
+    pub y: i16,
+    pub z: i16,
+    /// Heading
+    pub h: u8,
+    /// Pitch
+    pub p: u8,
+}
+
+impl Spawn {
+    /// Marks the spawn written after the blocks in CRS files from before the header held it
+    const MAGIC: [u8; 4] = *b"SPWN";
+    const LENGTH: usize = 12;
+
+    /// A spawn in the middle of a world, just above half its height
+    pub fn centre(x: usize, y: usize, z: usize) -> Self {
+        Self {
+            x: (((x / 2) * 32) + 16) as i16,
+            y: (((y / 2) + 2) * 32) as i16,
+            z: (((z / 2) * 32) + 16) as i16,
+            h: 0,
+            p: 0,
+        }
+    }
+
+    /// Read a spawn saved after the blocks of an old CRS file, None if `bytes` doesn't hold one
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < Spawn::LENGTH || bytes[..4] != Spawn::MAGIC {
+            return None;
+        }
+        Some(Self {
+            x: i16::from_be_bytes([bytes[4], bytes[5]]),
+            y: i16::from_be_bytes([bytes[6], bytes[7]]),
+            z: i16::from_be_bytes([bytes[8], bytes[9]]),
+            h: bytes[10],
+            p: bytes[11],
+        })
+    }
 }
\ No newline at end of file
@@ -0,0 +1,84 @@
+//! # Explosions
+//! TNT, off until [ClassicWorld::set_tnt] turns it on for a world.
+//!
+//! TNT is lit when lava touches it or by [ClassicWorld::prime], then explodes [FUSE] ticks later.
+//! An explosion clears every block within its radius except bedrock and obsidian, see [blast_resistant],
+//! and lights any TNT it reaches with a shorter fuse so explosions chain.
+//! Only the blocks an explosion changes are returned from [ClassicWorld::tick], each once, so a blast costs
+//! a packet per block destroyed however many explosions overlap.
+//! TNT is updated by physics' [Scheduler](super::scheduler::Scheduler) but works with physics on or off
+
+use super::{Block, ClassicWorld};
+use super::scheduler::Scheduler;
+
+/// Ticks between TNT being lit and exploding, 2 seconds
+pub const FUSE: u64 = 40;
+/// Fewest ticks before TNT lit by another explosion goes off, it goes off at random before twice this
+pub const CHAIN_FUSE: u64 = 10;
+/// Biggest radius explosions can have, past this one explosion changes tens of thousands of blocks
+pub const MAX_RADIUS: usize = 16;
+
+/// Whether `block` survives explosions
+pub fn blast_resistant(block: Block) -> bool {
+    matches!(block, Block::Bedrock | Block::Obsidian)
+}
+
+impl ClassicWorld {
+    /// Radius of explosions, None while TNT is off
+    pub fn tnt_radius(&self) -> Option<usize> {
+        self.physics.tnt
+    }
+
+    /// Turn TNT on with explosions reaching `radius` blocks, up to [MAX_RADIUS], or off with None.
+    /// TNT already lit when it's turned off doesn't go off
+    pub fn set_tnt(&mut self, radius: Option<usize>) {
+        self.physics.tnt = radius.map(|r| r.min(MAX_RADIUS));
+        if !self.physics_active() {
+            self.physics.scheduler = Scheduler::default();
+        }
+    }
+
+    /// Light the TNT at `x`, `y`, `z`, returning false if there's no TNT there, it's outside the world or TNT is off
+    pub fn prime(&mut self, x: usize, y: usize, z: usize) -> bool {
+        if x >= self.x || y >= self.y || z >= self.z {
+            return false;
+        }
+        let index = x + self.x * (z + self.z * y);
+        if self.physics.tnt.is_none() || Block::from(self.blocks[index]) != Block::TNT {
+            return false;
+        }
+        self.light_fuse(index, FUSE);
+        true
+    }
+
+    /// Schedule the TNT at `index` to explode in `fuse` ticks, unless it's lit already
+    pub(super) fn light_fuse(&mut self, index: usize, fuse: u64) {
+        if self.physics.tnt.is_some() {
+            self.physics.scheduler.schedule(index, fuse);
+        }
+    }
+
+    /// Blow up the TNT at `index`
+    pub(super) fn explode(&mut self, index: usize, changes: &mut Vec<(usize, usize, usize, Block)>) {
+        let radius = match self.physics.tnt {
+            Some(radius) => radius,
+            None => return,
+        };
+        self.change(index, Block::Air, changes);
+        let [x, y, z] = self.position(index);
+        for hit in self.cube(index, radius) {
+            let [hx, hy, hz] = self.position(hit);
+            let distance = hx.abs_diff(x).pow(2) + hy.abs_diff(y).pow(2) + hz.abs_diff(z).pow(2);
+            match Block::from(self.blocks[hit]) {
+                _ if distance > radius * radius => {}
+                Block::Air => {}
+                Block::TNT => {
+                    let fuse = CHAIN_FUSE + self.physics.random.next_int(CHAIN_FUSE as i32) as u64;
+                    self.light_fuse(hit, fuse);
+                }
+                block if blast_resistant(block) => {}
+                _ => self.change(hit, Block::Air, changes),
+            }
+        }
+    }
+}
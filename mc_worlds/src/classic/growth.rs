@@ -0,0 +1,93 @@
+//! # Growth
+//! Random block ticks, which slowly change the world the way Classic does while physics is on.
+//!
+//! Every tick [ClassicWorld::random_tick] picks [RANDOM_TICKS] blocks at random for every 16 by 16 by 16 of the world.
+//! Dirt in the light turns to grass and grass in shadow back to dirt, see [light](super::light).
+//! Saplings grow into trees, one random tick in [SAPLING_CHANCE] if there's room for the tree.
+//! Flowers and saplings need grass or dirt under them and light, mushrooms need stone, cobblestone
+//! or gravel under them and shadow, otherwise a random tick breaks them.
+//! Physics also breaks them as soon as the block under them changes to one they can't stand on
+
+use super::{Block, ClassicWorld};
+use crate::generation::tree;
+
+/// Blocks picked each tick for every [SECTION_VOLUME] blocks of the world
+pub const RANDOM_TICKS: usize = 3;
+/// Volume of a 16 by 16 by 16 section
+pub const SECTION_VOLUME: usize = 16 * 16 * 16;
+/// A sapling grows on one in this many of its random ticks
+pub const SAPLING_CHANCE: i32 = 5;
+
+/// Whether `block` is a sapling, flower or mushroom
+pub fn is_plant(block: Block) -> bool {
+    matches!(block, Block::Sapling | Block::Dandelion | Block::Rose | Block::BrownMushroom | Block::RedMushroom)
+}
+
+/// Whether `plant` can stand on `below`, light aside. Anything that isn't a plant can stand anywhere
+pub fn planted_on(plant: Block, below: Block) -> bool {
+    match plant {
+        Block::Sapling | Block::Dandelion | Block::Rose => matches!(below, Block::GrassBlock | Block::Dirt),
+        Block::BrownMushroom | Block::RedMushroom => matches!(below, Block::Stone | Block::Cobblestone | Block::Gravel),
+        _ => true,
+    }
+}
+
+impl ClassicWorld {
+    /// Run a tick of random block ticks, returning every block they changed in the order they changed.
+    /// Does nothing while physics is off
+    pub fn random_tick(&mut self) -> Vec<(usize, usize, usize, Block)> {
+        let mut changes = vec![];
+        if !self.physics_enabled() {
+            return changes;
+        }
+        let count = (self.blocks.len() * RANDOM_TICKS).div_ceil(SECTION_VOLUME);
+        for _ in 0..count {
+            let x = self.physics.random.next_int(self.x as i32) as usize;
+            let y = self.physics.random.next_int(self.y as i32) as usize;
+            let z = self.physics.random.next_int(self.z as i32) as usize;
+            changes.extend(self.random_tick_at(x, y, z));
+        }
+        changes
+    }
+
+    /// Give the block at `x`, `y`, `z` a random tick as if it had been picked by [ClassicWorld::random_tick]
+    pub fn random_tick_at(&mut self, x: usize, y: usize, z: usize) -> Vec<(usize, usize, usize, Block)> {
+        let mut changes = vec![];
+        let lit_above = self.is_lit(x, y + 1, z);
+        match self.get_block(x, y, z) {
+            Block::Dirt if lit_above => changes.push(self.replace_block(x, y, z, Block::GrassBlock)),
+            Block::GrassBlock if !lit_above => changes.push(self.replace_block(x, y, z, Block::Dirt)),
+            plant if is_plant(plant) => {
+                let below = if y > 0 { self.get_block(x, y - 1, z) } else { Block::Air };
+                let mushroom = matches!(plant, Block::BrownMushroom | Block::RedMushroom);
+                if !planted_on(plant, below) || self.is_lit(x, y, z) == mushroom {
+                    changes.push(self.replace_block(x, y, z, Block::Air));
+                } else if plant == Block::Sapling && self.physics.random.next_int(SAPLING_CHANCE) == 0 {
+                    self.grow_tree(x, y, z, &mut changes);
+                }
+            }
+            _ => {}
+        }
+        changes
+    }
+
+    /// Grow the sapling at `x`, `y`, `z` into a tree if there's room
+    fn grow_tree(&mut self, x: usize, y: usize, z: usize, changes: &mut Vec<(usize, usize, usize, Block)>) {
+        let height = 5 + self.physics.random.next_int(3);
+        let (x, y, z) = (x as i32, y as i32, z as i32);
+        let size = [self.x as i32, self.y as i32, self.z as i32];
+        let fits = tree::fits(x, y, z, height, |xx, yy, zz| {
+            if xx < 0 || yy < 0 || zz < 0 || xx >= size[0] || yy >= size[1] || zz >= size[2] {
+                return false;
+            }
+            let index = xx as usize + self.x * (zz as usize + self.z * yy as usize);
+            (xx, yy, zz) == (x, y, z) || Block::from(self.blocks[index]) == Block::Air
+        });
+        if !fits {
+            return;
+        }
+        for ([x, y, z], block) in tree::blocks(&mut self.physics.random, x, y, z, height) {
+            changes.push(self.replace_block(x as usize, y as usize, z as usize, block));
+        }
+    }
+}
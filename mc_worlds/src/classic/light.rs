@@ -0,0 +1,51 @@
+//! # Light
+//! Light as Classic has it, from the sky straight down each column with nothing spreading sideways.
+//!
+//! Every column keeps the height just above its highest block that stops light, see [blocks_light].
+//! Blocks at or above that height are lit and everything below is in shadow.
+//! The heights are worked out when a world is made and kept up to date as blocks change
+
+use super::{Block, ClassicWorld};
+
+/// Whether light stops at `block`. Light passes through air, glass, leaves and plants
+pub fn blocks_light(block: Block) -> bool {
+    !matches!(block, Block::Air | Block::Glass | Block::Leaves | Block::Sapling | Block::Dandelion | Block::Rose |
+        Block::BrownMushroom | Block::RedMushroom)
+}
+
+impl ClassicWorld {
+    /// The lowest lit y of the column at `x`, `z`, 0 if light reaches the bottom of the world
+    pub fn light_height(&self, x: usize, z: usize) -> usize {
+        self.light[x + self.x * z] as usize
+    }
+
+    /// Whether light from the sky reaches `x`, `y`, `z`. Anywhere above the world is lit
+    pub fn is_lit(&self, x: usize, y: usize, z: usize) -> bool {
+        y >= self.light_height(x, z)
+    }
+
+    /// Work out the light height of every column
+    pub(super) fn light_columns(&mut self) {
+        self.light = (0..self.x * self.z).map(|column| self.column_height(column, self.y)).collect();
+    }
+
+    /// Keep the light height of the column up to date after the block at `index` changed from `old` to `new`
+    pub(super) fn light_changed(&mut self, index: usize, old: Block, new: Block) {
+        let layer = self.x * self.z;
+        let (column, y) = (index % layer, index / layer);
+        let height = self.light[column] as usize;
+        if blocks_light(new) && y + 1 > height {
+            self.light[column] = (y + 1) as u32;
+        } else if !blocks_light(new) && blocks_light(old) && y + 1 == height {
+            self.light[column] = self.column_height(column, y);
+        }
+    }
+
+    /// Height just above the highest block in `column` below `top` that stops light
+    fn column_height(&self, column: usize, top: usize) -> u32 {
+        let layer = self.x * self.z;
+        (0..top).rev()
+            .find(|y| blocks_light(Block::from(self.blocks[column + y * layer])))
+            .map_or(0, |y| (y + 1) as u32)
+    }
+}
@@ -0,0 +1,293 @@
+//! # Physics
+//! Blocks that move on their own, as in Classic.
+//!
+//! Physics runs in ticks of [TICK], see [ClassicWorld::tick]. Changing a block wakes it and the blocks next to it,
+//! queueing them on a [Scheduler] to be updated after a delay that depends on the block, see [delay].
+//! Updating a block can change others, which wakes the blocks around those in turn.
+//!
+//! Water and lava spread into the air below and beside them, water every [WATER_DELAY] ticks and lava
+//! every [LAVA_DELAY]. Lava that meets water hardens, a lava source to stone and flowing lava to cobblestone.
+//! Sponges soak up water within [SPONGE_RADIUS] blocks and keep it from flowing back until they're removed.
+//! Sand and gravel fall a block every [FALL_DELAY] ticks through air and fluids until they land.
+//! Plants break when the block under them changes to one they can't stand on, see [growth](super::growth).
+//! Physics is off until [ClassicWorld::set_physics] turns it on.
+//! TNT is updated the same way but has a switch of its own, see [explosion](super::explosion)
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::{Block, ClassicWorld};
+use super::explosion::FUSE;
+use super::growth::{is_plant, planted_on};
+use super::scheduler::Scheduler;
+use crate::generation::random::JavaRandom;
+
+/// Time between ticks, 20 a second like Classic
+pub const TICK: Duration = Duration::from_millis(50);
+/// Ticks before woken water spreads
+pub const WATER_DELAY: u64 = 5;
+/// Ticks before woken lava spreads
+pub const LAVA_DELAY: u64 = 30;
+/// Ticks before a woken block of sand or gravel falls a block
+pub const FALL_DELAY: u64 = 1;
+/// How far from a sponge water is soaked up, in each direction
+pub const SPONGE_RADIUS: usize = 2;
+/// Most blocks updated in one tick, the rest wait for the next so a flood can't hold up the server
+pub const MAX_UPDATES: usize = 4096;
+
+/// Whether physics and TNT are on and the blocks waiting to be updated
+#[derive(Debug)]
+pub(super) struct Physics {
+    enabled: bool,
+    /// Radius of explosions, None while TNT is off
+    pub(super) tnt: Option<usize>,
+    pub(super) scheduler: Scheduler,
+    /// Picks the blocks given random ticks, how saplings grow and chained fuses
+    pub(super) random: JavaRandom,
+}
+
+impl Default for Physics {
+    fn default() -> Self {
+        let seed = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |t| t.as_nanos() as i64);
+        Self { enabled: false, tnt: None, scheduler: Scheduler::default(), random: JavaRandom::new(seed) }
+    }
+}
+
+/// Ticks after being woken that a block is updated, None for blocks physics leaves alone
+pub fn delay(block: Block) -> Option<u64> {
+    match block {
+        block if is_water(block) => Some(WATER_DELAY),
+        block if is_lava(block) => Some(LAVA_DELAY),
+        Block::Sponge => Some(1),
+        Block::Sand | Block::Gravel => Some(FALL_DELAY),
+        block if is_plant(block) => Some(1),
+        _ => None,
+    }
+}
+
+fn is_water(block: Block) -> bool {
+    matches!(block, Block::FlowingWater | Block::StationaryWater)
+}
+
+fn is_lava(block: Block) -> bool {
+    matches!(block, Block::FlowingLava | Block::StationaryLava)
+}
+
+/// Whether falling blocks drop through `block`
+fn can_fall_into(block: Block) -> bool {
+    block == Block::Air || is_water(block) || is_lava(block)
+}
+
+/// What lava turns into when it meets water
+fn hardened(lava: Block) -> Block {
+    if lava == Block::StationaryLava { Block::Stone } else { Block::Cobblestone }
+}
+
+impl ClassicWorld {
+    pub fn physics_enabled(&self) -> bool {
+        self.physics.enabled
+    }
+
+    /// Whether anything is updated when blocks change, physics or TNT
+    pub(super) fn physics_active(&self) -> bool {
+        self.physics.enabled || self.physics.tnt.is_some()
+    }
+
+    /// Turn physics on or off. Turning it on wakes every flowing fluid and unsupported block of sand or gravel
+    /// so anything left moving when it was turned off carries on, turning it off leaves them where they are
+    pub fn set_physics(&mut self, enabled: bool) {
+        if enabled == self.physics.enabled {
+            return;
+        }
+        self.physics.enabled = enabled;
+        if !self.physics_active() {
+            self.physics.scheduler = Scheduler::default();
+        }
+        if enabled {
+            for index in 0..self.blocks.len() {
+                if self.unsettled(index) {
+                    self.wake(index);
+                }
+            }
+        }
+    }
+
+    /// Number of blocks waiting for physics to update them
+    pub fn pending_updates(&self) -> usize {
+        self.physics.scheduler.len()
+    }
+
+    /// Queue the block at `x`, `y`, `z` to be updated `delay` ticks from now, as if something next to it changed.
+    /// Does nothing while physics is off or if the block is outside the world
+    pub fn schedule_update(&mut self, x: usize, y: usize, z: usize, delay: u64) {
+        if self.physics.enabled && x < self.x && y < self.y && z < self.z {
+            self.physics.scheduler.schedule(x + self.x * (z + self.z * y), delay);
+        }
+    }
+
+    /// Run a tick of physics and TNT, returning every block they changed in the order they changed
+    pub fn tick(&mut self) -> Vec<(usize, usize, usize, Block)> {
+        let mut changes = vec![];
+        if !self.physics_active() {
+            return changes;
+        }
+        self.physics.scheduler.advance();
+        for index in self.physics.scheduler.take_due(MAX_UPDATES) {
+            self.update(index, &mut changes);
+        }
+        changes
+    }
+
+    /// Called with every block that changes while physics or TNT is on, `old` is what it was before
+    pub(super) fn block_changed(&mut self, index: usize, old: Block, new: Block) {
+        if old == Block::Sponge && new != Block::Sponge {
+            // Water held back by the sponge can flow in again
+            for neighbour in self.cube(index, SPONGE_RADIUS + 1) {
+                self.wake(neighbour);
+            }
+        }
+        self.wake(index);
+        for neighbour in self.neighbours(index, true) {
+            self.wake(neighbour);
+        }
+    }
+
+    /// Schedule a block to be updated after its [delay], or light TNT that lava has reached
+    fn wake(&mut self, index: usize) {
+        match Block::from(self.blocks[index]) {
+            Block::TNT => {
+                if self.neighbours(index, true).iter().any(|n| is_lava(Block::from(self.blocks[*n]))) {
+                    self.light_fuse(index, FUSE);
+                }
+            }
+            block => match delay(block) {
+                Some(delay) if self.physics.enabled => self.physics.scheduler.schedule(index, delay),
+                _ => {}
+            },
+        }
+    }
+
+    /// Whether a block would change if it were updated without anything around it changing first
+    fn unsettled(&self, index: usize) -> bool {
+        match Block::from(self.blocks[index]) {
+            Block::FlowingWater | Block::FlowingLava => true,
+            Block::Sand | Block::Gravel => index >= self.x * self.z &&
+                can_fall_into(Block::from(self.blocks[index - self.x * self.z])),
+            _ => false,
+        }
+    }
+
+    fn update(&mut self, index: usize, changes: &mut Vec<(usize, usize, usize, Block)>) {
+        let block = Block::from(self.blocks[index]);
+        if block == Block::TNT {
+            self.explode(index, changes);
+            return;
+        }
+        if !self.physics.enabled {
+            return;
+        }
+        match block {
+            block if is_water(block) => {
+                for neighbour in self.neighbours(index, true) {
+                    let block = Block::from(self.blocks[neighbour]);
+                    if is_lava(block) {
+                        self.change(neighbour, hardened(block), changes);
+                    }
+                }
+                for neighbour in self.neighbours(index, false) {
+                    if Block::from(self.blocks[neighbour]) == Block::Air && !self.near_sponge(neighbour) {
+                        self.change(neighbour, Block::FlowingWater, changes);
+                    }
+                }
+            }
+            block if is_lava(block) => {
+                if self.neighbours(index, true).iter().any(|n| is_water(Block::from(self.blocks[*n]))) {
+                    self.change(index, hardened(block), changes);
+                    return;
+                }
+                for neighbour in self.neighbours(index, false) {
+                    if Block::from(self.blocks[neighbour]) == Block::Air {
+                        self.change(neighbour, Block::FlowingLava, changes);
+                    }
+                }
+            }
+            Block::Sponge => {
+                for soaked in self.cube(index, SPONGE_RADIUS) {
+                    if is_water(Block::from(self.blocks[soaked])) {
+                        self.change(soaked, Block::Air, changes);
+                    }
+                }
+            }
+            block @ Block::Sand | block @ Block::Gravel => {
+                let below = match index.checked_sub(self.x * self.z) {
+                    Some(below) if can_fall_into(Block::from(self.blocks[below])) => below,
+                    _ => return,
+                };
+                // Falling into a fluid washes it away, the fluid around flows back in once woken.
+                // Landing first so the block is woken with its own delay, not the fluid's
+                self.change(below, block, changes);
+                self.change(index, Block::Air, changes);
+            }
+            plant if is_plant(plant) => {
+                let below = index.checked_sub(self.x * self.z).map_or(Block::Air, |below| Block::from(self.blocks[below]));
+                if !planted_on(plant, below) {
+                    self.change(index, Block::Air, changes);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub(super) fn change(&mut self, index: usize, block: Block, changes: &mut Vec<(usize, usize, usize, Block)>) {
+        let [x, y, z] = self.position(index);
+        changes.push(self.replace_block(x, y, z, block));
+    }
+
+    pub(super) fn position(&self, index: usize) -> [usize; 3] {
+        [index % self.x, index / (self.x * self.z), (index / self.x) % self.z]
+    }
+
+    /// Blocks next to `index` inside the world, below and beside it, and above it if `up` is set
+    fn neighbours(&self, index: usize, up: bool) -> Vec<usize> {
+        let [x, y, z] = self.position(index);
+        let layer = self.x * self.z;
+        let mut neighbours = Vec::with_capacity(6);
+        if y > 0 {
+            neighbours.push(index - layer);
+        }
+        if x > 0 {
+            neighbours.push(index - 1);
+        }
+        if x + 1 < self.x {
+            neighbours.push(index + 1);
+        }
+        if z > 0 {
+            neighbours.push(index - self.x);
+        }
+        if z + 1 < self.z {
+            neighbours.push(index + self.x);
+        }
+        if up && y + 1 < self.y {
+            neighbours.push(index + layer);
+        }
+        neighbours
+    }
+
+    /// Blocks inside the world at most `radius` from `index` along each axis
+    pub(super) fn cube(&self, index: usize, radius: usize) -> Vec<usize> {
+        let [x, y, z] = self.position(index);
+        let range = |centre: usize, size: usize| centre.saturating_sub(radius)..(centre + radius + 1).min(size);
+        let mut blocks = vec![];
+        for by in range(y, self.y) {
+            for bz in range(z, self.z) {
+                for bx in range(x, self.x) {
+                    blocks.push(bx + self.x * (bz + self.z * by));
+                }
+            }
+        }
+        blocks
+    }
+
+    fn near_sponge(&self, index: usize) -> bool {
+        self.cube(index, SPONGE_RADIUS).into_iter().any(|i| Block::from(self.blocks[i]) == Block::Sponge)
+    }
+}
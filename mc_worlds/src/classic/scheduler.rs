@@ -0,0 +1,69 @@
+//! # Scheduler
+//! Block updates waiting for a later tick, shared by every physics rule.
+//!
+//! Blocks are queued by their index with a delay in ticks. A block queued again before it's updated
+//! keeps its first slot, so a block woken by several changes at once is only updated once
+
+use std::collections::{BTreeMap, HashSet};
+
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    tick: u64,
+    /// Indices of the blocks to update, by the tick they're due
+    due: BTreeMap<u64, Vec<usize>>,
+    /// Every block in `due`
+    scheduled: HashSet<usize>,
+}
+
+impl Scheduler {
+    /// Ticks run since the scheduler was made
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Move on to the next tick
+    pub fn advance(&mut self) {
+        self.tick += 1;
+    }
+
+    /// Queue the block at `index` to be updated `delay` ticks from now, unless it already is
+    pub fn schedule(&mut self, index: usize, delay: u64) {
+        if self.scheduled.insert(index) {
+            self.due.entry(self.tick + delay).or_default().push(index);
+        }
+    }
+
+    pub fn is_scheduled(&self, index: usize) -> bool {
+        self.scheduled.contains(&index)
+    }
+
+    /// Number of blocks waiting to be updated
+    pub fn len(&self) -> usize {
+        self.scheduled.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scheduled.is_empty()
+    }
+
+    /// Take up to `limit` blocks due by now, earliest first, leaving the rest for later ticks
+    pub fn take_due(&mut self, limit: usize) -> Vec<usize> {
+        let mut taken = vec![];
+        while taken.len() < limit {
+            let mut entry = match self.due.first_entry() {
+                Some(entry) if *entry.key() <= self.tick => entry,
+                _ => break,
+            };
+            let blocks = entry.get_mut();
+            let rest = blocks.len().saturating_sub(limit - taken.len());
+            taken.extend(blocks.drain(rest..));
+            if blocks.is_empty() {
+                entry.remove();
+            }
+        }
+        for index in &taken {
+            self.scheduled.remove(index);
+        }
+        taken
+    }
+}
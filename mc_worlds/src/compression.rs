@@ -0,0 +1,85 @@
+//! # Compression
+//! Gzipping the level sent to clients in LevelDataChunk packets: the block count as a big endian int
+//! followed by the blocks. Slabs of layers are deflated in parallel and joined into a single gzip member,
+//! each slab ends on a byte boundary with a sync flush so the pieces can be concatenated
+
+use flate2::{Compress, Compression, Crc, FlushCompress, Status};
+use rayon::prelude::*;
+
+use crate::progress::{Progress, Stage};
+
+/// Smallest slab deflated on its own, smaller slabs compress worse for little gain
+const MIN_SLAB: usize = 1 << 20;
+/// A gzip header with no name, comment or timestamp
+const HEADER: [u8; 10] = [0x1F, 0x8B, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF];
+
+/// Gzip the level stream for `blocks`, the same bytes [gzip_level_sequential] makes once decompressed.
+/// `layer` is the size of a layer of the map, slabs are cut on layer boundaries
+pub fn gzip_level(blocks: &[u8], layer: usize, progress: &Progress) -> Vec<u8> {
+    let layer = layer.max(1);
+    let slab = (MIN_SLAB / layer).max(1) * layer;
+    let slabs: Vec<&[u8]> = blocks.chunks(slab).collect();
+    progress.start(Stage::Compressing, slabs.len());
+
+    let prefix = (blocks.len() as u32).to_be_bytes();
+    let last = slabs.len();
+    let pieces: Vec<(Vec<u8>, Crc)> = std::iter::once(&prefix[..]).chain(slabs).enumerate()
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|(i, data)| {
+            let piece = deflate(data, i == last);
+            if i > 0 {
+                progress.advance(1);
+            }
+            piece
+        })
+        .collect();
+
+    let size = pieces.iter().map(|(piece, _)| piece.len()).sum::<usize>();
+    let mut gzipped = Vec::with_capacity(HEADER.len() + size + 8);
+    gzipped.extend_from_slice(&HEADER);
+    let mut crc = Crc::new();
+    for (piece, piece_crc) in &pieces {
+        gzipped.extend_from_slice(piece);
+        crc.combine(piece_crc);
+    }
+    gzipped.extend_from_slice(&crc.sum().to_le_bytes());
+    gzipped.extend_from_slice(&crc.amount().to_le_bytes());
+    gzipped
+}
+
+/// Gzip the level stream for `blocks` on the current thread
+pub fn gzip_level_sequential(blocks: &[u8]) -> Vec<u8> {
+    use std::io::Write;
+    use flate2::write::GzEncoder;
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+    encoder.write_all(&(blocks.len() as u32).to_be_bytes()).unwrap();
+    encoder.write_all(blocks).unwrap();
+    encoder.finish().expect("Failed to compress data")
+}
+
+/// Raw deflate `data`, ending the stream if it is the `last` piece and otherwise with a sync flush
+fn deflate(data: &[u8], last: bool) -> (Vec<u8>, Crc) {
+    let mut crc = Crc::new();
+    crc.update(data);
+
+    let mut compress = Compress::new(Compression::fast(), false);
+    let mut out: Vec<u8> = Vec::with_capacity(data.len() / 4 + 64);
+    let flush = if last { FlushCompress::Finish } else { FlushCompress::Sync };
+    loop {
+        let read = compress.total_in() as usize;
+        let status = compress.compress_vec(&data[read..], &mut out, flush).expect("Failed to compress data");
+        let finished = if last {
+            status == Status::StreamEnd
+        } else {
+            // The flush is complete once everything is read and there was room left over
+            compress.total_in() as usize == data.len() && out.len() < out.capacity()
+        };
+        if finished {
+            break;
+        }
+        out.reserve(out.capacity().max(64));
+    }
+    (out, crc)
+}
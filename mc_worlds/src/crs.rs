@@ -0,0 +1,209 @@
+//! # CRS
+//! The file format worlds are saved in.
+//!
+//! A CRS file starts with a fixed size header describing the world, followed by the gzipped body,
+//! the blocks in the same order as the LevelDataChunk packet followed by optional records such as
+//! the map generator. All numbers are big endian.
+//!
+//! | Bytes | Field |
+//! |-------|-------|
+//! | 4 | Magic, `CRSW` |
+//! | 1 | Version |
+//! | 2 × 3 | Width, height and length |
+//! | 2 × 3, 1 × 2 | Spawn x, y and z in fixed point, then heading and pitch |
+//! | 16 | UUID |
+//! | 8 × 3 | Unix timestamps of when the world was created, last accessed and last modified |
+//! | 4 | Length of the gzipped body |
+//! | 4 | CRC32 of the header before it and the gzipped body |
+//!
+//! Files from before the header was added are the raw blocks followed by the records, their size has to
+//! be known to read them. They can't be mistaken for a new file as the magic starts with a byte higher
+//! than any block id.
+
+use std::convert::TryInto;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use flate2::{Compression, Crc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use uuid::Uuid;
+
+use crate::classic::Spawn;
+use crate::formats::{map_volume, FormatError, MAX_VOLUME};
+
+pub const MAGIC: [u8; 4] = *b"CRSW";
+/// Version written to new files, files without a header count as version 1
+pub const VERSION: u8 = 2;
+pub const HEADER_LENGTH: usize = 67;
+/// Most bytes of records read after the blocks, so a small file can't decompress into as much as it likes
+pub const MAX_RECORDS: usize = 1 << 24;
+
+/// Everything about a world stored uncompressed at the start of a CRS file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    pub version: u8,
+    pub x: usize,
+    pub y: usize,
+    pub z: usize,
+    pub spawn: Spawn,
+    pub uuid: Uuid,
+    pub time_created: u64,
+    pub last_accessed: u64,
+    pub last_modified: u64,
+}
+
+impl Header {
+    /// The number of blocks in the world
+    pub fn volume(&self) -> usize {
+        self.x * self.y * self.z
+    }
+
+    /// The header as written, without the body length and checksum
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LENGTH);
+        bytes.extend_from_slice(&MAGIC);
+        bytes.push(self.version);
+        for size in [self.x, self.y, self.z] {
+            bytes.extend_from_slice(&(size as u16).to_be_bytes());
+        }
+        for position in [self.spawn.x, self.spawn.y, self.spawn.z] {
+            bytes.extend_from_slice(&position.to_be_bytes());
+        }
+        bytes.push(self.spawn.h);
+        bytes.push(self.spawn.p);
+        bytes.extend_from_slice(self.uuid.as_bytes());
+        for time in [self.time_created, self.last_accessed, self.last_modified] {
+            bytes.extend_from_slice(&time.to_be_bytes());
+        }
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8; HEADER_LENGTH]) -> Self {
+        let u16_at = |i: usize| u16::from_be_bytes([bytes[i], bytes[i + 1]]);
+        let u64_at = |i: usize| {
+            let mut time = [0u8; 8];
+            time.copy_from_slice(&bytes[i..i + 8]);
+            u64::from_be_bytes(time)
+        };
+        let mut uuid = [0u8; 16];
+        uuid.copy_from_slice(&bytes[19..35]);
+        Self {
+            version: bytes[4],
+            x: u16_at(5) as usize,
+            y: u16_at(7) as usize,
+            z: u16_at(9) as usize,
+            spawn: Spawn {
+                x: u16_at(11) as i16,
+                y: u16_at(13) as i16,
+                z: u16_at(15) as i16,
+                h: bytes[17],
+                p: bytes[18],
+            },
+            uuid: Uuid::from_bytes(uuid),
+            time_created: u64_at(35),
+            last_accessed: u64_at(43),
+            last_modified: u64_at(51),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CrsError {
+    /// Failed to read or write the file at the path
+    Io(PathBuf, io::Error),
+    /// Has a header from a version of the format this can't read
+    Version(u8),
+    /// The file isn't the length the header says, holds the expected and actual length
+    Length(usize, usize),
+    /// The checksum doesn't match the contents, holds the stored and calculated checksum
+    Checksum(u32, u32),
+    Gzip(io::Error),
+    /// The blocks don't fit the world's size, holds the size and the number of blocks
+    Size([usize; 3], usize),
+    /// Data after the blocks that isn't a known record, holds how many bytes there are
+    Trailing(usize),
+    /// Failed to import the map at the path from another server's format
+    Import(PathBuf, FormatError),
+}
+
+impl fmt::Display for CrsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CrsError::Io(path, e) => write!(f, "Failed to access {}: {}", path.display(), e),
+            CrsError::Version(version) => write!(f, "CRS version {} isn't supported, only version {} is",
+                                                 version, VERSION),
+            CrsError::Length(expected, actual) => write!(f, "File should be {} bytes but is {}, it may be truncated",
+                                                         expected, actual),
+            CrsError::Checksum(stored, actual) => write!(f, "Checksum {:08x} doesn't match the contents ({:08x})",
+                                                         stored, actual),
+            CrsError::Gzip(e) => write!(f, "Failed to decompress world: {}", e),
+            CrsError::Size([x, y, z], blocks) => write!(f, "{} blocks don't fill a {}x{}x{} world", blocks, x, y, z),
+            CrsError::Trailing(length) => write!(f, "{} unrecognised bytes after the blocks, is the world size right?",
+                                                 length),
+            CrsError::Import(path, e) => write!(f, "Failed to import {}: {}", path.display(), e),
+        }
+    }
+}
+
+impl std::error::Error for CrsError {}
+
+/// True if `bytes` start with a CRS header rather than being a file from before headers were added
+pub fn is_crs(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC)
+}
+
+/// Write a CRS file for a world described by `header`, `body` is the blocks followed by any records
+pub fn encode(header: &Header, body: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::with_capacity(body.len() / 16), Compression::default());
+    encoder.write_all(body).expect("Writing to a Vec can't fail");
+    let gzipped = encoder.finish().expect("Writing to a Vec can't fail");
+
+    let mut bytes = header.to_bytes();
+    bytes.reserve(8 + gzipped.len());
+    bytes.extend_from_slice(&(gzipped.len() as u32).to_be_bytes());
+    let mut crc = Crc::new();
+    crc.update(&bytes);
+    crc.update(&gzipped);
+    bytes.extend_from_slice(&crc.sum().to_be_bytes());
+    bytes.extend_from_slice(&gzipped);
+    bytes
+}
+
+/// Read a CRS file, returning its header and body.
+/// The body is at least as long as the blocks, what follows them is any records
+pub fn decode(bytes: &[u8]) -> Result<(Header, Vec<u8>), CrsError> {
+    if bytes.len() < HEADER_LENGTH {
+        return Err(CrsError::Length(HEADER_LENGTH, bytes.len()));
+    }
+    let (header_bytes, gzipped) = bytes.split_at(HEADER_LENGTH);
+    let header = Header::from_bytes(header_bytes.try_into().expect("Split at the header length"));
+    if header.version != VERSION {
+        return Err(CrsError::Version(header.version));
+    }
+    let length = u32::from_be_bytes([bytes[59], bytes[60], bytes[61], bytes[62]]) as usize;
+    if gzipped.len() != length {
+        return Err(CrsError::Length(HEADER_LENGTH + length, bytes.len()));
+    }
+    let stored = u32::from_be_bytes([bytes[63], bytes[64], bytes[65], bytes[66]]);
+    let mut crc = Crc::new();
+    crc.update(&bytes[..63]);
+    crc.update(gzipped);
+    if crc.sum() != stored {
+        return Err(CrsError::Checksum(stored, crc.sum()));
+    }
+
+    // Even the most blocks a file can hold don't fill a world this big
+    let volume = map_volume([header.x, header.y, header.z])
+        .map_err(|_| CrsError::Size([header.x, header.y, header.z], MAX_VOLUME))?;
+    let limit = volume + MAX_RECORDS;
+    let mut body = Vec::with_capacity(volume);
+    GzDecoder::new(gzipped).take(limit as u64 + 1).read_to_end(&mut body).map_err(CrsError::Gzip)?;
+    if body.len() < volume {
+        return Err(CrsError::Size([header.x, header.y, header.z], body.len()));
+    }
+    if body.len() > limit {
+        return Err(CrsError::Trailing(body.len() - volume));
+    }
+    Ok((header, body))
+}
@@ -0,0 +1,185 @@
+//! # CW
+//! The ClassicWorld format defined at https://wiki.vg/ClassicWorld_file_format, used by ClassiCube and MCGalaxy.
+//!
+//! The file is a gzipped NBT compound called ClassicWorld holding the FormatVersion, Name, UUID, the size as
+//! X, Y and Z, who created it and with what, the TimeCreated, LastAccessed and LastModified timestamps, the
+//! Spawn in block coordinates and the BlockArray in the same order as CRS files.
+//! Metadata holds a compound for each piece of software that saved something with the map, the values
+//! in them are kept as strings in the world's metadata
+
+use std::convert::TryInto;
+use nbt::{Blob, Map, Value};
+use uuid::Uuid;
+
+use crate::classic::{ClassicWorld, MapGenerator};
+use crate::crs::{self, Header};
+use super::{FormatError, Remapped, cpe_block, to_classic, check_size, spawn_in_block, spawn_block};
+
+/// Name of the root compound
+const ROOT: &str = "ClassicWorld";
+pub const VERSION: i8 = 1;
+
+/// Read a ClassicWorld map as the world called `name`.
+/// The author is who the map says created it, or `author` if it doesn't say
+pub fn read(bytes: &[u8], name: &str, author: &str) -> Result<(ClassicWorld, Remapped), FormatError> {
+    if !bytes.starts_with(&[0x1F, 0x8B]) {
+        return Err(FormatError::Identifier);
+    }
+    let blob = Blob::from_gzip_reader(&mut &bytes[..])?;
+    match blob.get("FormatVersion") {
+        Some(Value::Byte(VERSION)) => {}
+        Some(Value::Byte(version)) => return Err(FormatError::Version(*version as u32)),
+        _ => return Err(FormatError::Missing("FormatVersion")),
+    }
+    let size = |name: &'static str| match blob.get(name) {
+        Some(Value::Short(size)) => Ok(*size as u16 as usize),
+        _ => Err(FormatError::Missing(name)),
+    };
+    let (x, y, z) = (size("X")?, size("Y")?, size("Z")?);
+    let mut blocks: Vec<u8> = match blob.get("BlockArray") {
+        Some(Value::ByteArray(blocks)) => blocks.iter().map(|b| *b as u8).collect(),
+        _ => return Err(FormatError::Missing("BlockArray")),
+    };
+    if blocks.len() != x * y * z {
+        return Err(FormatError::Size([x, y, z]));
+    }
+    let remapped = to_classic(&mut blocks, cpe_block);
+
+    let spawn = match blob.get("Spawn") {
+        Some(Value::Compound(spawn)) => {
+            let short = |name: &str| match spawn.get(name) {
+                Some(Value::Short(value)) => *value,
+                _ => 0,
+            };
+            let byte = |name: &str| match spawn.get(name) {
+                Some(Value::Byte(value)) => *value as u8,
+                _ => 0,
+            };
+            spawn_in_block(short("X"), short("Y"), short("Z"), byte("H"), byte("P"))
+        }
+        _ => return Err(FormatError::Missing("Spawn")),
+    };
+    let uuid = match blob.get("UUID") {
+        Some(Value::ByteArray(uuid)) if uuid.len() == 16 => {
+            let uuid: Vec<u8> = uuid.iter().map(|b| *b as u8).collect();
+            Uuid::from_bytes(uuid.as_slice().try_into().expect("Checked the length"))
+        }
+        _ => Uuid::new_v4(),
+    };
+    let time = |name: &'static str| match blob.get(name) {
+        Some(Value::Long(time)) => (*time).max(0) as u64,
+        _ => 0,
+    };
+    let header = Header {
+        version: crs::VERSION,
+        x,
+        y,
+        z,
+        spawn,
+        uuid,
+        time_created: time("TimeCreated"),
+        last_accessed: time("LastAccessed"),
+        last_modified: time("LastModified"),
+    };
+    let creator = match blob.get("CreatedBy") {
+        Some(Value::Compound(created_by)) => match created_by.get("Username") {
+            Some(Value::String(username)) => Some(username.as_str()),
+            _ => None,
+        },
+        _ => None,
+    };
+    let mut world = ClassicWorld::from_header(name, creator.unwrap_or(author), &header, blocks)
+        .map_err(|_| FormatError::Size([x, y, z]))?;
+
+    if let Some(Value::Compound(generator)) = blob.get("MapGenerator") {
+        let string = |name: &str| match generator.get(name) {
+            Some(Value::String(value)) => value.clone(),
+            _ => String::new(),
+        };
+        world.set_map_generator(Some(MapGenerator {
+            service: string("Software"),
+            name: string("MapGeneratorName"),
+            seed: None,
+        }));
+    }
+    if let Some(Value::Compound(metadata)) = blob.get("Metadata") {
+        for (group, values) in metadata {
+            if let Value::Compound(values) = values {
+                read_metadata(&mut world, group, "", values);
+            }
+        }
+    }
+    Ok((world, remapped))
+}
+
+/// Keep every value in a compound of metadata, the keys of nested compounds are joined with dots
+fn read_metadata(world: &mut ClassicWorld, group: &str, prefix: &str, values: &Map<String, Value>) {
+    for (key, value) in values {
+        let key = format!("{}{}", prefix, key);
+        let value = match value {
+            Value::Compound(values) => {
+                read_metadata(world, group, &format!("{}.", key), values);
+                continue;
+            }
+            Value::String(value) => value.clone(),
+            Value::Byte(value) => value.to_string(),
+            Value::Short(value) => value.to_string(),
+            Value::Int(value) => value.to_string(),
+            Value::Long(value) => value.to_string(),
+            Value::Float(value) => value.to_string(),
+            Value::Double(value) => value.to_string(),
+            _ => continue,
+        };
+        world.metadata_mut().set(group, &key, &value);
+    }
+}
+
+/// Write a world as a ClassicWorld map
+pub fn write(world: &ClassicWorld) -> Result<Vec<u8>, FormatError> {
+    let [x, y, z] = check_size(world)?;
+    let header = world.get_header();
+    let [spawn_x, spawn_y, spawn_z] = spawn_block(header.spawn);
+
+    let mut blob = Blob::named(ROOT);
+    blob.insert("FormatVersion", Value::Byte(VERSION))?;
+    blob.insert("Name", Value::String(world.get_name().to_string()))?;
+    blob.insert("UUID", Value::ByteArray(header.uuid.as_bytes().iter().map(|b| *b as i8).collect()))?;
+    blob.insert("X", Value::Short(x as u16 as i16))?;
+    blob.insert("Y", Value::Short(y as u16 as i16))?;
+    blob.insert("Z", Value::Short(z as u16 as i16))?;
+    if let Some(author) = world.get_author() {
+        let mut created_by = Map::new();
+        created_by.insert("Service".to_string(), Value::String("Classic-RS".to_string()));
+        created_by.insert("Username".to_string(), Value::String(author.to_string()));
+        blob.insert("CreatedBy", Value::Compound(created_by))?;
+    }
+    if let Some(generator) = world.get_map_generator() {
+        let mut map_generator = Map::new();
+        map_generator.insert("Software".to_string(), Value::String(generator.service.clone()));
+        map_generator.insert("MapGeneratorName".to_string(), Value::String(generator.name.clone()));
+        blob.insert("MapGenerator", Value::Compound(map_generator))?;
+    }
+    blob.insert("TimeCreated", Value::Long(header.time_created as i64))?;
+    blob.insert("LastAccessed", Value::Long(header.last_accessed as i64))?;
+    blob.insert("LastModified", Value::Long(header.last_modified as i64))?;
+    let mut spawn = Map::new();
+    for (name, position) in [("X", spawn_x), ("Y", spawn_y), ("Z", spawn_z)] {
+        spawn.insert(name.to_string(), Value::Short(position as i16));
+    }
+    spawn.insert("H".to_string(), Value::Byte(header.spawn.h as i8));
+    spawn.insert("P".to_string(), Value::Byte(header.spawn.p as i8));
+    blob.insert("Spawn", Value::Compound(spawn))?;
+    blob.insert("BlockArray", Value::ByteArray(world.get_blocks().iter().map(|b| *b as i8).collect()))?;
+
+    let mut metadata: Map<String, Value> = Map::new();
+    for (group, key, value) in world.get_metadata().iter() {
+        let group = metadata.entry(group.to_string()).or_insert_with(|| Value::Compound(Map::new()));
+        if let Value::Compound(group) = group {
+            group.insert(key.to_string(), Value::String(value.to_string()));
+        }
+    }
+    blob.insert("Metadata", Value::Compound(metadata))?;
+    let mut bytes = vec![];
+    blob.to_gzip_writer(&mut bytes)?;
+    Ok(bytes)
+}
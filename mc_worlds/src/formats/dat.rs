@@ -0,0 +1,450 @@
+//! # DAT
+//! The level.dat and server_level.dat maps of Minecraft Classic itself.
+//!
+//! The file is gzipped, starting with the magic 0x271BB788 and a version byte, all big endian.
+//! Version 1 then holds the name, creator, creation time in milliseconds, the width, length and height
+//! as shorts, then the blocks in the same order as CRS files.
+//! Version 2 holds a `com.mojang.minecraft.level.Level` written with Java's object serialization,
+//! whose `width`, `height` and `depth` fields are the width, length and height.
+//!
+//! Maps are written as version 1, which every version of Classic that saves version 2 can still load
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use uuid::Uuid;
+
+use crate::classic::{ClassicWorld, Spawn};
+use crate::crs::{self, Header};
+use crate::generation::surface_spawn;
+use super::{FormatError, Remapped, cpe_block, to_classic, check_size, map_volume, spawn_in_block};
+
+pub const MAGIC: u32 = 0x271B_B788;
+/// Version written, the one without Java serialization
+pub const VERSION: u8 = 1;
+/// Class of the serialized level in version 2
+const LEVEL_CLASS: &str = "com.mojang.minecraft.level.Level";
+
+/// Read a Minecraft Classic map as the world called `name`
+pub fn read(bytes: &[u8], name: &str, author: &str) -> Result<(ClassicWorld, Remapped), FormatError> {
+    if !bytes.starts_with(&[0x1F, 0x8B]) {
+        return Err(FormatError::Identifier);
+    }
+    let mut data = vec![];
+    GzDecoder::new(bytes).read_to_end(&mut data)?;
+    let mut reader = data.as_slice();
+    if reader.read_u32::<BigEndian>()? != MAGIC {
+        return Err(FormatError::Identifier);
+    }
+    let (level, creator) = match reader.read_u8()? {
+        1 => read_version_1(reader)?,
+        2 => read_version_2(reader)?,
+        version => return Err(FormatError::Version(version as u32)),
+    };
+    let Level { size: [x, y, z], mut blocks, spawn, created } = level;
+    if blocks.len() != map_volume([x, y, z])? {
+        return Err(FormatError::Size([x, y, z]));
+    }
+    let remapped = to_classic(&mut blocks, cpe_block);
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let header = Header {
+        version: crs::VERSION,
+        x,
+        y,
+        z,
+        spawn: spawn.unwrap_or_else(|| surface_spawn(&blocks, x, y, z)),
+        uuid: Uuid::new_v4(),
+        time_created: created.unwrap_or(now),
+        last_accessed: now,
+        last_modified: now,
+    };
+    let author = creator.as_deref().filter(|c| !c.is_empty()).unwrap_or(author);
+    let world = ClassicWorld::from_header(name, author, &header, blocks)
+        .map_err(|_| FormatError::Size([x, y, z]))?;
+    Ok((world, remapped))
+}
+
+/// What a map holds whichever version it is
+struct Level {
+    size: [usize; 3],
+    blocks: Vec<u8>,
+    spawn: Option<Spawn>,
+    /// Unix timestamp in seconds
+    created: Option<u64>,
+}
+
+fn read_version_1(mut reader: &[u8]) -> Result<(Level, Option<String>), FormatError> {
+    let _name = read_utf(&mut reader)?;
+    let creator = read_utf(&mut reader)?;
+    let created = reader.read_i64::<BigEndian>()?;
+    let x = reader.read_u16::<BigEndian>()? as usize;
+    let z = reader.read_u16::<BigEndian>()? as usize;
+    let y = reader.read_u16::<BigEndian>()? as usize;
+    let volume = map_volume([x, y, z])?;
+    if volume > reader.len() {
+        return Err(FormatError::Truncated);
+    }
+    let blocks = reader[..volume].to_vec();
+    let level = Level { size: [x, y, z], blocks, spawn: None, created: Some(created.max(0) as u64 / 1000) };
+    Ok((level, Some(creator)))
+}
+
+fn read_version_2(reader: &[u8]) -> Result<(Level, Option<String>), FormatError> {
+    let level = match java::Reader::new(reader)?.read()? {
+        java::Value::Object(level) if level.class == LEVEL_CLASS => level,
+        _ => return Err(FormatError::Missing("Level")),
+    };
+    let int = |name: &'static str| level.int(name).ok_or(FormatError::Missing(name));
+    let size = [int("width")?, int("depth")?, int("height")?].map(|size| size.max(0) as usize);
+    let blocks = match level.fields.get("blocks") {
+        Some(java::Value::Bytes(blocks)) => blocks.clone(),
+        _ => return Err(FormatError::Missing("blocks")),
+    };
+    let spawn = match (level.int("xSpawn"), level.int("ySpawn"), level.int("zSpawn")) {
+        (Some(x), Some(y), Some(z)) => {
+            let rotation = match level.fields.get("rotSpawn") {
+                Some(java::Value::Float(rotation)) => *rotation,
+                _ => 0.0,
+            };
+            // The spawn is where the player's feet go
+            Some(spawn_in_block(x as i16, y as i16, z as i16, (rotation * 256.0 / 360.0) as i64 as u8, 0))
+        }
+        _ => None,
+    };
+    let created = level.int("createTime").map(|time| time.max(0) as u64 / 1000);
+    let creator = match level.fields.get("creator") {
+        Some(java::Value::String(creator)) => Some(creator.clone()),
+        _ => None,
+    };
+    Ok((Level { size, blocks, spawn, created }, creator))
+}
+
+/// Read a string written by Java's `DataOutput.writeUTF`, its length as a short then the bytes
+fn read_utf(reader: &mut &[u8]) -> Result<String, FormatError> {
+    let length = reader.read_u16::<BigEndian>()? as usize;
+    if length > reader.len() {
+        return Err(FormatError::Truncated);
+    }
+    let (text, rest) = reader.split_at(length);
+    *reader = rest;
+    Ok(String::from_utf8_lossy(text).into_owned())
+}
+
+fn write_utf(writer: &mut impl Write, text: &str) -> Result<(), FormatError> {
+    let text = &text.as_bytes()[..text.len().min(u16::MAX as usize)];
+    writer.write_u16::<BigEndian>(text.len() as u16)?;
+    writer.write_all(text)?;
+    Ok(())
+}
+
+/// Write a world as a version 1 Minecraft Classic map, which can't hold the spawn
+pub fn write(world: &ClassicWorld) -> Result<Vec<u8>, FormatError> {
+    let [x, y, z] = check_size(world)?;
+    if [x, y, z].iter().any(|s| *s > i16::MAX as usize) {
+        return Err(FormatError::Size([x, y, z]));
+    }
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder.write_u32::<BigEndian>(MAGIC)?;
+    encoder.write_u8(VERSION)?;
+    write_utf(&mut encoder, world.get_name())?;
+    write_utf(&mut encoder, world.get_author().unwrap_or(""))?;
+    encoder.write_i64::<BigEndian>(world.get_header().time_created as i64 * 1000)?;
+    for size in [x, z, y] {
+        encoder.write_u16::<BigEndian>(size as u16)?;
+    }
+    encoder.write_all(world.get_blocks())?;
+    Ok(encoder.finish()?)
+}
+
+/// Just enough of Java's object serialization to read a serialized Level, see
+/// https://docs.oracle.com/javase/8/docs/platform/serialization/spec/protocol.html
+mod java {
+    use std::rc::Rc;
+    use super::*;
+
+    const STREAM_MAGIC: u16 = 0xACED;
+    const STREAM_VERSION: u16 = 5;
+    /// The first handle given to an object in the stream
+    const BASE_HANDLE: u32 = 0x7E_0000;
+
+    const TC_NULL: u8 = 0x70;
+    const TC_REFERENCE: u8 = 0x71;
+    const TC_CLASSDESC: u8 = 0x72;
+    const TC_OBJECT: u8 = 0x73;
+    const TC_STRING: u8 = 0x74;
+    const TC_ARRAY: u8 = 0x75;
+    const TC_CLASS: u8 = 0x76;
+    const TC_BLOCKDATA: u8 = 0x77;
+    const TC_ENDBLOCKDATA: u8 = 0x78;
+    const TC_RESET: u8 = 0x79;
+    const TC_BLOCKDATALONG: u8 = 0x7A;
+    const TC_LONGSTRING: u8 = 0x7C;
+    const TC_ENUM: u8 = 0x7E;
+
+    const SC_WRITE_METHOD: u8 = 0x01;
+    const SC_SERIALIZABLE: u8 = 0x02;
+    const SC_EXTERNALIZABLE: u8 = 0x04;
+    const SC_BLOCK_DATA: u8 = 0x08;
+
+    #[derive(Debug, Clone)]
+    pub enum Value {
+        Null,
+        /// Any integer, char or boolean
+        Int(i64),
+        Float(f64),
+        String(String),
+        /// An array of bytes
+        Bytes(Vec<u8>),
+        /// Any other array, whose elements aren't needed
+        Array,
+        Object(Object),
+        /// Something that isn't needed, such as a class or block data
+        Other,
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct Object {
+        pub class: String,
+        /// Fields of the object's class and every class it extends
+        pub fields: HashMap<String, Value>,
+    }
+
+    impl Object {
+        pub fn int(&self, name: &str) -> Option<i64> {
+            match self.fields.get(name) {
+                Some(Value::Int(value)) => Some(*value),
+                _ => None,
+            }
+        }
+    }
+
+    struct Class {
+        name: String,
+        flags: u8,
+        /// The type code and name of each field
+        fields: Vec<(u8, String)>,
+        super_class: Option<Rc<Class>>,
+    }
+
+    enum Handle {
+        Class(Rc<Class>),
+        Value(Value),
+    }
+
+    pub struct Reader<'a> {
+        bytes: &'a [u8],
+        handles: Vec<Handle>,
+    }
+
+    impl<'a> Reader<'a> {
+        pub fn new(mut bytes: &'a [u8]) -> Result<Self, FormatError> {
+            if bytes.read_u16::<BigEndian>()? != STREAM_MAGIC || bytes.read_u16::<BigEndian>()? != STREAM_VERSION {
+                return Err(FormatError::Corrupt("Java stream"));
+            }
+            Ok(Self { bytes, handles: vec![] })
+        }
+
+        /// Read the next object in the stream
+        pub fn read(&mut self) -> Result<Value, FormatError> {
+            let code = self.bytes.read_u8()?;
+            self.content(code)
+        }
+
+        fn content(&mut self, code: u8) -> Result<Value, FormatError> {
+            match code {
+                TC_NULL => Ok(Value::Null),
+                TC_REFERENCE => match self.reference()? {
+                    Handle::Value(value) => Ok(value.clone()),
+                    Handle::Class(_) => Ok(Value::Other),
+                },
+                TC_STRING => {
+                    let length = self.bytes.read_u16::<BigEndian>()? as usize;
+                    let text = Value::String(self.utf(length)?);
+                    self.handles.push(Handle::Value(text.clone()));
+                    Ok(text)
+                }
+                TC_LONGSTRING => {
+                    let length = self.bytes.read_u64::<BigEndian>()? as usize;
+                    let text = Value::String(self.utf(length)?);
+                    self.handles.push(Handle::Value(text.clone()));
+                    Ok(text)
+                }
+                TC_ARRAY => self.array(),
+                TC_OBJECT => self.object(),
+                TC_CLASS => {
+                    self.class_description()?;
+                    self.handles.push(Handle::Value(Value::Other));
+                    Ok(Value::Other)
+                }
+                TC_ENUM => {
+                    self.class_description()?;
+                    let handle = self.new_handle();
+                    let name = self.read()?;
+                    self.handles[handle] = Handle::Value(name.clone());
+                    Ok(name)
+                }
+                TC_BLOCKDATA => {
+                    let length = self.bytes.read_u8()? as usize;
+                    self.skip(length)?;
+                    Ok(Value::Other)
+                }
+                TC_BLOCKDATALONG => {
+                    let length = self.bytes.read_u32::<BigEndian>()? as usize;
+                    self.skip(length)?;
+                    Ok(Value::Other)
+                }
+                TC_RESET => {
+                    self.handles.clear();
+                    self.read()
+                }
+                _ => Err(FormatError::Corrupt("Java stream")),
+            }
+        }
+
+        fn new_handle(&mut self) -> usize {
+            self.handles.push(Handle::Value(Value::Null));
+            self.handles.len() - 1
+        }
+
+        fn reference(&mut self) -> Result<&Handle, FormatError> {
+            let handle = self.bytes.read_u32::<BigEndian>()?.wrapping_sub(BASE_HANDLE) as usize;
+            self.handles.get(handle).ok_or(FormatError::Corrupt("Java stream"))
+        }
+
+        fn skip(&mut self, length: usize) -> Result<(), FormatError> {
+            if self.bytes.len() < length {
+                return Err(FormatError::Truncated);
+            }
+            self.bytes = &self.bytes[length..];
+            Ok(())
+        }
+
+        fn utf(&mut self, length: usize) -> Result<String, FormatError> {
+            if self.bytes.len() < length {
+                return Err(FormatError::Truncated);
+            }
+            let (text, rest) = self.bytes.split_at(length);
+            self.bytes = rest;
+            Ok(String::from_utf8_lossy(text).into_owned())
+        }
+
+        /// A class description, None for null
+        fn class_description(&mut self) -> Result<Option<Rc<Class>>, FormatError> {
+            match self.bytes.read_u8()? {
+                TC_NULL => Ok(None),
+                TC_REFERENCE => match self.reference()? {
+                    Handle::Class(class) => Ok(Some(class.clone())),
+                    Handle::Value(_) => Err(FormatError::Corrupt("Java stream")),
+                },
+                TC_CLASSDESC => {
+                    let length = self.bytes.read_u16::<BigEndian>()? as usize;
+                    let name = self.utf(length)?;
+                    let _serial_version = self.bytes.read_u64::<BigEndian>()?;
+                    let handle = self.new_handle();
+                    let flags = self.bytes.read_u8()?;
+                    let mut fields = vec![];
+                    for _ in 0..self.bytes.read_u16::<BigEndian>()? {
+                        let code = self.bytes.read_u8()?;
+                        let length = self.bytes.read_u16::<BigEndian>()? as usize;
+                        let field = self.utf(length)?;
+                        if code == b'L' || code == b'[' {
+                            // The field's class name
+                            self.read()?;
+                        }
+                        fields.push((code, field));
+                    }
+                    self.annotation()?;
+                    let super_class = self.class_description()?;
+                    let class = Rc::new(Class { name, flags, fields, super_class });
+                    self.handles[handle] = Handle::Class(class.clone());
+                    Ok(Some(class))
+                }
+                _ => Err(FormatError::Corrupt("Java stream")),
+            }
+        }
+
+        /// Skip what a class wrote with its own serialization method, up to the end marker
+        fn annotation(&mut self) -> Result<(), FormatError> {
+            loop {
+                match self.bytes.read_u8()? {
+                    TC_ENDBLOCKDATA => return Ok(()),
+                    code => self.content(code)?,
+                };
+            }
+        }
+
+        fn array(&mut self) -> Result<Value, FormatError> {
+            let class = self.class_description()?.ok_or(FormatError::Corrupt("Java stream"))?;
+            let handle = self.new_handle();
+            let length = self.bytes.read_i32::<BigEndian>()?.max(0) as usize;
+            let element = class.name.as_bytes().get(1).copied().unwrap_or(b'L');
+            let array = if element == b'B' {
+                let mut bytes = vec![0u8; length.min(self.bytes.len())];
+                self.bytes.read_exact(&mut bytes)?;
+                if bytes.len() < length {
+                    return Err(FormatError::Truncated);
+                }
+                Value::Bytes(bytes)
+            } else {
+                for _ in 0..length {
+                    self.field(element)?;
+                }
+                Value::Array
+            };
+            self.handles[handle] = Handle::Value(array.clone());
+            Ok(array)
+        }
+
+        fn object(&mut self) -> Result<Value, FormatError> {
+            let class = self.class_description()?.ok_or(FormatError::Corrupt("Java stream"))?;
+            let handle = self.new_handle();
+            // Each class's data is written starting from the class furthest up the hierarchy
+            let mut hierarchy = vec![class.clone()];
+            while let Some(super_class) = hierarchy.last().and_then(|c| c.super_class.clone()) {
+                hierarchy.push(super_class);
+            }
+            let mut fields = HashMap::new();
+            for class in hierarchy.iter().rev() {
+                if class.flags & SC_EXTERNALIZABLE != 0 {
+                    if class.flags & SC_BLOCK_DATA == 0 {
+                        return Err(FormatError::Corrupt("Java stream"));
+                    }
+                    self.annotation()?;
+                    continue;
+                }
+                if class.flags & SC_SERIALIZABLE != 0 {
+                    for (code, name) in &class.fields {
+                        let value = self.field(*code)?;
+                        fields.insert(name.clone(), value);
+                    }
+                }
+                if class.flags & SC_WRITE_METHOD != 0 {
+                    self.annotation()?;
+                }
+            }
+            let object = Value::Object(Object { class: class.name.clone(), fields });
+            self.handles[handle] = Handle::Value(object.clone());
+            Ok(object)
+        }
+
+        fn field(&mut self, code: u8) -> Result<Value, FormatError> {
+            Ok(match code {
+                b'B' => Value::Int(self.bytes.read_i8()? as i64),
+                b'C' => Value::Int(self.bytes.read_u16::<BigEndian>()? as i64),
+                b'D' => Value::Float(self.bytes.read_f64::<BigEndian>()?),
+                b'F' => Value::Float(self.bytes.read_f32::<BigEndian>()? as f64),
+                b'I' => Value::Int(self.bytes.read_i32::<BigEndian>()? as i64),
+                b'J' => Value::Int(self.bytes.read_i64::<BigEndian>()?),
+                b'S' => Value::Int(self.bytes.read_i16::<BigEndian>()? as i64),
+                b'Z' => Value::Int(self.bytes.read_u8()? as i64),
+                b'L' | b'[' => self.read()?,
+                _ => return Err(FormatError::Corrupt("Java stream")),
+            })
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! # FCM
+//! fCraft's FCMv3 map format, also read by ClassiCube.
+//!
+//! A 79 byte little endian header holds the size, spawn, timestamps, UUID and an index of the layers
+//! in the file, followed by a raw deflate stream of the metadata then the blocks.
+//! The metadata is a list of group, key and value strings, each prefixed with its length as a short.
+
+use std::convert::TryInto;
+use std::io::{Read, Write};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use uuid::Uuid;
+
+use crate::classic::{ClassicWorld, Metadata, Spawn};
+use crate::crs::{self, Header};
+use super::{FormatError, Remapped, cpe_block, to_classic, check_size, map_volume};
+
+pub const IDENTIFIER: u32 = 0x0FC2_AF40;
+pub const REVISION: u8 = 13;
+const HEADER_LENGTH: usize = 79;
+/// Layer type of the blocks in the layer index
+const BLOCKS_LAYER: u8 = 0;
+
+/// Read an FCMv3 map as the world called `name`, CPE blocks are replaced with their fallbacks
+pub fn read(bytes: &[u8], name: &str, author: &str) -> Result<(ClassicWorld, Remapped), FormatError> {
+    if bytes.len() < 5 || bytes[..4] != IDENTIFIER.to_le_bytes() {
+        return Err(FormatError::Identifier);
+    }
+    if bytes[4] != REVISION {
+        return Err(FormatError::Version(bytes[4] as u32));
+    }
+    if bytes.len() < HEADER_LENGTH {
+        return Err(FormatError::Truncated);
+    }
+    let mut reader = &bytes[5..];
+    let x = reader.read_u16::<LittleEndian>()? as usize;
+    let y = reader.read_u16::<LittleEndian>()? as usize;
+    let z = reader.read_u16::<LittleEndian>()? as usize;
+    let spawn = Spawn {
+        x: reader.read_i32::<LittleEndian>()? as i16,
+        y: reader.read_i32::<LittleEndian>()? as i16,
+        z: reader.read_i32::<LittleEndian>()? as i16,
+        h: reader.read_u8()?,
+        p: reader.read_u8()?,
+    };
+    let last_modified = reader.read_u32::<LittleEndian>()? as u64;
+    let time_created = reader.read_u32::<LittleEndian>()? as u64;
+    let uuid = Uuid::from_bytes(bytes[33..49].try_into().expect("Header is long enough"));
+    // The layer index isn't needed as the blocks always follow the metadata
+    let mut reader = &bytes[75..];
+    let metadata_count = reader.read_u32::<LittleEndian>()?;
+
+    let mut decoder = DeflateDecoder::new(&bytes[HEADER_LENGTH..]);
+    let mut metadata = Metadata::default();
+    for _ in 0..metadata_count {
+        let group = read_string(&mut decoder)?;
+        let key = read_string(&mut decoder)?;
+        let value = read_string(&mut decoder)?;
+        metadata.set(&group, &key, &value);
+    }
+    let mut blocks = vec![0u8; map_volume([x, y, z])?];
+    decoder.read_exact(&mut blocks)?;
+    let remapped = to_classic(&mut blocks, cpe_block);
+
+    let header = Header {
+        version: crs::VERSION,
+        x,
+        y,
+        z,
+        spawn,
+        uuid,
+        time_created,
+        last_accessed: last_modified,
+        last_modified,
+    };
+    let mut world = ClassicWorld::from_header(name, author, &header, blocks)
+        .map_err(|_| FormatError::Size([x, y, z]))?;
+    *world.metadata_mut() = metadata;
+    Ok((world, remapped))
+}
+
+/// Write a world as an FCMv3 map
+pub fn write(world: &ClassicWorld) -> Result<Vec<u8>, FormatError> {
+    let [x, y, z] = check_size(world)?;
+    let header = world.get_header();
+    let metadata = world.get_metadata();
+
+    let mut encoder = DeflateEncoder::new(vec![], Compression::default());
+    for (group, key, value) in metadata.iter() {
+        for text in [group, key, value] {
+            let text = &text.as_bytes()[..text.len().min(u16::MAX as usize)];
+            encoder.write_u16::<LittleEndian>(text.len() as u16)?;
+            encoder.write_all(text)?;
+        }
+    }
+    encoder.write_all(world.get_blocks())?;
+    let compressed = encoder.finish()?;
+
+    let mut bytes = Vec::with_capacity(HEADER_LENGTH + compressed.len());
+    bytes.write_u32::<LittleEndian>(IDENTIFIER)?;
+    bytes.write_u8(REVISION)?;
+    for size in [x, y, z] {
+        bytes.write_u16::<LittleEndian>(size as u16)?;
+    }
+    for position in [header.spawn.x, header.spawn.y, header.spawn.z] {
+        bytes.write_i32::<LittleEndian>(position as i32)?;
+    }
+    bytes.write_u8(header.spawn.h)?;
+    bytes.write_u8(header.spawn.p)?;
+    bytes.write_u32::<LittleEndian>(header.last_modified as u32)?;
+    bytes.write_u32::<LittleEndian>(header.time_created as u32)?;
+    bytes.write_all(header.uuid.as_bytes())?;
+    // A single layer holding the blocks, which fCraft writes as the whole compressed stream
+    bytes.write_u8(1)?;
+    bytes.write_u8(BLOCKS_LAYER)?;
+    bytes.write_i64::<LittleEndian>(HEADER_LENGTH as i64)?;
+    bytes.write_i32::<LittleEndian>(compressed.len() as i32)?;
+    bytes.write_i32::<LittleEndian>(1)?;
+    bytes.write_i64::<LittleEndian>(world.get_blocks().len() as i64)?;
+    bytes.write_u32::<LittleEndian>(metadata.len() as u32)?;
+    bytes.extend_from_slice(&compressed);
+    Ok(bytes)
+}
+
+fn read_string(reader: &mut impl Read) -> Result<String, FormatError> {
+    let length = reader.read_u16::<LittleEndian>()? as usize;
+    let mut text = vec![0u8; length];
+    reader.read_exact(&mut text)?;
+    Ok(String::from_utf8_lossy(&text).into_owned())
+}
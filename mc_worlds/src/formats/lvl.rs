@@ -0,0 +1,185 @@
+//! # LVL
+//! MCGalaxy's map format, which grew out of MCSharp's.
+//!
+//! The whole file is gzipped. It starts with the version, 1874, then the width, length and height,
+//! the spawn in block coordinates, its heading and pitch and the permissions needed to visit and build,
+//! all little endian shorts and bytes. The blocks follow in the same order as CRS files.
+//! Maps from MCSharp and MCLawl before the version was added start straight away with the width
+//! and have no permissions.
+//!
+//! Blocks outside the classic range are CPE blocks, physics and op blocks or 163, a custom block whose id is
+//! in an optional section after the blocks. That section starts with 0xBD and then has a byte for each
+//! 16x16x16 chunk of the map, 1 if the 4096 custom ids of that chunk follow
+
+use std::io::{Read, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use uuid::Uuid;
+
+use crate::classic::{Block, ClassicWorld};
+use crate::crs::{self, Header};
+use super::{FormatError, Remapped, cpe_block, to_classic, check_size, map_volume, spawn_in_block, spawn_block};
+
+pub const VERSION: u16 = 1874;
+/// Stands in for a custom block in the main block array
+const CUSTOM_BLOCK: u8 = 163;
+/// Marks the section holding the ids of custom blocks
+const CUSTOM_SECTION: u8 = 0xBD;
+const CHUNK_SIZE: usize = 16;
+/// Group of the world's metadata holding what doesn't map onto a world
+pub const METADATA_GROUP: &str = "mcgalaxy";
+
+/// Read an MCGalaxy or MCSharp map as the world called `name`
+pub fn read(bytes: &[u8], name: &str, author: &str) -> Result<(ClassicWorld, Remapped), FormatError> {
+    if !bytes.starts_with(&[0x1F, 0x8B]) {
+        return Err(FormatError::Identifier);
+    }
+    let mut decoder = GzDecoder::new(bytes);
+    // Maps without a version start with their width, MCSharp only made maps a power of two wide
+    let first = decoder.read_u16::<LittleEndian>()?;
+    let has_version = first == VERSION;
+    let x = if has_version { decoder.read_u16::<LittleEndian>()? } else { first } as usize;
+    let z = decoder.read_u16::<LittleEndian>()? as usize;
+    let y = decoder.read_u16::<LittleEndian>()? as usize;
+    let spawn_x = decoder.read_u16::<LittleEndian>()? as i16;
+    let spawn_z = decoder.read_u16::<LittleEndian>()? as i16;
+    let spawn_y = decoder.read_u16::<LittleEndian>()? as i16;
+    let spawn = spawn_in_block(spawn_x, spawn_y, spawn_z, decoder.read_u8()?, decoder.read_u8()?);
+    let permissions = if has_version {
+        Some((decoder.read_u8()?, decoder.read_u8()?))
+    } else {
+        None
+    };
+
+    let mut blocks = vec![0u8; map_volume([x, y, z])?];
+    decoder.read_exact(&mut blocks)?;
+    // Older maps end with the blocks
+    let mut section = [0u8];
+    let mut custom = Remapped::default();
+    if decoder.read(&mut section)? == 1 && section[0] == CUSTOM_SECTION {
+        read_custom_blocks(&mut decoder, &mut blocks, [x, y, z], &mut custom)?;
+    }
+    let mut remapped = to_classic(&mut blocks, classic_block);
+    for (name, block, count) in custom.iter_names() {
+        remapped.add_name(name, block, count);
+    }
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let header = Header {
+        version: crs::VERSION,
+        x,
+        y,
+        z,
+        spawn,
+        uuid: Uuid::new_v4(),
+        time_created: now,
+        last_accessed: now,
+        last_modified: now,
+    };
+    let mut world = ClassicWorld::from_header(name, author, &header, blocks)
+        .map_err(|_| FormatError::Size([x, y, z]))?;
+    if let Some((visit_permission, build_permission)) = permissions {
+        let metadata = world.metadata_mut();
+        metadata.set(METADATA_GROUP, "visit_permission", &visit_permission.to_string());
+        metadata.set(METADATA_GROUP, "build_permission", &build_permission.to_string());
+    }
+    Ok((world, remapped))
+}
+
+/// The classic block closest to an MCGalaxy or MCSharp block. Ids 50 to 65 are CPE blocks, above them are
+/// op blocks, doors and physics blocks which mostly look like a classic block already.
+/// Anything else, such as a custom block left without an id, becomes stone
+pub fn classic_block(id: u8) -> Block {
+    match id {
+        0..=65 => cpe_block(id),
+        // Op blocks can't be broken by normal players
+        100 | 114 | 124 => Block::Glass,
+        101 | 113 | 123 => Block::Obsidian,
+        102 => Block::Bricks,
+        103 | 108 | 115 | 125 => Block::Stone,
+        104 => Block::Cobblestone,
+        105 | 132 | 137 | 160 => Block::Air,
+        106 | 133 | 138 | 161 => Block::StationaryWater,
+        107 | 112 | 134 | 139 | 162 => Block::StationaryLava,
+        109 => Block::Sponge,
+        // Floating wood and doors, the tdoors after them toggle when touched
+        110 | 111 | 122 => Block::Wood,
+        116 | 126 => Block::Leaves,
+        117 | 127 => Block::Sand,
+        118 | 128 => Block::Planks,
+        119 | 129 => Block::GreenCloth,
+        120 | 135 => Block::TNT,
+        121 | 136 => Block::Slab,
+        // Message blocks
+        130 => Block::WhiteCloth,
+        131 => Block::DarkGrayCloth,
+        // Faucets and finite liquids
+        140 | 144 => Block::CyanCloth,
+        141 => Block::OrangeCloth,
+        142 => Block::FlowingWater,
+        143 => Block::FlowingLava,
+        _ => Block::Stone,
+    }
+}
+
+/// Put the classic fallback of each custom block in place of the [CUSTOM_BLOCK] standing in for it.
+/// Custom ids are their own range rather than MCGalaxy block ids, so they go through [cpe_block]
+/// and anything that isn't a CPE block becomes stone. Each one is reported by name as `custom <id>`
+fn read_custom_blocks(reader: &mut impl Read, blocks: &mut [u8], [x, y, z]: [usize; 3],
+                      remapped: &mut Remapped) -> Result<(), FormatError> {
+    let mut chunk = [0u8; CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE];
+    for chunk_y in (0..y).step_by(CHUNK_SIZE) {
+        for chunk_z in (0..z).step_by(CHUNK_SIZE) {
+            for chunk_x in (0..x).step_by(CHUNK_SIZE) {
+                if reader.read_u8()? != 1 {
+                    continue;
+                }
+                reader.read_exact(&mut chunk)?;
+                for (i, id) in chunk.iter().enumerate() {
+                    let (block_x, block_y, block_z) = (chunk_x + (i & 0xF), chunk_y + (i >> 8),
+                                                       chunk_z + ((i >> 4) & 0xF));
+                    if block_x >= x || block_y >= y || block_z >= z {
+                        continue;
+                    }
+                    let index = block_x + x * (block_z + z * block_y);
+                    if blocks[index] == CUSTOM_BLOCK {
+                        let block = cpe_block(*id);
+                        if *id > Block::MAX_ID {
+                            remapped.add_name(&format!("custom {}", id), block, 1);
+                        }
+                        blocks[index] = block.into();
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write a world as an MCGalaxy map, keeping the permissions it was imported with
+pub fn write(world: &ClassicWorld) -> Result<Vec<u8>, FormatError> {
+    let [x, y, z] = check_size(world)?;
+    let spawn = world.get_spawn();
+    let permission = |key: &str| world.get_metadata().get(METADATA_GROUP, key)
+        .and_then(|p| p.parse::<u8>().ok())
+        .unwrap_or(0);
+
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder.write_u16::<LittleEndian>(VERSION)?;
+    for size in [x, z, y] {
+        encoder.write_u16::<LittleEndian>(size as u16)?;
+    }
+    let [spawn_x, spawn_y, spawn_z] = spawn_block(spawn);
+    for position in [spawn_x, spawn_z, spawn_y] {
+        encoder.write_u16::<LittleEndian>(position)?;
+    }
+    encoder.write_u8(spawn.h)?;
+    encoder.write_u8(spawn.p)?;
+    encoder.write_u8(permission("visit_permission"))?;
+    encoder.write_u8(permission("build_permission"))?;
+    encoder.write_all(world.get_blocks())?;
+    Ok(encoder.finish()?)
+}
@@ -0,0 +1,169 @@
+//! # MCLevel
+//! The map format of Indev and early Infdev, saved as .mclevel or .mine.
+//!
+//! The file is a gzipped NBT compound called MinecraftLevel. Its Map compound holds the Width, Length
+//! and Height, the Spawn as a list of three shorts in block coordinates and the Blocks in the same order as
+//! CRS files. About holds when the map was made, its name and author, Environment holds the sky and the
+//! ground around the map, Data holds the light and metadata of each block.
+//!
+//! Indev added blocks after obsidian, such as torches, chests and ice, which are replaced with
+//! a similar classic block
+
+use std::time::{SystemTime, UNIX_EPOCH};
+use nbt::{Blob, Map, Value};
+use uuid::Uuid;
+
+use crate::classic::{Block, ClassicWorld};
+use crate::crs::{self, Header};
+use super::{FormatError, Remapped, to_classic, check_size, spawn_in_block, spawn_block};
+
+/// Name of the root compound
+const ROOT: &str = "MinecraftLevel";
+/// Group of the world's metadata holding the name and author the map was saved with
+pub const METADATA_GROUP: &str = "indev";
+/// Full sky light and no metadata, the lighting is recalculated when the map loads
+const FULL_LIGHT: i8 = 0xF0u8 as i8;
+
+/// Read an Indev map as the world called `name`
+pub fn read(bytes: &[u8], name: &str, author: &str) -> Result<(ClassicWorld, Remapped), FormatError> {
+    if !bytes.starts_with(&[0x1F, 0x8B]) {
+        return Err(FormatError::Identifier);
+    }
+    let blob = Blob::from_gzip_reader(&mut &bytes[..])?;
+    let map = match blob.get("Map") {
+        Some(Value::Compound(map)) => map,
+        _ => return Err(FormatError::Missing("Map")),
+    };
+    let x = short(map, "Width")?.max(0) as usize;
+    let y = short(map, "Height")?.max(0) as usize;
+    let z = short(map, "Length")?.max(0) as usize;
+    let mut blocks: Vec<u8> = match map.get("Blocks") {
+        Some(Value::ByteArray(blocks)) => blocks.iter().map(|b| *b as u8).collect(),
+        _ => return Err(FormatError::Missing("Blocks")),
+    };
+    if blocks.len() != x * y * z {
+        return Err(FormatError::Size([x, y, z]));
+    }
+    let remapped = to_classic(&mut blocks, classic_block);
+    let spawn = match map.get("Spawn") {
+        Some(Value::List(spawn)) => match spawn.as_slice() {
+            [Value::Short(x), Value::Short(y), Value::Short(z)] => spawn_in_block(*x, *y, *z, 0, 0),
+            _ => return Err(FormatError::Missing("Spawn")),
+        },
+        _ => return Err(FormatError::Missing("Spawn")),
+    };
+
+    let about = match blob.get("About") {
+        Some(Value::Compound(about)) => Some(about),
+        _ => None,
+    };
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let created = match about.and_then(|a| a.get("CreatedOn")) {
+        Some(Value::Long(millis)) => (*millis).max(0) as u64 / 1000,
+        _ => now,
+    };
+    let header = Header {
+        version: crs::VERSION,
+        x,
+        y,
+        z,
+        spawn,
+        uuid: Uuid::new_v4(),
+        time_created: created,
+        last_accessed: now,
+        last_modified: now,
+    };
+    let mut world = ClassicWorld::from_header(name, author, &header, blocks)
+        .map_err(|_| FormatError::Size([x, y, z]))?;
+    for key in ["Name", "Author"] {
+        if let Some(Value::String(value)) = about.and_then(|a| a.get(key)) {
+            world.metadata_mut().set(METADATA_GROUP, &key.to_ascii_lowercase(), value);
+        }
+    }
+    Ok((world, remapped))
+}
+
+fn short(compound: &Map<String, Value>, name: &'static str) -> Result<i16, FormatError> {
+    match compound.get(name) {
+        Some(Value::Short(value)) => Ok(*value),
+        _ => Err(FormatError::Missing(name)),
+    }
+}
+
+/// The classic block closest to a block added in Indev.
+/// Blocks that are only decoration, such as torches and signs, become air
+pub fn classic_block(id: u8) -> Block {
+    match id {
+        0..=Block::MAX_ID => Block::from(id),
+        // Furnaces and cobblestone stairs
+        61 | 62 | 67 => Block::Cobblestone,
+        // Chests, stairs, crafting tables, jukeboxes and fences
+        53 | 54 | 58 | 84 | 85 => Block::Planks,
+        52 | 79 => Block::Glass,
+        56 | 73 | 74 => Block::IronOre,
+        57 => Block::IronBlock,
+        59 | 83 => Block::Sapling,
+        60 | 88 => Block::Dirt,
+        80 => Block::WhiteCloth,
+        81 => Block::GreenCloth,
+        82 => Block::LightGrayCloth,
+        86 | 91 => Block::OrangeCloth,
+        87 => Block::RedCloth,
+        89 => Block::YellowCloth,
+        90 => Block::VioletCloth,
+        // Torches, fire, redstone, signs, doors, ladders, rails, levers, pressure plates, buttons and snow
+        50 | 51 | 55 | 63..=66 | 68..=72 | 75..=78 => Block::Air,
+        _ => Block::Stone,
+    }
+}
+
+/// Write a world as an Indev map
+pub fn write(world: &ClassicWorld) -> Result<Vec<u8>, FormatError> {
+    let [x, y, z] = check_size(world)?;
+    // Sizes are signed shorts
+    if [x, y, z].iter().any(|s| *s > i16::MAX as usize) {
+        return Err(FormatError::Size([x, y, z]));
+    }
+    let header = world.get_header();
+    let [spawn_x, spawn_y, spawn_z] = spawn_block(header.spawn);
+    let metadata = world.get_metadata();
+
+    let mut about = Map::new();
+    about.insert("CreatedOn".to_string(), Value::Long(header.time_created as i64 * 1000));
+    about.insert("Name".to_string(), Value::String(
+        metadata.get(METADATA_GROUP, "name").unwrap_or_else(|| world.get_name()).to_string()));
+    about.insert("Author".to_string(), Value::String(
+        metadata.get(METADATA_GROUP, "author").unwrap_or("").to_string()));
+
+    let mut map = Map::new();
+    map.insert("Width".to_string(), Value::Short(x as i16));
+    map.insert("Length".to_string(), Value::Short(z as i16));
+    map.insert("Height".to_string(), Value::Short(y as i16));
+    map.insert("Spawn".to_string(), Value::List(
+        [spawn_x, spawn_y, spawn_z].iter().map(|c| Value::Short(*c as i16)).collect()));
+    map.insert("Blocks".to_string(), Value::ByteArray(world.get_blocks().iter().map(|b| *b as i8).collect()));
+    map.insert("Data".to_string(), Value::ByteArray(vec![FULL_LIGHT; world.get_blocks().len()]));
+
+    // Indev's defaults, with grass around the map and water up to half its height
+    let mut environment = Map::new();
+    environment.insert("TimeOfDay".to_string(), Value::Short(0));
+    environment.insert("SkyBrightness".to_string(), Value::Byte(100));
+    environment.insert("SkyColor".to_string(), Value::Int(0x99CCFF));
+    environment.insert("FogColor".to_string(), Value::Int(0xFFFFFF));
+    environment.insert("CloudColor".to_string(), Value::Int(0xFFFFFF));
+    environment.insert("CloudHeight".to_string(), Value::Short(y as i16 + 2));
+    environment.insert("SurroundingGroundType".to_string(), Value::Byte(u8::from(Block::GrassBlock) as i8));
+    environment.insert("SurroundingGroundHeight".to_string(), Value::Short(y as i16 / 2 - 1));
+    environment.insert("SurroundingWaterType".to_string(), Value::Byte(u8::from(Block::StationaryWater) as i8));
+    environment.insert("SurroundingWaterHeight".to_string(), Value::Short(y as i16 / 2));
+
+    let mut blob = Blob::named(ROOT);
+    blob.insert("About", Value::Compound(about))?;
+    blob.insert("Map", Value::Compound(map))?;
+    blob.insert("Environment", Value::Compound(environment))?;
+    blob.insert("Entities", Value::List(vec![]))?;
+    blob.insert("TileEntities", Value::List(vec![]))?;
+    let mut bytes = vec![];
+    blob.to_gzip_writer(&mut bytes)?;
+    Ok(bytes)
+}
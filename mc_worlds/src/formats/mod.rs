@@ -0,0 +1,393 @@
+//! # Formats
+//! Reading and writing the map files of other classic servers, so their maps can be dropped
+//! into the world directory and loaded.
+//!
+//! Block ids a classic client doesn't know are replaced with the closest classic block when a map is read,
+//! each format has its own table as the same id means different blocks to different servers.
+//! What was replaced is returned as a [Remapped] report alongside the world.
+//!
+//! Schematics hold a [Region] of a world rather than a whole one, to move builds to and from
+//! other tools.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use crate::classic::{Block, ClassicWorld, Spawn};
+
+pub mod cw;
+pub mod dat;
+pub mod fcm;
+pub mod lvl;
+pub mod mclevel;
+pub mod palette;
+pub mod schematic;
+pub mod sponge;
+
+/// Height of a player's eyes above their feet in the fixed point units positions are sent in.
+/// Formats that store the spawn as a block store where the player's feet are
+const EYE_HEIGHT: i16 = 51;
+
+/// Most blocks a map read from a file can hold, checked before room is made for them
+/// so a corrupt size can't ask for more memory than there is
+pub const MAX_VOLUME: usize = 1 << 28;
+
+/// Map formats worlds can be imported from and exported to
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Format {
+    /// The ClassicWorld NBT format
+    Cw,
+    /// Minecraft Classic's own level.dat
+    Dat,
+    /// fCraft and ClassiCube's FCMv3
+    Fcm,
+    /// MCGalaxy's gzipped .lvl, also MCSharp and MCLawl's older version of it
+    Lvl,
+    /// Indev and early Infdev's NBT .mclevel, also called .mine
+    Mclevel,
+}
+
+impl Format {
+    pub const ALL: [Format; 5] = [Format::Cw, Format::Dat, Format::Fcm, Format::Lvl, Format::Mclevel];
+
+    /// Extensions files of this format are saved with, the first is the one written
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Format::Cw => &["cw"],
+            Format::Dat => &["dat"],
+            Format::Fcm => &["fcm"],
+            Format::Lvl => &["lvl"],
+            Format::Mclevel => &["mclevel", "mine"],
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        self.extensions()[0]
+    }
+
+    /// The format of a file going by its extension
+    pub fn from_path(path: &Path) -> Option<Format> {
+        let extension = path.extension()?.to_str()?;
+        Format::ALL.iter().copied()
+            .find(|f| f.extensions().iter().any(|e| e.eq_ignore_ascii_case(extension)))
+    }
+
+    /// Read a map in this format as the world called `name`, along with which blocks had to be remapped
+    pub fn read(self, bytes: &[u8], name: &str, author: &str) -> Result<(ClassicWorld, Remapped), FormatError> {
+        match self {
+            Format::Cw => cw::read(bytes, name, author),
+            Format::Dat => dat::read(bytes, name, author),
+            Format::Fcm => fcm::read(bytes, name, author),
+            Format::Lvl => lvl::read(bytes, name, author),
+            Format::Mclevel => mclevel::read(bytes, name, author),
+        }
+    }
+
+    pub fn write(self, world: &ClassicWorld) -> Result<Vec<u8>, FormatError> {
+        match self {
+            Format::Cw => cw::write(world),
+            Format::Dat => dat::write(world),
+            Format::Fcm => fcm::write(world),
+            Format::Lvl => lvl::write(world),
+            Format::Mclevel => mclevel::write(world),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, ".{}", self.extension())
+    }
+}
+
+/// Formats a region of a world can be imported from and exported to
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RegionFormat {
+    /// MCEdit's .schematic
+    Schematic,
+    /// Sponge's .schem, version 2
+    Sponge,
+}
+
+impl RegionFormat {
+    pub const ALL: [RegionFormat; 2] = [RegionFormat::Schematic, RegionFormat::Sponge];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            RegionFormat::Schematic => "schematic",
+            RegionFormat::Sponge => "schem",
+        }
+    }
+
+    /// The format of a file going by its extension
+    pub fn from_path(path: &Path) -> Option<RegionFormat> {
+        let extension = path.extension()?.to_str()?;
+        RegionFormat::ALL.iter().copied().find(|f| f.extension().eq_ignore_ascii_case(extension))
+    }
+
+    /// Read a region in this format, along with which blocks had to be remapped
+    pub fn read(self, bytes: &[u8]) -> Result<(Region, Remapped), FormatError> {
+        match self {
+            RegionFormat::Schematic => schematic::read(bytes),
+            RegionFormat::Sponge => sponge::read(bytes),
+        }
+    }
+
+    pub fn write(self, region: &Region) -> Result<Vec<u8>, FormatError> {
+        match self {
+            RegionFormat::Schematic => schematic::write(region),
+            RegionFormat::Sponge => sponge::write(region),
+        }
+    }
+}
+
+impl fmt::Display for RegionFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, ".{}", self.extension())
+    }
+}
+
+/// A cuboid of blocks copied out of a world, in the same order as a world's blocks
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Region {
+    size: [usize; 3],
+    blocks: Vec<u8>,
+}
+
+impl Region {
+    /// A region of `blocks`, which have to be classic blocks and fill `size`
+    pub fn new(size: [usize; 3], blocks: Vec<u8>) -> Result<Self, FormatError> {
+        if blocks.len() != size.iter().product::<usize>() || blocks.iter().any(|b| *b > Block::MAX_ID) {
+            return Err(FormatError::Size(size));
+        }
+        Ok(Self { size, blocks })
+    }
+
+    /// Copy the blocks of `world` from `origin` to `origin + size`, cut short at the edges of the world
+    pub fn copy(world: &ClassicWorld, origin: [usize; 3], size: [usize; 3]) -> Self {
+        let world_size = world.get_size();
+        let [x, y, z] = [0, 1, 2].map(|i| size[i].min(world_size[i].saturating_sub(origin[i])));
+        let world_blocks = world.get_blocks();
+        let mut blocks = Vec::with_capacity(x * y * z);
+        for block_y in origin[1]..origin[1] + y {
+            for block_z in origin[2]..origin[2] + z {
+                let start = origin[0] + world_size[0] * (block_z + world_size[2] * block_y);
+                blocks.extend_from_slice(&world_blocks[start..start + x]);
+            }
+        }
+        Self { size: [x, y, z], blocks }
+    }
+
+    /// Put the region into `world` with its lowest corner at `origin`, leaving out what doesn't fit.
+    /// Returns the blocks that changed
+    pub fn paste(&self, world: &mut ClassicWorld, origin: [usize; 3]) -> Vec<(usize, usize, usize, Block)> {
+        let world_size = world.get_size();
+        let [x, y, z] = self.size;
+        let mut changed = vec![];
+        for region_y in 0..y.min(world_size[1].saturating_sub(origin[1])) {
+            for region_z in 0..z.min(world_size[2].saturating_sub(origin[2])) {
+                for region_x in 0..x.min(world_size[0].saturating_sub(origin[0])) {
+                    let block = self.blocks[region_x + x * (region_z + z * region_y)];
+                    let [block_x, block_y, block_z] = [origin[0] + region_x, origin[1] + region_y, origin[2] + region_z];
+                    let index = block_x + world_size[0] * (block_z + world_size[2] * block_y);
+                    if world.get_blocks()[index] != block {
+                        changed.push(world.replace_block(block_x, block_y, block_z, block.into()));
+                    }
+                }
+            }
+        }
+        changed
+    }
+
+    /// Width, height and length
+    pub fn size(&self) -> [usize; 3] {
+        self.size
+    }
+
+    pub fn blocks(&self) -> &[u8] {
+        &self.blocks
+    }
+}
+
+#[derive(Debug)]
+pub enum FormatError {
+    /// The file doesn't start with the identifier of the format it was read as
+    Identifier,
+    /// A version of the format that isn't supported
+    Version(u32),
+    /// The file ended part way through the map
+    Truncated,
+    Io(io::Error),
+    /// The world is too big for the format or the map has an impossible size, holds the size
+    Size([usize; 3]),
+    /// The map is missing something the format requires, holds its name
+    Missing(&'static str),
+    /// Part of the map holds values that can't be right, holds the name of the part
+    Corrupt(&'static str),
+    Nbt(nbt::Error),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::Identifier => write!(f, "Not a map of this format"),
+            FormatError::Version(version) => write!(f, "Version {} of this format isn't supported", version),
+            FormatError::Truncated => write!(f, "The map ends early, it may be truncated"),
+            FormatError::Io(e) => write!(f, "Failed to read map: {}", e),
+            FormatError::Size([x, y, z]) => write!(f, "A {}x{}x{} map can't be stored", x, y, z),
+            FormatError::Missing(name) => write!(f, "The map has no {}", name),
+            FormatError::Corrupt(name) => write!(f, "The map's {} is corrupt", name),
+            FormatError::Nbt(e) => write!(f, "Failed to read NBT: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+impl From<io::Error> for FormatError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::UnexpectedEof => FormatError::Truncated,
+            _ => FormatError::Io(e),
+        }
+    }
+}
+
+impl From<nbt::Error> for FormatError {
+    fn from(e: nbt::Error) -> Self {
+        match e {
+            nbt::Error::IncompleteNbtValue => FormatError::Truncated,
+            nbt::Error::IoError(e) => e.into(),
+            e => FormatError::Nbt(e),
+        }
+    }
+}
+
+/// Block ids of a map that aren't classic blocks, which classic block each became and how many there were
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Remapped {
+    blocks: BTreeMap<u8, (Block, usize)>,
+    /// Blocks of formats that name blocks rather than numbering them
+    names: BTreeMap<String, (Block, usize)>,
+}
+
+impl Remapped {
+    /// What `id` was replaced with and how many times, None if the map had none
+    pub fn get(&self, id: u8) -> Option<(Block, usize)> {
+        self.blocks.get(&id).copied()
+    }
+
+    /// What the block called `name` was replaced with and how many times, None if the map had none
+    pub fn get_name(&self, name: &str) -> Option<(Block, usize)> {
+        self.names.get(name).copied()
+    }
+
+    /// Every remapped id in order, with its replacement and count
+    pub fn iter(&self) -> impl Iterator<Item = (u8, Block, usize)> + '_ {
+        self.blocks.iter().map(|(id, (block, count))| (*id, *block, *count))
+    }
+
+    /// Every remapped block name in order, with its replacement and count
+    pub fn iter_names(&self) -> impl Iterator<Item = (&str, Block, usize)> + '_ {
+        self.names.iter().map(|(name, (block, count))| (name.as_str(), *block, *count))
+    }
+
+    /// How many blocks were replaced in total
+    pub fn total(&self) -> usize {
+        self.blocks.values().chain(self.names.values()).map(|(_, count)| count).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty() && self.names.is_empty()
+    }
+
+    /// Count `count` blocks of `id` being replaced with `block`
+    fn add(&mut self, id: u8, block: Block, count: usize) {
+        self.blocks.entry(id).or_insert((block, 0)).1 += count;
+    }
+
+    fn add_name(&mut self, name: &str, block: Block, count: usize) {
+        self.names.entry(name.to_string()).or_insert((block, 0)).1 += count;
+    }
+}
+
+impl fmt::Display for Remapped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ids = self.iter().map(|(id, block, count)| format!("{} {} to {:?}", count, id, block));
+        let names = self.iter_names().map(|(name, block, count)| format!("{} {} to {:?}", count, name, block));
+        write!(f, "{}", ids.chain(names).collect::<Vec<_>>().join(", "))
+    }
+}
+
+/// The classic block shown in place of a CPE CustomBlocks id, the fallback sent to clients without
+/// the extension. Anything else becomes stone
+pub fn cpe_block(id: u8) -> Block {
+    match id {
+        0..=Block::MAX_ID => Block::from(id),
+        50 => Block::Slab,
+        51 => Block::BrownMushroom,
+        52 => Block::Sand,
+        53 => Block::Air,
+        54 => Block::StationaryLava,
+        55 => Block::RoseCloth,
+        56 => Block::GreenCloth,
+        57 => Block::Dirt,
+        58 => Block::UltramarineCloth,
+        59 => Block::CapriCloth,
+        60 => Block::Glass,
+        61 => Block::IronBlock,
+        62 => Block::Obsidian,
+        63 => Block::WhiteCloth,
+        64 => Block::Planks,
+        _ => Block::Stone,
+    }
+}
+
+/// Replace every block id a classic client doesn't know using `classic_block`, reporting what was replaced
+fn to_classic(blocks: &mut [u8], classic_block: fn(u8) -> Block) -> Remapped {
+    let mut remapped = Remapped::default();
+    for block in blocks.iter_mut().filter(|b| **b > Block::MAX_ID) {
+        let replacement = classic_block(*block);
+        remapped.add(*block, replacement, 1);
+        *block = replacement.into();
+    }
+    remapped
+}
+
+/// The spawn of a player standing in the block at `x`, `y`, `z`
+fn spawn_in_block(x: i16, y: i16, z: i16, h: u8, p: u8) -> Spawn {
+    Spawn {
+        x: x.saturating_mul(32).saturating_add(16),
+        y: y.saturating_mul(32).saturating_add(EYE_HEIGHT),
+        z: z.saturating_mul(32).saturating_add(16),
+        h,
+        p,
+    }
+}
+
+/// The block a player at `spawn` is standing in, clamped to positive coordinates
+fn spawn_block(spawn: Spawn) -> [u16; 3] {
+    [spawn.x / 32, spawn.y.saturating_sub(EYE_HEIGHT) / 32, spawn.z / 32].map(|c| c.max(0) as u16)
+}
+
+/// Check a map's size fits in the unsigned shorts most formats store it in
+fn check_size(world: &ClassicWorld) -> Result<[usize; 3], FormatError> {
+    let size = world.get_size();
+    if size.iter().any(|s| *s > u16::MAX as usize) {
+        return Err(FormatError::Size(size));
+    }
+    Ok(size)
+}
+
+/// The number of blocks in a map of `size` read from a file, refusing sizes no classic map could have
+pub(crate) fn map_volume(size: [usize; 3]) -> Result<usize, FormatError> {
+    if size.iter().any(|s| *s > i16::MAX as usize) {
+        return Err(FormatError::Size(size));
+    }
+    let volume = size.iter().product::<usize>();
+    if volume > MAX_VOLUME {
+        return Err(FormatError::Size(size));
+    }
+    Ok(volume)
+}
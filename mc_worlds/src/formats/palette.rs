@@ -0,0 +1,219 @@
+//! # Palette
+//! Mapping classic blocks to the blocks of modern Minecraft, both the numeric ids and data values used
+//! before 1.13 and the namespaced block states used since.
+//!
+//! Every classic block maps to a different modern block so a region survives a round trip.
+//! Classic has more cloth colours than wool has, the extra ones use concrete of the nearest colour.
+//! Modern blocks without an exact classic block are matched on their name, so every kind of planks
+//! becomes planks and every colour of stained glass becomes glass
+
+use crate::classic::Block;
+
+/// Id of wool before 1.13, its colour is the data value
+const WOOL: u8 = 35;
+/// Id of concrete before 1.13, its colour is the data value
+const CONCRETE: u8 = 251;
+
+/// Wool and concrete colours in data value order, matching the modern block names
+const COLOURS: [&str; 16] = ["white", "orange", "magenta", "light_blue", "yellow", "lime", "pink", "gray",
+                             "light_gray", "cyan", "purple", "blue", "brown", "green", "red", "black"];
+
+/// The cloth closest to each wool colour, in data value order
+const WOOL_CLOTH: [Block; 16] = [
+    Block::WhiteCloth, Block::OrangeCloth, Block::MagentaCloth, Block::CapriCloth,
+    Block::YellowCloth, Block::ChartreuseCloth, Block::RoseCloth, Block::DarkGrayCloth,
+    Block::LightGrayCloth, Block::CyanCloth, Block::VioletCloth, Block::UltramarineCloth,
+    Block::OrangeCloth, Block::GreenCloth, Block::RedCloth, Block::DarkGrayCloth,
+];
+
+/// The id and data value of a classic block before 1.13
+pub fn alpha_block(block: Block) -> (u8, u8) {
+    match block {
+        Block::SpringGreenCloth => (CONCRETE, 5),
+        Block::PurpleCloth => (CONCRETE, 10),
+        _ if is_cloth(block) => {
+            // Orange and dark gray cloth come before brown and black wool so are never them
+            let colour = WOOL_CLOTH.iter().position(|cloth| *cloth == block).expect("Every other cloth has a wool");
+            (WOOL, colour as u8)
+        }
+        _ => (block.into(), 0),
+    }
+}
+
+/// The classic block for an id and data value from before 1.13, and whether it is an exact match
+pub fn from_alpha(id: u8, data: u8) -> (Block, bool) {
+    let colour = (data & 0xF) as usize;
+    match id {
+        0..=20 | 37..=49 => (Block::from(id), true),
+        CONCRETE if colour == 5 => (Block::SpringGreenCloth, true),
+        CONCRETE if colour == 10 => (Block::PurpleCloth, true),
+        // Brown and black wool have no cloth of their own
+        WOOL => (WOOL_CLOTH[colour], colour != 12 && colour != 15),
+        // Concrete and stained terracotta
+        CONCRETE | 159 => (WOOL_CLOTH[colour], false),
+        // Stained glass and panes
+        95 | 160 => (Block::Glass, false),
+        // Lapis and emerald ore
+        21 | 129 => (Block::IronOre, false),
+        22 => (Block::UltramarineCloth, false),
+        // Sandstone
+        24 | 179 => (Block::Sand, false),
+        // Dispensers
+        23 => (Block::Cobblestone, false),
+        // Note blocks and pistons
+        25 | 29 | 33 | 34 => (Block::Planks, false),
+        // Beds, rails, cobwebs, grass, dead bushes and moving pistons
+        26..=28 | 30..=32 | 36 => (Block::Air, false),
+        161 => (Block::Leaves, false),
+        162 => (Block::Wood, false),
+        125 => (Block::DoubleSlab, false),
+        126 => (Block::Slab, false),
+        // Blocks added in Indev kept their ids
+        _ => (super::mclevel::classic_block(id), false),
+    }
+}
+
+/// The namespaced block state of a classic block
+pub fn modern_block(block: Block) -> &'static str {
+    match block {
+        Block::Air => "minecraft:air",
+        Block::Stone => "minecraft:stone",
+        Block::GrassBlock => "minecraft:grass_block",
+        Block::Dirt => "minecraft:dirt",
+        Block::Cobblestone => "minecraft:cobblestone",
+        Block::Planks => "minecraft:oak_planks",
+        Block::Sapling => "minecraft:oak_sapling",
+        Block::Bedrock => "minecraft:bedrock",
+        Block::FlowingWater => "minecraft:water[level=1]",
+        Block::StationaryWater => "minecraft:water[level=0]",
+        Block::FlowingLava => "minecraft:lava[level=1]",
+        Block::StationaryLava => "minecraft:lava[level=0]",
+        Block::Sand => "minecraft:sand",
+        Block::Gravel => "minecraft:gravel",
+        Block::GoldOre => "minecraft:gold_ore",
+        Block::IronOre => "minecraft:iron_ore",
+        Block::CoalOre => "minecraft:coal_ore",
+        Block::Wood => "minecraft:oak_log",
+        Block::Leaves => "minecraft:oak_leaves[persistent=true]",
+        Block::Sponge => "minecraft:sponge",
+        Block::Glass => "minecraft:glass",
+        Block::RedCloth => "minecraft:red_wool",
+        Block::OrangeCloth => "minecraft:orange_wool",
+        Block::YellowCloth => "minecraft:yellow_wool",
+        Block::ChartreuseCloth => "minecraft:lime_wool",
+        Block::GreenCloth => "minecraft:green_wool",
+        Block::SpringGreenCloth => "minecraft:lime_concrete",
+        Block::CyanCloth => "minecraft:cyan_wool",
+        Block::CapriCloth => "minecraft:light_blue_wool",
+        Block::UltramarineCloth => "minecraft:blue_wool",
+        Block::VioletCloth => "minecraft:purple_wool",
+        Block::PurpleCloth => "minecraft:purple_concrete",
+        Block::MagentaCloth => "minecraft:magenta_wool",
+        Block::RoseCloth => "minecraft:pink_wool",
+        Block::DarkGrayCloth => "minecraft:gray_wool",
+        Block::LightGrayCloth => "minecraft:light_gray_wool",
+        Block::WhiteCloth => "minecraft:white_wool",
+        Block::Dandelion => "minecraft:dandelion",
+        Block::Rose => "minecraft:poppy",
+        Block::BrownMushroom => "minecraft:brown_mushroom",
+        Block::RedMushroom => "minecraft:red_mushroom",
+        Block::GoldBlock => "minecraft:gold_block",
+        Block::IronBlock => "minecraft:iron_block",
+        Block::DoubleSlab => "minecraft:smooth_stone_slab[type=double]",
+        Block::Slab => "minecraft:smooth_stone_slab[type=bottom]",
+        Block::Bricks => "minecraft:bricks",
+        Block::TNT => "minecraft:tnt",
+        Block::Bookshelf => "minecraft:bookshelf",
+        Block::MossyCobblestone => "minecraft:mossy_cobblestone",
+        Block::Obsidian => "minecraft:obsidian",
+    }
+}
+
+/// The classic block for a namespaced block state, and whether it is an exact match.
+/// Properties other than a liquid's level and a slab's type are ignored
+pub fn from_modern(state: &str) -> (Block, bool) {
+    let (name, properties) = match state.find('[') {
+        Some(i) => (&state[..i], state[i + 1..].trim_end_matches(']')),
+        None => (state, ""),
+    };
+    let name = name.strip_prefix("minecraft:").unwrap_or(name);
+    let property = |key: &str| properties.split(',')
+        .filter_map(|p| p.split_once('='))
+        .find(|(k, _)| k.trim() == key)
+        .map(|(_, v)| v.trim());
+
+    match name {
+        "water" | "lava" => {
+            let still = property("level").is_none_or(|level| level == "0");
+            let block = match (name, still) {
+                ("water", true) => Block::StationaryWater,
+                ("water", false) => Block::FlowingWater,
+                (_, true) => Block::StationaryLava,
+                (_, false) => Block::FlowingLava,
+            };
+            return (block, true);
+        }
+        "smooth_stone_slab" => {
+            let block = if property("type") == Some("double") { Block::DoubleSlab } else { Block::Slab };
+            return (block, true);
+        }
+        _ => {}
+    }
+    let namespaced = format!("minecraft:{}", name);
+    if let Some(block) = (0..=Block::MAX_ID).map(Block::from)
+        .find(|block| modern_block(*block).split('[').next() == Some(namespaced.as_str()))
+    {
+        return (block, true);
+    }
+    (closest_modern(name), false)
+}
+
+/// The classic block most like a modern block without an exact match, going by its name
+fn closest_modern(name: &str) -> Block {
+    let colour = COLOURS.iter()
+        .enumerate()
+        .filter(|(_, colour)| name.starts_with(&format!("{}_", colour)))
+        // light_gray has to win over gray
+        .max_by_key(|(_, colour)| colour.len())
+        .map(|(i, _)| WOOL_CLOTH[i]);
+    let ends_with = |suffixes: &[&str]| suffixes.iter().any(|s| name.ends_with(s));
+
+    if name.ends_with("air") {
+        Block::Air
+    } else if name.contains("glass") {
+        Block::Glass
+    } else if let (Some(cloth), true) = (colour, ends_with(&["_wool", "_concrete", "_terracotta", "_carpet"])) {
+        cloth
+    } else if ends_with(&["_planks", "_stairs", "_fence", "chest", "crafting_table"]) {
+        Block::Planks
+    } else if ends_with(&["_log", "_wood", "_stem"]) {
+        Block::Wood
+    } else if name.ends_with("_leaves") {
+        Block::Leaves
+    } else if name.ends_with("_slab") {
+        Block::Slab
+    } else if name.ends_with("_sapling") {
+        Block::Sapling
+    } else if name.ends_with("_ore") {
+        Block::IronOre
+    } else if ends_with(&["_flower", "tulip", "orchid", "allium", "daisy", "bluet", "cornflower"]) {
+        Block::Rose
+    } else if name.contains("sand") {
+        Block::Sand
+    } else if ends_with(&["dirt", "podzol", "mycelium", "farmland", "dirt_path"]) {
+        Block::Dirt
+    } else if name.contains("cobblestone") {
+        Block::Cobblestone
+    } else if name.contains("brick") {
+        Block::Bricks
+    } else if ends_with(&["grass", "fern", "torch", "sign", "door", "button", "pressure_plate", "rail", "ladder",
+                          "lever", "redstone_wire", "snow", "vine"]) {
+        Block::Air
+    } else {
+        Block::Stone
+    }
+}
+
+fn is_cloth(block: Block) -> bool {
+    (u8::from(Block::RedCloth)..=u8::from(Block::WhiteCloth)).contains(&block.into())
+}
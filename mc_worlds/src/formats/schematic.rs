@@ -0,0 +1,96 @@
+//! # Schematic
+//! MCEdit's format for a region of a world, read by most tools from before 1.13.
+//!
+//! The file is a gzipped NBT compound called Schematic holding the Width, Height and Length, then
+//! the Blocks and their Data values in the same order as CRS files. Materials says which ids the
+//! blocks are, Alpha for the ids from before 1.13 or Classic for classic ids.
+//! Regions are written with Alpha ids so modern tools can read them, see [super::palette]
+
+use nbt::{Blob, Value};
+
+use crate::classic::Block;
+use super::{FormatError, Region, Remapped, cpe_block, to_classic, palette};
+
+/// Name of the root compound
+const ROOT: &str = "Schematic";
+const ALPHA: &str = "Alpha";
+const CLASSIC: &str = "Classic";
+
+/// Read an MCEdit schematic
+pub fn read(bytes: &[u8]) -> Result<(Region, Remapped), FormatError> {
+    if !bytes.starts_with(&[0x1F, 0x8B]) {
+        return Err(FormatError::Identifier);
+    }
+    let blob = Blob::from_gzip_reader(&mut &bytes[..])?;
+    let size = [short(&blob, "Width")?, short(&blob, "Height")?, short(&blob, "Length")?];
+    let mut blocks = byte_array(&blob, "Blocks")?;
+    if blocks.len() != size.iter().product::<usize>() {
+        return Err(FormatError::Size(size));
+    }
+    let remapped = match blob.get("Materials") {
+        Some(Value::String(materials)) if materials == CLASSIC => to_classic(&mut blocks, cpe_block),
+        Some(Value::String(materials)) if materials == ALPHA => {
+            // Data is optional for blocks that don't need it
+            let data = byte_array(&blob, "Data").unwrap_or_default();
+            from_alpha(&mut blocks, &data)
+        }
+        _ => return Err(FormatError::Missing("Materials")),
+    };
+    Ok((Region::new(size, blocks)?, remapped))
+}
+
+/// Replace ids and data values from before 1.13 with classic blocks
+fn from_alpha(blocks: &mut [u8], data: &[u8]) -> Remapped {
+    let mut remapped = Remapped::default();
+    for (i, id) in blocks.iter_mut().enumerate() {
+        let (block, exact) = palette::from_alpha(*id, data.get(i).copied().unwrap_or(0));
+        if !exact {
+            remapped.add(*id, block, 1);
+        }
+        *id = block.into();
+    }
+    remapped
+}
+
+fn short(blob: &Blob, name: &'static str) -> Result<usize, FormatError> {
+    match blob.get(name) {
+        Some(Value::Short(value)) => Ok((*value).max(0) as usize),
+        _ => Err(FormatError::Missing(name)),
+    }
+}
+
+fn byte_array(blob: &Blob, name: &'static str) -> Result<Vec<u8>, FormatError> {
+    match blob.get(name) {
+        Some(Value::ByteArray(bytes)) => Ok(bytes.iter().map(|b| *b as u8).collect()),
+        _ => Err(FormatError::Missing(name)),
+    }
+}
+
+/// Write a region as an MCEdit schematic with Alpha ids
+pub fn write(region: &Region) -> Result<Vec<u8>, FormatError> {
+    let [x, y, z] = region.size();
+    if region.size().iter().any(|s| *s > i16::MAX as usize) {
+        return Err(FormatError::Size(region.size()));
+    }
+    let (blocks, data): (Vec<i8>, Vec<i8>) = region.blocks().iter()
+        .map(|b| palette::alpha_block(Block::from(*b)))
+        .map(|(id, data)| (id as i8, data as i8))
+        .unzip();
+
+    let mut blob = Blob::named(ROOT);
+    blob.insert("Width", Value::Short(x as i16))?;
+    blob.insert("Height", Value::Short(y as i16))?;
+    blob.insert("Length", Value::Short(z as i16))?;
+    blob.insert("Materials", Value::String(ALPHA.to_string()))?;
+    blob.insert("Blocks", Value::ByteArray(blocks))?;
+    blob.insert("Data", Value::ByteArray(data))?;
+    blob.insert("Entities", Value::List(vec![]))?;
+    blob.insert("TileEntities", Value::List(vec![]))?;
+    // Where the region was copied from isn't kept, so it's pasted relative to its corner
+    for axis in ["WEOriginX", "WEOriginY", "WEOriginZ", "WEOffsetX", "WEOffsetY", "WEOffsetZ"] {
+        blob.insert(axis, Value::Int(0))?;
+    }
+    let mut bytes = vec![];
+    blob.to_gzip_writer(&mut bytes)?;
+    Ok(bytes)
+}
@@ -0,0 +1,137 @@
+//! # Sponge
+//! The Sponge schematic format, version 2, used by WorldEdit since 1.13.
+//!
+//! The file is a gzipped NBT compound called Schematic holding the Version, the DataVersion of the game
+//! it was saved by, the Width, Height and Length, and a Palette from namespaced block states to indices.
+//! BlockData holds the index of each block in the same order as CRS files, each a variable length
+//! integer of 7 bits per byte with the top bit set on all but the last.
+//! Version 1 has the same blocks so is read too
+
+use nbt::{Blob, Map, Value};
+
+use crate::classic::Block;
+use super::{FormatError, Region, Remapped, palette};
+
+/// Name of the root compound
+const ROOT: &str = "Schematic";
+pub const VERSION: i32 = 2;
+/// Data version of Minecraft 1.16.5, which every block in the palette exists in
+const DATA_VERSION: i32 = 2586;
+
+/// Read a Sponge schematic
+pub fn read(bytes: &[u8]) -> Result<(Region, Remapped), FormatError> {
+    if !bytes.starts_with(&[0x1F, 0x8B]) {
+        return Err(FormatError::Identifier);
+    }
+    let blob = Blob::from_gzip_reader(&mut &bytes[..])?;
+    match blob.get("Version") {
+        Some(Value::Int(1)) | Some(Value::Int(VERSION)) => {}
+        Some(Value::Int(version)) => return Err(FormatError::Version(*version as u32)),
+        _ => return Err(FormatError::Missing("Version")),
+    }
+    let size = [short(&blob, "Width")?, short(&blob, "Height")?, short(&blob, "Length")?];
+
+    let palette = match blob.get("Palette") {
+        Some(Value::Compound(palette)) => palette,
+        _ => return Err(FormatError::Missing("Palette")),
+    };
+    // The classic block for each index in the palette, and the state if it isn't an exact match
+    let mut blocks_by_index = vec![None; palette.len()];
+    for (state, index) in palette {
+        match index {
+            Value::Int(index) if (*index as usize) < palette.len() => {
+                let (block, exact) = palette::from_modern(state);
+                blocks_by_index[*index as usize] = Some((block, if exact { None } else { Some(state.as_str()) }));
+            }
+            _ => return Err(FormatError::Corrupt("Palette")),
+        }
+    }
+
+    let data = match blob.get("BlockData") {
+        Some(Value::ByteArray(data)) => data,
+        _ => return Err(FormatError::Missing("BlockData")),
+    };
+    let volume = size.iter().product::<usize>();
+    // Every block takes at least a byte, so a size the data can't fill is refused before making room for it
+    if volume > data.len() {
+        return Err(FormatError::Truncated);
+    }
+    let mut blocks = Vec::with_capacity(volume);
+    let mut remapped = Remapped::default();
+    let mut counts = vec![0usize; palette.len()];
+    let mut data = data.iter().map(|b| *b as u8);
+    while blocks.len() < volume {
+        let index = read_varint(&mut data)?;
+        let (block, _) = blocks_by_index.get(index).copied().flatten().ok_or(FormatError::Corrupt("BlockData"))?;
+        counts[index] += 1;
+        blocks.push(block.into());
+    }
+    for (count, entry) in counts.iter().zip(&blocks_by_index).filter(|(count, _)| **count > 0) {
+        if let Some((block, Some(state))) = entry {
+            remapped.add_name(state, *block, *count);
+        }
+    }
+    Ok((Region::new(size, blocks)?, remapped))
+}
+
+/// Sizes are unsigned shorts stored as signed ones
+fn short(blob: &Blob, name: &'static str) -> Result<usize, FormatError> {
+    match blob.get(name) {
+        Some(Value::Short(value)) => Ok(*value as u16 as usize),
+        _ => Err(FormatError::Missing(name)),
+    }
+}
+
+fn read_varint(bytes: &mut impl Iterator<Item = u8>) -> Result<usize, FormatError> {
+    let mut value = 0usize;
+    for shift in (0..35).step_by(7) {
+        let byte = bytes.next().ok_or(FormatError::Truncated)?;
+        value |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(FormatError::Corrupt("BlockData"))
+}
+
+fn write_varint(bytes: &mut Vec<i8>, mut value: usize) {
+    while value >= 0x80 {
+        bytes.push((value as u8 | 0x80) as i8);
+        value >>= 7;
+    }
+    bytes.push(value as i8);
+}
+
+/// Write a region as a version 2 Sponge schematic
+pub fn write(region: &Region) -> Result<Vec<u8>, FormatError> {
+    let [x, y, z] = region.size();
+    if region.size().iter().any(|s| *s > u16::MAX as usize) {
+        return Err(FormatError::Size(region.size()));
+    }
+    // Only blocks in the region go in the palette, in the order they're first seen
+    let mut indices = [None; Block::MAX_ID as usize + 1];
+    let mut palette = Map::new();
+    let mut data = Vec::with_capacity(region.blocks().len());
+    for block in region.blocks() {
+        let index = *indices[*block as usize].get_or_insert_with(|| {
+            palette.insert(palette::modern_block(Block::from(*block)).to_string(), Value::Int(palette.len() as i32));
+            palette.len() - 1
+        });
+        write_varint(&mut data, index);
+    }
+
+    let mut blob = Blob::named(ROOT);
+    blob.insert("Version", Value::Int(VERSION))?;
+    blob.insert("DataVersion", Value::Int(DATA_VERSION))?;
+    blob.insert("Width", Value::Short(x as u16 as i16))?;
+    blob.insert("Height", Value::Short(y as u16 as i16))?;
+    blob.insert("Length", Value::Short(z as u16 as i16))?;
+    blob.insert("Offset", Value::IntArray(vec![0, 0, 0]))?;
+    blob.insert("PaletteMax", Value::Int(palette.len() as i32))?;
+    blob.insert("Palette", Value::Compound(palette))?;
+    blob.insert("BlockData", Value::ByteArray(data))?;
+    blob.insert("BlockEntities", Value::List(vec![]))?;
+    let mut bytes = vec![];
+    blob.to_gzip_writer(&mut bytes)?;
+    Ok(bytes)
+}
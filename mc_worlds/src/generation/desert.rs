@@ -0,0 +1,72 @@
+//! # Desert Generator
+//! Rolling sand dunes over stone with the odd oasis in the lowest hollows
+
+use serde::{Deserialize, Serialize};
+
+use crate::classic::Block;
+use crate::progress::Progress;
+use super::WorldGenerator;
+use super::random::JavaRandom;
+use super::terrain::{noise_map, Cover, Terrain};
+
+pub const NAME: &str = "desert";
+
+const DUNE: Cover = Cover { surface: Block::Sand, under: Block::Sand, depth: 5 };
+const OASIS_BED: Cover = Cover { surface: Block::Dirt, under: Block::Sand, depth: 5 };
+
+/// Parameters from `[map.generator.desert]`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// How many blocks the dunes rise and fall from half the map height
+    pub dune_height: usize,
+    /// Whether the lowest hollows fill with water
+    pub oases: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            dune_height: 8,
+            oases: true,
+        }
+    }
+}
+
+pub struct Desert {
+    settings: Settings,
+}
+
+impl Desert {
+    pub fn new(settings: &Settings) -> Self {
+        Self { settings: settings.clone() }
+    }
+}
+
+impl WorldGenerator for Desert {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn generate(&self, seed: i64, x: usize, y: usize, z: usize, progress: &Progress) -> Vec<u8> {
+        let mut random = JavaRandom::new(seed);
+        let dunes = noise_map(&mut random, x, z, 0.8);
+        let mut terrain = Terrain::new(x, y, z);
+        let middle = (y / 2) as i32;
+        let dune_height = self.settings.dune_height as f64;
+        let water_level = if self.settings.oases {
+            middle - (dune_height * 0.6) as i32
+        } else {
+            0
+        };
+
+        for column_z in 0..z {
+            for column_x in 0..x {
+                let top = middle + (dunes[column_x + x * column_z] * dune_height) as i32;
+                let cover = if top < water_level - 1 { &OASIS_BED } else { &DUNE };
+                terrain.column(column_x, column_z, top, cover, water_level);
+            }
+        }
+        terrain.into_blocks(progress)
+    }
+}
@@ -0,0 +1,28 @@
+//! # Empty Generator
+//! Nothing but air, players spawn floating in the middle of the void
+
+use crate::classic::Spawn;
+use crate::progress::Progress;
+use super::{WorldGenerator, fill_layers};
+
+pub const NAME: &str = "empty";
+
+pub struct Empty;
+
+impl WorldGenerator for Empty {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn seeded(&self) -> bool {
+        false
+    }
+
+    fn generate(&self, _seed: i64, x: usize, y: usize, z: usize, progress: &Progress) -> Vec<u8> {
+        fill_layers(x, y, z, progress, |_, _| {})
+    }
+
+    fn spawn(&self, _blocks: &[u8], x: usize, y: usize, z: usize) -> Spawn {
+        Spawn::centre(x, y, z)
+    }
+}
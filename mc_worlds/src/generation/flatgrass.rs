@@ -0,0 +1,68 @@
+//! # Flatgrass Generator
+//! Flat layers of blocks, by default bedrock then dirt up to half the height of the map topped with grass
+
+use serde::{Deserialize, Serialize};
+
+use crate::classic::Block;
+use crate::progress::Progress;
+use super::{WorldGenerator, block_setting, fill_layers};
+
+pub const NAME: &str = "flatgrass";
+
+/// A layer of blocks `depth` high
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Layer {
+    pub block: String,
+    pub depth: usize,
+}
+
+/// Parameters from `[map.generator.flatgrass]`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Layers from the bottom of the map up, left empty for the default layers
+    pub layers: Vec<Layer>,
+}
+
+#[derive(Default)]
+pub struct Flatgrass {
+    layers: Vec<(Block, usize)>,
+}
+
+impl Flatgrass {
+    pub fn new(settings: &Settings) -> Result<Self, String> {
+        let layers = settings.layers.iter()
+            .map(|layer| Ok((block_setting("flatgrass layer", &layer.block)?, layer.depth)))
+            .collect::<Result<_, String>>()?;
+        Ok(Self { layers })
+    }
+}
+
+impl WorldGenerator for Flatgrass {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn seeded(&self) -> bool {
+        false
+    }
+
+    fn generate(&self, _seed: i64, x: usize, y: usize, z: usize, progress: &Progress) -> Vec<u8> {
+        let layers = if self.layers.is_empty() {
+            let half = y / 2;
+            vec![(Block::Bedrock, 1), (Block::Dirt, half.saturating_sub(2)), (Block::GrassBlock, 1)]
+        } else {
+            self.layers.clone()
+        };
+        // The block in each layer of the map from the bottom up
+        let column: Vec<Block> = layers.iter()
+            .flat_map(|&(block, depth)| std::iter::repeat_n(block, depth))
+            .collect();
+
+        fill_layers(x, y, z, progress, |height, layer| {
+            if let Some(&block) = column.get(height) {
+                layer.fill(block.into());
+            }
+        })
+    }
+}
@@ -0,0 +1,88 @@
+//! # Islands Generator
+//! Grassy islands ringed with beaches in a sea covering most of the map
+
+use serde::{Deserialize, Serialize};
+
+use crate::classic::Block;
+use crate::progress::Progress;
+use super::WorldGenerator;
+use super::random::JavaRandom;
+use super::terrain::{noise_map, Cover, Terrain};
+
+pub const NAME: &str = "islands";
+
+const BEACH: Cover = Cover { surface: Block::Sand, under: Block::Sand, depth: 3 };
+const GRASS: Cover = Cover { surface: Block::GrassBlock, under: Block::Dirt, depth: 3 };
+
+/// Parameters from `[map.generator.islands]`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Height of the sea as a fraction of the map height
+    pub water_level: f64,
+    /// Roughly how much of the map is land, from 0 to 1
+    pub land: f64,
+    /// Size of the islands, larger values make bigger islands
+    pub size: f64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            water_level: 0.5,
+            land: 0.3,
+            size: 1.0,
+        }
+    }
+}
+
+pub struct Islands {
+    settings: Settings,
+}
+
+impl Islands {
+    pub fn new(settings: &Settings) -> Result<Self, String> {
+        if !(0.0..=1.0).contains(&settings.water_level) {
+            return Err(format!("islands water_level must be between 0 and 1, got {}", settings.water_level));
+        }
+        if !(0.0..=1.0).contains(&settings.land) {
+            return Err(format!("islands land must be between 0 and 1, got {}", settings.land));
+        }
+        if settings.size <= 0.0 {
+            return Err(format!("islands size must be above 0, got {}", settings.size));
+        }
+        Ok(Self { settings: settings.clone() })
+    }
+}
+
+impl WorldGenerator for Islands {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn generate(&self, seed: i64, x: usize, y: usize, z: usize, progress: &Progress) -> Vec<u8> {
+        let mut random = JavaRandom::new(seed);
+        let noise = noise_map(&mut random, x, z, 0.8 / self.settings.size);
+        let mut terrain = Terrain::new(x, y, z);
+        let water_level = (y as f64 * self.settings.water_level) as i32;
+        // Noise above this is land, picked so `land` of the map ends up above the water
+        let mut sorted = noise.clone();
+        sorted.sort_unstable_by(|a, b| a.total_cmp(b));
+        let shore = if x * z > 0 {
+            sorted[((1.0 - self.settings.land) * (sorted.len() - 1) as f64) as usize]
+        } else {
+            0.0
+        };
+        let relief = y as f64 / 3.0;
+
+        for column_z in 0..z {
+            for column_x in 0..x {
+                let value = noise[column_x + x * column_z];
+                let top = water_level + ((value - shore) * relief).floor() as i32;
+                let cover = if top <= water_level + 1 { &BEACH } else { &GRASS };
+                terrain.column(column_x, column_z, top, cover, water_level);
+            }
+        }
+        terrain.into_blocks(progress)
+    }
+}
@@ -0,0 +1,123 @@
+//! # Generation
+//! Procedural generation of the blocks for new worlds.
+//! Every generator implements [WorldGenerator] and is picked by name with [from_settings]
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::classic::{Block, Spawn};
+use crate::progress::{Progress, Stage};
+
+pub mod random;
+pub mod noise;
+pub mod tree;
+mod terrain;
+pub mod notch;
+pub mod flatgrass;
+pub mod empty;
+pub mod islands;
+pub mod mountains;
+pub mod desert;
+pub mod pixel;
+
+/// Names of every generator [from_settings] can build
+pub const GENERATORS: [&str; 7] = [flatgrass::NAME, empty::NAME, notch::NAME, islands::NAME, mountains::NAME,
+    desert::NAME, pixel::NAME];
+
+/// Makes the blocks for a new world
+pub trait WorldGenerator: Send + Sync {
+    /// Name the generator is selected by and stored under in a worlds generator metadata
+    fn name(&self) -> &'static str;
+
+    /// Whether the map depends on the seed, the seed is only kept in a worlds metadata if it does
+    fn seeded(&self) -> bool {
+        true
+    }
+
+    /// Generate a map of `x` by `y` by `z` blocks, in the same order as the LevelDataChunk packet,
+    /// counting finished work in `progress`
+    fn generate(&self, seed: i64, x: usize, y: usize, z: usize, progress: &Progress) -> Vec<u8>;
+
+    /// Where players spawn in a map made by this generator
+    fn spawn(&self, blocks: &[u8], x: usize, y: usize, z: usize) -> Spawn {
+        surface_spawn(blocks, x, y, z)
+    }
+}
+
+/// Which generator new worlds are made with and the parameters for each generator,
+/// read from the `[map.generator]` table of the server config
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GeneratorSettings {
+    /// One of [GENERATORS]
+    pub name: String,
+    /// Seed for the generator, 0 picks a random seed for every new world
+    pub seed: i64,
+    pub flatgrass: flatgrass::Settings,
+    pub islands: islands::Settings,
+    pub mountains: mountains::Settings,
+    pub desert: desert::Settings,
+    pub pixel: pixel::Settings,
+}
+
+impl Default for GeneratorSettings {
+    fn default() -> Self {
+        Self {
+            name: flatgrass::NAME.to_string(),
+            seed: 0,
+            flatgrass: flatgrass::Settings::default(),
+            islands: islands::Settings::default(),
+            mountains: mountains::Settings::default(),
+            desert: desert::Settings::default(),
+            pixel: pixel::Settings::default(),
+        }
+    }
+}
+
+/// Build the generator named in `settings` ignoring case, with its parameters from `settings`
+pub fn from_settings(settings: &GeneratorSettings) -> Result<Box<dyn WorldGenerator>, String> {
+    let generator: Box<dyn WorldGenerator> = match settings.name.to_ascii_lowercase().as_str() {
+        flatgrass::NAME => Box::new(flatgrass::Flatgrass::new(&settings.flatgrass)?),
+        empty::NAME => Box::new(empty::Empty),
+        notch::NAME => Box::new(notch::NotchGenerator),
+        islands::NAME => Box::new(islands::Islands::new(&settings.islands)?),
+        mountains::NAME => Box::new(mountains::Mountains::new(&settings.mountains)?),
+        desert::NAME => Box::new(desert::Desert::new(&settings.desert)),
+        pixel::NAME => Box::new(pixel::Pixel::new(&settings.pixel)?),
+        _ => return Err(format!("unknown generator \"{}\", expected one of {}", settings.name,
+                                GENERATORS.join(", "))),
+    };
+    Ok(generator)
+}
+
+/// A spawn standing on the highest block in the middle of the map, or the centre of the map if that column is empty
+pub fn surface_spawn(blocks: &[u8], x: usize, y: usize, z: usize) -> Spawn {
+    let mut spawn = Spawn::centre(x, y, z);
+    let (column_x, column_z) = (x / 2, z / 2);
+    if x * z == 0 {
+        return spawn;
+    }
+    if let Some(top) = (0..y).rev().find(|&h| blocks[column_x + x * (column_z + z * h)] != u8::from(Block::Air)) {
+        spawn.y = ((top + 3).min(y) * 32) as i16;
+    }
+    spawn
+}
+
+/// Make an `x` by `y` by `z` map filling its layers in parallel, `fill(y, layer)` sets the blocks of layer `y`
+fn fill_layers<F>(x: usize, y: usize, z: usize, progress: &Progress, fill: F) -> Vec<u8>
+    where F: Fn(usize, &mut [u8]) + Sync {
+    let mut blocks: Vec<u8> = vec![Block::Air.into(); x * y * z];
+    progress.start(Stage::Generating, y);
+    if x * z > 0 {
+        blocks.par_chunks_mut(x * z).enumerate().for_each(|(height, layer)| {
+            fill(height, layer);
+            progress.advance(1);
+        });
+    }
+    blocks
+}
+
+/// Look up a block named in a generators parameters
+fn block_setting(setting: &str, name: &str) -> Result<Block, String> {
+    Block::from_name(name).ok_or_else(|| format!("{} \"{}\" is not a block", setting, name))
+}
@@ -0,0 +1,91 @@
+//! # Mountains Generator
+//! Sharp ridges of bare stone rising out of grassy valleys with lakes at the bottom
+
+use serde::{Deserialize, Serialize};
+
+use crate::classic::Block;
+use crate::progress::Progress;
+use super::WorldGenerator;
+use super::random::JavaRandom;
+use super::terrain::{noise_map, Cover, Terrain};
+
+pub const NAME: &str = "mountains";
+
+const LAKE_BED: Cover = Cover { surface: Block::Gravel, under: Block::Dirt, depth: 2 };
+const BARE: Cover = Cover { surface: Block::Stone, under: Block::Stone, depth: 0 };
+const GRASS: Cover = Cover { surface: Block::GrassBlock, under: Block::Dirt, depth: 3 };
+
+/// Parameters from `[map.generator.mountains]`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// How high the peaks reach as a fraction of the map height
+    pub peak_height: f64,
+    /// Height of the lakes in the valleys as a fraction of the map height
+    pub water_level: f64,
+    /// Height above which mountains are bare stone, as a fraction of the map height
+    pub tree_line: f64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            peak_height: 0.9,
+            water_level: 0.25,
+            tree_line: 0.6,
+        }
+    }
+}
+
+pub struct Mountains {
+    settings: Settings,
+}
+
+impl Mountains {
+    pub fn new(settings: &Settings) -> Result<Self, String> {
+        for (name, value) in [("peak_height", settings.peak_height), ("water_level", settings.water_level),
+                              ("tree_line", settings.tree_line)] {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(format!("mountains {} must be between 0 and 1, got {}", name, value));
+            }
+        }
+        Ok(Self { settings: settings.clone() })
+    }
+}
+
+impl WorldGenerator for Mountains {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn generate(&self, seed: i64, x: usize, y: usize, z: usize, progress: &Progress) -> Vec<u8> {
+        let mut random = JavaRandom::new(seed);
+        let ridges = noise_map(&mut random, x, z, 0.35);
+        let detail = noise_map(&mut random, x, z, 1.5);
+        let mut terrain = Terrain::new(x, y, z);
+        let height = y as f64;
+        let peak = height * self.settings.peak_height;
+        let water_level = (height * self.settings.water_level) as i32;
+        // Valley floors sit just above the water so only the deepest hollows hold lakes
+        let valley = water_level as f64 + 1.0;
+        let tree_line = (height * self.settings.tree_line) as i32;
+
+        for column_z in 0..z {
+            for column_x in 0..x {
+                let index = column_x + x * column_z;
+                // Folding the noise at 0 makes sharp ridges along its zero line
+                let ridge = (1.0 - ridges[index].abs()).powi(4);
+                let top = (valley + ridge * (peak - valley) + detail[index] * 4.0) as i32;
+                let cover = if top < water_level {
+                    &LAKE_BED
+                } else if top >= tree_line {
+                    &BARE
+                } else {
+                    &GRASS
+                };
+                terrain.column(column_x, column_z, top, cover, water_level);
+            }
+        }
+        terrain.into_blocks(progress)
+    }
+}
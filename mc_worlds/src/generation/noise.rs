@@ -0,0 +1,101 @@
+//! # Noise
+//! Ken Perlin's improved noise, layered into the octave and combined noise used by the classic level generator
+
+use super::random::JavaRandom;
+
+/// A single layer of 2D improved noise
+pub struct ImprovedNoise {
+    permutation: [u8; 512],
+}
+
+impl ImprovedNoise {
+    pub fn new(random: &mut JavaRandom) -> Self {
+        let mut permutation = [0u8; 512];
+        for (i, p) in permutation.iter_mut().enumerate().take(256) {
+            *p = i as u8;
+        }
+        for i in 0..256 {
+            let j = random.next_range(i as i32, 256) as usize;
+            permutation.swap(i, j);
+        }
+        let (low, high) = permutation.split_at_mut(256);
+        high.copy_from_slice(low);
+        Self { permutation }
+    }
+
+    pub fn compute(&self, x: f64, y: f64) -> f64 {
+        let p = &self.permutation;
+        let (x_floor, y_floor) = (x.floor(), y.floor());
+        let xi = (x_floor as i32 & 0xFF) as usize;
+        let yi = (y_floor as i32 & 0xFF) as usize;
+        let (x, y) = (x - x_floor, y - y_floor);
+        let u = fade(x);
+        let v = fade(y);
+
+        let a = p[xi] as usize + yi;
+        let b = p[xi + 1] as usize + yi;
+        let c1 = lerp(u, grad(p[p[a] as usize], x, y), grad(p[p[b] as usize], x - 1.0, y));
+        let c2 = lerp(u, grad(p[p[a + 1] as usize], x, y - 1.0), grad(p[p[b + 1] as usize], x - 1.0, y - 1.0));
+        lerp(v, c1, c2)
+    }
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+/// The gradient function from improved noise with z fixed at 0
+fn grad(hash: u8, x: f64, y: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 { y } else if h == 12 || h == 14 { x } else { 0.0 };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+/// Several layers of noise, each at half the frequency and double the amplitude of the last
+pub struct OctaveNoise {
+    octaves: Vec<ImprovedNoise>,
+}
+
+impl OctaveNoise {
+    pub fn new(random: &mut JavaRandom, octaves: usize) -> Self {
+        Self {
+            octaves: (0..octaves).map(|_| ImprovedNoise::new(random)).collect(),
+        }
+    }
+
+    pub fn compute(&self, x: f64, y: f64) -> f64 {
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        let mut sum = 0.0;
+        for octave in &self.octaves {
+            sum += octave.compute(x * frequency, y * frequency) * amplitude;
+            amplitude *= 2.0;
+            frequency /= 2.0;
+        }
+        sum
+    }
+}
+
+/// Octave noise whose x input is offset by a second octave noise
+pub struct CombinedNoise {
+    first: OctaveNoise,
+    second: OctaveNoise,
+}
+
+impl CombinedNoise {
+    pub fn new(random: &mut JavaRandom, first_octaves: usize, second_octaves: usize) -> Self {
+        let first = OctaveNoise::new(random, first_octaves);
+        let second = OctaveNoise::new(random, second_octaves);
+        Self { first, second }
+    }
+
+    pub fn compute(&self, x: f64, y: f64) -> f64 {
+        let offset = self.second.compute(x, y);
+        self.first.compute(x + offset, y)
+    }
+}
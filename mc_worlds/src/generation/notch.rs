@@ -0,0 +1,418 @@
+//! # Notch Generator
+//! The level generator from Minecraft Classic: a noise heightmap, stone and dirt strata, caves, ore veins,
+//! water and lava flooding, beaches, flowers, mushrooms and trees.
+//! Every random choice comes from a single [JavaRandom] so the same seed always makes the same map.
+
+use std::f32::consts::PI;
+
+use crate::classic::Block;
+use crate::progress::{Progress, Stage};
+use super::WorldGenerator;
+use super::random::JavaRandom;
+use super::tree;
+use super::noise::{CombinedNoise, OctaveNoise};
+
+/// Name stored in a worlds generator metadata for maps made by [NotchGenerator]
+pub const NAME: &str = "notch";
+
+/// The level generator from Minecraft Classic
+pub struct NotchGenerator;
+
+impl WorldGenerator for NotchGenerator {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    /// Every step draws from the same random numbers in turn, so this runs on one thread
+    fn generate(&self, seed: i64, x: usize, y: usize, z: usize, progress: &Progress) -> Vec<u8> {
+        Level::generate(seed, x, y, z, progress)
+    }
+}
+
+/// A level part way through being generated
+struct Level {
+    width: usize,
+    height: usize,
+    length: usize,
+    blocks: Vec<u8>,
+    heightmap: Vec<i32>,
+    water_level: i32,
+    random: JavaRandom,
+}
+
+impl Level {
+    /// Steps [Level::generate] goes through, for reporting progress
+    const STEPS: usize = 13;
+
+    fn generate(seed: i64, x: usize, y: usize, z: usize, progress: &Progress) -> Vec<u8> {
+        let mut generator = Self {
+            width: x,
+            height: y,
+            length: z,
+            blocks: vec![Block::Air.into(); x * y * z],
+            heightmap: vec![0; x * z],
+            water_level: (y / 2) as i32,
+            random: JavaRandom::new(seed),
+        };
+        progress.start(Stage::Generating, Self::STEPS);
+        // Every step assumes there's at least one block
+        if generator.blocks.is_empty() {
+            progress.advance(Self::STEPS);
+            return generator.blocks;
+        }
+        let steps: [fn(&mut Self); Self::STEPS] = [
+            Self::create_heightmap,
+            Self::create_strata,
+            Self::carve_caves,
+            |level| level.carve_ore_veins(0.9, Block::CoalOre),
+            |level| level.carve_ore_veins(0.7, Block::IronOre),
+            |level| level.carve_ore_veins(0.5, Block::GoldOre),
+            Self::flood_water_borders,
+            Self::flood_water,
+            Self::flood_lava,
+            Self::create_surface,
+            Self::plant_flowers,
+            Self::plant_mushrooms,
+            Self::plant_trees,
+        ];
+        for step in steps {
+            step(&mut generator);
+            progress.advance(1);
+        }
+        generator.blocks
+    }
+
+    fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        x + self.width * (z + self.length * y)
+    }
+
+    fn contains(&self, x: i32, y: i32, z: i32) -> bool {
+        x >= 0 && y >= 0 && z >= 0 &&
+            (x as usize) < self.width && (y as usize) < self.height && (z as usize) < self.length
+    }
+
+    fn get(&self, x: usize, y: usize, z: usize) -> Block {
+        self.blocks[self.index(x, y, z)].into()
+    }
+
+    fn set(&mut self, x: usize, y: usize, z: usize, block: Block) {
+        let index = self.index(x, y, z);
+        self.blocks[index] = block.into();
+    }
+
+    fn random_x(&mut self) -> i32 {
+        self.random.next_int(self.width as i32)
+    }
+
+    fn random_z(&mut self) -> i32 {
+        self.random.next_int(self.length as i32)
+    }
+
+    /// A random step of up to 5 blocks in either direction, used to scatter plants around a patch
+    fn random_step(&mut self) -> i32 {
+        self.random.next_int(6) - self.random.next_int(6)
+    }
+
+    fn create_heightmap(&mut self) {
+        let low = CombinedNoise::new(&mut self.random, 8, 8);
+        let high = CombinedNoise::new(&mut self.random, 8, 8);
+        let select = OctaveNoise::new(&mut self.random, 6);
+
+        for z in 0..self.length {
+            for x in 0..self.width {
+                let (fx, fz) = (x as f64, z as f64);
+                let height_low = low.compute(fx * 1.3, fz * 1.3) / 6.0 - 4.0;
+                let mut height = height_low;
+                if select.compute(fx, fz) <= 0.0 {
+                    let height_high = high.compute(fx * 1.3, fz * 1.3) / 5.0 + 6.0;
+                    height = height_low.max(height_high);
+                }
+                height *= 0.5;
+                if height < 0.0 {
+                    height *= 0.8;
+                }
+                self.heightmap[x + z * self.width] = (height + self.water_level as f64) as i32;
+            }
+        }
+    }
+
+    fn create_strata(&mut self) {
+        let noise = OctaveNoise::new(&mut self.random, 8);
+        let max_y = self.height as i32 - 1;
+
+        // The bottom of the map is always lava
+        self.blocks[..self.width * self.length].fill(Block::StationaryLava.into());
+
+        for z in 0..self.length {
+            for x in 0..self.width {
+                let dirt_thickness = (noise.compute(x as f64, z as f64) / 24.0 - 4.0) as i32;
+                let dirt_height = self.heightmap[x + z * self.width].min(max_y);
+                let stone_height = (dirt_height + dirt_thickness).min(max_y);
+
+                for y in 1..=stone_height {
+                    self.set(x, y as usize, z, Block::Stone);
+                }
+                for y in (stone_height + 1).max(1)..=dirt_height {
+                    self.set(x, y as usize, z, Block::Dirt);
+                }
+            }
+        }
+    }
+
+    fn carve_caves(&mut self) {
+        let count = self.blocks.len() / 8192;
+        for _ in 0..count {
+            let mut cave_x = self.random_x() as f32;
+            let mut cave_y = self.random.next_int(self.height as i32) as f32;
+            let mut cave_z = self.random_z() as f32;
+
+            let length = (self.random.next_float() * self.random.next_float() * 200.0) as i32;
+            let mut theta = self.random.next_float() * 2.0 * PI;
+            let mut delta_theta = 0.0;
+            let mut phi = self.random.next_float() * 2.0 * PI;
+            let mut delta_phi = 0.0;
+            let cave_radius = self.random.next_float() * self.random.next_float();
+
+            for i in 0..length {
+                cave_x += theta.sin() * phi.cos();
+                cave_z += theta.cos() * phi.cos();
+                cave_y += phi.sin();
+
+                theta += delta_theta * 0.2;
+                delta_theta = delta_theta * 0.9 + self.random.next_float() - self.random.next_float();
+                phi = phi * 0.5 + delta_phi * 0.25;
+                delta_phi = delta_phi * 0.75 + self.random.next_float() - self.random.next_float();
+                if self.random.next_float() < 0.25 {
+                    continue;
+                }
+
+                let centre_x = cave_x + (self.random.next_int(4) - 2) as f32 * 0.2;
+                let centre_y = cave_y + (self.random.next_int(4) - 2) as f32 * 0.2;
+                let centre_z = cave_z + (self.random.next_int(4) - 2) as f32 * 0.2;
+
+                let depth = (self.height as f32 - centre_y) / self.height as f32;
+                let radius = (1.2 + (depth * 3.5 + 1.0) * cave_radius) * (i as f32 * PI / length as f32).sin();
+                self.fill_oblate_spheroid(centre_x as i32, centre_y as i32, centre_z as i32, radius, Block::Air);
+            }
+        }
+    }
+
+    fn carve_ore_veins(&mut self, abundance: f32, ore: Block) {
+        let count = (self.blocks.len() as f32 * abundance / 16384.0) as usize;
+        for _ in 0..count {
+            let mut vein_x = self.random_x() as f32;
+            let mut vein_y = self.random.next_int(self.height as i32) as f32;
+            let mut vein_z = self.random_z() as f32;
+
+            let length = (self.random.next_float() * self.random.next_float() * 75.0 * abundance) as i32;
+            let mut theta = self.random.next_float() * 2.0 * PI;
+            let mut delta_theta = 0.0;
+            let mut phi = self.random.next_float() * 2.0 * PI;
+            let mut delta_phi = 0.0;
+
+            for i in 0..length {
+                vein_x += theta.sin() * phi.cos();
+                vein_z += theta.cos() * phi.cos();
+                vein_y += phi.sin();
+
+                theta += delta_theta * 0.2;
+                delta_theta = delta_theta * 0.9 + self.random.next_float() - self.random.next_float();
+                phi = phi * 0.5 + delta_phi * 0.25;
+                delta_phi = delta_phi * 0.9 + self.random.next_float() - self.random.next_float();
+
+                let radius = abundance * (i as f32 * PI / length as f32).sin() + 1.0;
+                self.fill_oblate_spheroid(vein_x as i32, vein_y as i32, vein_z as i32, radius, ore);
+            }
+        }
+    }
+
+    /// Replace the stone in a sphere squashed to half its height with `block`
+    fn fill_oblate_spheroid(&mut self, x: i32, y: i32, z: i32, radius: f32, block: Block) {
+        let start = |c: i32| (c as f32 - radius).max(0.0) as i32;
+        let end = |c: i32, size: usize| (c as f32 + radius).min(size as f32 - 1.0) as i32;
+        let radius_sq = radius * radius;
+        for yy in start(y)..=end(y, self.height) {
+            for zz in start(z)..=end(z, self.length) {
+                for xx in start(x)..=end(x, self.width) {
+                    let (dx, dy, dz) = ((xx - x) as f32, (yy - y) as f32, (zz - z) as f32);
+                    if dx * dx + 2.0 * dy * dy + dz * dz < radius_sq &&
+                        self.get(xx as usize, yy as usize, zz as usize) == Block::Stone {
+                        self.set(xx as usize, yy as usize, zz as usize, block);
+                    }
+                }
+            }
+        }
+    }
+
+    fn flood_water_borders(&mut self) {
+        let water_y = self.water_level - 1;
+        if water_y < 0 {
+            return;
+        }
+        let (max_x, max_z) = (self.width as i32 - 1, self.length as i32 - 1);
+        for x in 0..=max_x {
+            self.flood_fill(x, water_y, 0, Block::StationaryWater);
+            self.flood_fill(x, water_y, max_z, Block::StationaryWater);
+        }
+        for z in 0..=max_z {
+            self.flood_fill(0, water_y, z, Block::StationaryWater);
+            self.flood_fill(max_x, water_y, z, Block::StationaryWater);
+        }
+    }
+
+    fn flood_water(&mut self) {
+        let count = self.width * self.length / 800;
+        for _ in 0..count {
+            let x = self.random_x();
+            let z = self.random_z();
+            let y = self.water_level - self.random.next_range(1, 3);
+            self.flood_fill(x, y, z, Block::StationaryWater);
+        }
+    }
+
+    fn flood_lava(&mut self) {
+        let count = self.blocks.len() / 20000;
+        for _ in 0..count {
+            let x = self.random_x();
+            let z = self.random_z();
+            let y = ((self.water_level - 3) as f32 * self.random.next_float() * self.random.next_float()) as i32;
+            self.flood_fill(x, y, z, Block::StationaryLava);
+        }
+    }
+
+    /// Fill the air connected to a point with `block`, spreading sideways and down but never up
+    fn flood_fill(&mut self, x: i32, y: i32, z: i32, block: Block) {
+        if !self.contains(x, y, z) {
+            return;
+        }
+        let mut stack = vec![(x as usize, y as usize, z as usize)];
+        while let Some((x, y, z)) = stack.pop() {
+            if self.get(x, y, z) != Block::Air {
+                continue;
+            }
+            self.set(x, y, z, block);
+            if x > 0 { stack.push((x - 1, y, z)); }
+            if x < self.width - 1 { stack.push((x + 1, y, z)); }
+            if z > 0 { stack.push((x, y, z - 1)); }
+            if z < self.length - 1 { stack.push((x, y, z + 1)); }
+            if y > 0 { stack.push((x, y - 1, z)); }
+        }
+    }
+
+    fn create_surface(&mut self) {
+        let sand = OctaveNoise::new(&mut self.random, 8);
+        let gravel = OctaveNoise::new(&mut self.random, 8);
+        for z in 0..self.length {
+            for x in 0..self.width {
+                let y = self.heightmap[x + z * self.width];
+                if y < 0 || y as usize >= self.height {
+                    continue;
+                }
+                let y = y as usize;
+                let above = if y + 1 >= self.height { Block::Air } else { self.get(x, y + 1, z) };
+                let (fx, fz) = (x as f64, z as f64);
+                if above == Block::StationaryWater && gravel.compute(fx, fz) > 12.0 {
+                    self.set(x, y, z, Block::Gravel);
+                } else if above == Block::Air {
+                    let beach = y as i32 <= self.water_level && sand.compute(fx, fz) > 8.0;
+                    self.set(x, y, z, if beach { Block::Sand } else { Block::GrassBlock });
+                }
+            }
+        }
+    }
+
+    fn plant_flowers(&mut self) {
+        let patches = self.width * self.length / 3000;
+        for _ in 0..patches {
+            let flower = if self.random.next_int(2) == 0 { Block::Dandelion } else { Block::Rose };
+            let patch_x = self.random_x();
+            let patch_z = self.random_z();
+            for _ in 0..10 {
+                let (mut x, mut z) = (patch_x, patch_z);
+                for _ in 0..5 {
+                    x += self.random_step();
+                    z += self.random_step();
+                    if !self.contains(x, 0, z) {
+                        continue;
+                    }
+                    let y = self.heightmap[x as usize + z as usize * self.width] + 1;
+                    if y <= 0 || y as usize >= self.height {
+                        continue;
+                    }
+                    let (x, y, z) = (x as usize, y as usize, z as usize);
+                    if self.get(x, y, z) == Block::Air && self.get(x, y - 1, z) == Block::GrassBlock {
+                        self.set(x, y, z, flower);
+                    }
+                }
+            }
+        }
+    }
+
+    fn plant_mushrooms(&mut self) {
+        let patches = self.blocks.len() / 2000;
+        for _ in 0..patches {
+            let mushroom = if self.random.next_int(2) == 0 { Block::BrownMushroom } else { Block::RedMushroom };
+            let patch_x = self.random_x();
+            let patch_y = self.random.next_int(self.height as i32);
+            let patch_z = self.random_z();
+            for _ in 0..20 {
+                let (mut x, y, mut z) = (patch_x, patch_y, patch_z);
+                for _ in 0..5 {
+                    x += self.random_step();
+                    z += self.random_step();
+                    if !self.contains(x, 0, z) || y <= 0 {
+                        continue;
+                    }
+                    let solid_height = self.heightmap[x as usize + z as usize * self.width];
+                    if y >= solid_height - 1 {
+                        continue;
+                    }
+                    let (x, y, z) = (x as usize, y as usize, z as usize);
+                    if self.get(x, y, z) == Block::Air && self.get(x, y - 1, z) == Block::Stone {
+                        self.set(x, y, z, mushroom);
+                    }
+                }
+            }
+        }
+    }
+
+    fn plant_trees(&mut self) {
+        let patches = self.width * self.length / 4000;
+        for _ in 0..patches {
+            let patch_x = self.random_x();
+            let patch_z = self.random_z();
+            for _ in 0..20 {
+                let (mut x, mut z) = (patch_x, patch_z);
+                for _ in 0..20 {
+                    x += self.random_step();
+                    z += self.random_step();
+                    if !self.contains(x, 0, z) || self.random.next_float() >= 0.25 {
+                        continue;
+                    }
+                    let y = self.heightmap[x as usize + z as usize * self.width] + 1;
+                    if y <= 0 || y as usize >= self.height {
+                        continue;
+                    }
+                    let tree_height = 5 + self.random.next_int(3);
+                    if self.get(x as usize, y as usize - 1, z as usize) == Block::GrassBlock &&
+                        self.can_grow_tree(x, y, z, tree_height) {
+                        self.grow_tree(x, y, z, tree_height);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Check there is room for the trunk and canopy of a tree
+    fn can_grow_tree(&self, x: i32, y: i32, z: i32, height: i32) -> bool {
+        tree::fits(x, y, z, height, |xx, yy, zz|
+            self.contains(xx, yy, zz) && self.get(xx as usize, yy as usize, zz as usize) == Block::Air
+        )
+    }
+
+    fn grow_tree(&mut self, x: i32, y: i32, z: i32, height: i32) {
+        for ([x, y, z], block) in tree::blocks(&mut self.random, x, y, z, height) {
+            self.set(x as usize, y as usize, z as usize, block);
+        }
+    }
+}
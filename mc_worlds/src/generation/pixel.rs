@@ -0,0 +1,68 @@
+//! # Pixel Art Generator
+//! A canvas for pixel art: a floor with walls around the edge of the map to build against
+
+use serde::{Deserialize, Serialize};
+
+use crate::classic::Block;
+use crate::progress::Progress;
+use super::{WorldGenerator, block_setting, fill_layers};
+
+pub const NAME: &str = "pixel";
+
+/// Parameters from `[map.generator.pixel]`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Block the walls are made of
+    pub wall: String,
+    /// Block the floor is made of
+    pub floor: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            wall: "white_cloth".to_string(),
+            floor: "bedrock".to_string(),
+        }
+    }
+}
+
+pub struct Pixel {
+    wall: Block,
+    floor: Block,
+}
+
+impl Pixel {
+    pub fn new(settings: &Settings) -> Result<Self, String> {
+        Ok(Self {
+            wall: block_setting("pixel wall", &settings.wall)?,
+            floor: block_setting("pixel floor", &settings.floor)?,
+        })
+    }
+}
+
+impl WorldGenerator for Pixel {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn seeded(&self) -> bool {
+        false
+    }
+
+    fn generate(&self, _seed: i64, x: usize, y: usize, z: usize, progress: &Progress) -> Vec<u8> {
+        fill_layers(x, y, z, progress, |height, layer| {
+            if height == 0 {
+                layer.fill(self.floor.into());
+                return;
+            }
+            for (i, block) in layer.iter_mut().enumerate() {
+                let (column_x, column_z) = (i % x, i / x);
+                if column_x == 0 || column_z == 0 || column_x == x - 1 || column_z == z - 1 {
+                    *block = self.wall.into();
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,50 @@
+//! # Random
+//! The linear congruential generator used by `java.util.Random`, which the classic level generator was
+//! written against. Using the same generator keeps maps reproducible from their seed.
+
+const MULTIPLIER: i64 = 0x5DEECE66D;
+const ADDEND: i64 = 0xB;
+const MASK: i64 = (1 << 48) - 1;
+
+#[derive(Debug, Clone)]
+pub struct JavaRandom {
+    seed: i64,
+}
+
+impl JavaRandom {
+    pub fn new(seed: i64) -> Self {
+        Self {
+            seed: (seed ^ MULTIPLIER) & MASK,
+        }
+    }
+
+    fn next(&mut self, bits: u32) -> i32 {
+        self.seed = (self.seed.wrapping_mul(MULTIPLIER).wrapping_add(ADDEND)) & MASK;
+        (self.seed >> (48 - bits)) as i32
+    }
+
+    /// A random integer in `0..bound`, `bound` must be positive
+    pub fn next_int(&mut self, bound: i32) -> i32 {
+        assert!(bound > 0, "bound must be positive");
+        if bound & -bound == bound {
+            return ((bound as i64 * self.next(31) as i64) >> 31) as i32;
+        }
+        loop {
+            let bits = self.next(31);
+            let value = bits % bound;
+            if bits.wrapping_sub(value).wrapping_add(bound - 1) >= 0 {
+                return value;
+            }
+        }
+    }
+
+    /// A random integer in `min..max`
+    pub fn next_range(&mut self, min: i32, max: i32) -> i32 {
+        min + self.next_int(max - min)
+    }
+
+    /// A random float in `0.0..1.0`
+    pub fn next_float(&mut self) -> f32 {
+        self.next(24) as f32 / (1 << 24) as f32
+    }
+}
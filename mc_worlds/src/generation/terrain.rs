@@ -0,0 +1,99 @@
+//! # Terrain
+//! Shared pieces of the heightmap based generators: a noise heightmap and column filling
+
+use crate::classic::Block;
+use super::random::JavaRandom;
+use crate::progress::Progress;
+use super::fill_layers;
+use super::noise::OctaveNoise;
+
+/// Octaves in [noise_map], the broadest covers 32 blocks at a scale of 1
+const OCTAVES: usize = 6;
+/// Standard deviations of noise that [noise_map] fits between -1 and 1
+const SPREAD: f64 = 2.5;
+
+/// Smooth noise for every column of a `width` by `length` map, centred on 0 and spread so nearly all of it is
+/// between -1 and 1. Larger scales make smaller hills
+pub fn noise_map(random: &mut JavaRandom, width: usize, length: usize, scale: f64) -> Vec<f64> {
+    let noise = OctaveNoise::new(random, OCTAVES);
+    let mut map = Vec::with_capacity(width * length);
+    for z in 0..length {
+        for x in 0..width {
+            map.push(noise.compute(x as f64 * scale, z as f64 * scale));
+        }
+    }
+    // The broad octaves barely change across a small map, so centre it on its own mean rather than 0
+    let mean = map.iter().sum::<f64>() / map.len() as f64;
+    let deviation = (map.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / map.len() as f64).sqrt().max(1e-9);
+    for value in &mut map {
+        *value = ((*value - mean) / (SPREAD * deviation)).clamp(-1.0, 1.0);
+    }
+    map
+}
+
+/// What the top of a column is made of: `surface` on top of `depth` blocks of `under`, with stone below
+#[derive(Copy, Clone)]
+pub struct Cover {
+    pub surface: Block,
+    pub under: Block,
+    pub depth: usize,
+}
+
+/// The heights and cover of every column of a map, turned into blocks a layer at a time
+pub struct Terrain {
+    width: usize,
+    height: usize,
+    length: usize,
+    columns: Vec<Column>,
+}
+
+#[derive(Copy, Clone)]
+struct Column {
+    top: usize,
+    cover: Cover,
+    water_level: usize,
+}
+
+impl Column {
+    fn block(&self, y: usize) -> Block {
+        if y == 0 {
+            Block::Bedrock
+        } else if y == self.top {
+            self.cover.surface
+        } else if y < self.top && y + self.cover.depth >= self.top {
+            self.cover.under
+        } else if y < self.top {
+            Block::Stone
+        } else if y < self.water_level {
+            Block::StationaryWater
+        } else {
+            Block::Air
+        }
+    }
+}
+
+impl Terrain {
+    pub fn new(width: usize, height: usize, length: usize) -> Self {
+        let empty = Column { top: 0, cover: Cover { surface: Block::Air, under: Block::Air, depth: 0 }, water_level: 0 };
+        Self { width, height, length, columns: vec![empty; width * length] }
+    }
+
+    /// Fill a column with bedrock at the bottom and `cover` up to `top`,
+    /// then water from above the surface up to `water_level`. Heights are clamped to the map
+    pub fn column(&mut self, x: usize, z: usize, top: i32, cover: &Cover, water_level: i32) {
+        self.columns[x + self.width * z] = Column {
+            top: top.min(self.height as i32 - 1).max(1) as usize,
+            cover: *cover,
+            water_level: water_level.max(0) as usize,
+        };
+    }
+
+    /// Build the blocks of every column, filling layers in parallel
+    pub fn into_blocks(self, progress: &Progress) -> Vec<u8> {
+        fill_layers(self.width, self.height, self.length, progress, |y, layer| {
+            for (block, column) in layer.iter_mut().zip(&self.columns) {
+                *block = column.block(y).into();
+            }
+        })
+    }
+}
@@ -0,0 +1,51 @@
+//! # Trees
+//! The trees of the classic level generator, also grown from saplings.
+//! A trunk of wood under two wide layers of leaves and two narrow ones, with corners left out at random
+
+use std::ops::Range;
+
+use crate::classic::Block;
+use super::random::JavaRandom;
+
+/// Whether there is room for a tree `height` blocks tall with its trunk starting at `x`, `y`, `z`.
+/// `is_clear` is asked about every block the trunk and leaves could fill
+pub fn fits(x: i32, y: i32, z: i32, height: i32, is_clear: impl Fn(i32, i32, i32) -> bool) -> bool {
+    let base_height = height - 4;
+    let clear = |radius: i32, ys: Range<i32>| {
+        ys.into_iter().all(|yy| (z - radius..=z + radius).all(|zz| (x - radius..=x + radius).all(|xx|
+            is_clear(xx, yy, zz)
+        )))
+    };
+    clear(1, y..y + base_height) && clear(2, y + base_height..y + height)
+}
+
+/// The blocks of a tree `height` blocks tall with its trunk starting at `x`, `y`, `z`,
+/// in the order they're placed. The trunk comes last, replacing the leaves it grows through
+pub fn blocks(random: &mut JavaRandom, x: i32, y: i32, z: i32, height: i32) -> Vec<([i32; 3], Block)> {
+    let mut blocks = vec![];
+    let top_start = y + height - 2;
+    for yy in y + height - 4..top_start {
+        for dz in -2i32..=2 {
+            for dx in -2i32..=2 {
+                // The corners of the lower leaves are left out at random
+                if dx.abs() == 2 && dz.abs() == 2 && random.next_float() < 0.5 {
+                    continue;
+                }
+                blocks.push(([x + dx, yy, z + dz], Block::Leaves));
+            }
+        }
+    }
+    for yy in top_start..y + height {
+        for dz in -1i32..=1 {
+            for dx in -1i32..=1 {
+                if dx == 0 || dz == 0 || (yy == top_start && random.next_float() >= 0.5) {
+                    blocks.push(([x + dx, yy, z + dz], Block::Leaves));
+                }
+            }
+        }
+    }
+    for yy in y..y + height - 1 {
+        blocks.push(([x, yy, z], Block::Wood));
+    }
+    blocks
+}
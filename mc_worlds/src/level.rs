@@ -0,0 +1,66 @@
+//! # Level
+//! The gzipped level stream sent to players when they enter a world, built once in the background
+//! and shared by every player who enters before the world changes
+
+use std::sync::Arc;
+use tokio::sync::watch;
+use tokio::task::spawn_blocking;
+use log::debug;
+
+use crate::compression::gzip_level;
+use crate::progress::Progress;
+
+/// A world's gzipped level as it was at one revision, which may still be being built
+#[derive(Clone)]
+pub struct LevelStream {
+    revision: u64,
+    level: watch::Receiver<Option<Arc<Vec<u8>>>>,
+    progress: Arc<Progress>,
+}
+
+impl LevelStream {
+    /// Gzip `blocks` from the world at `revision` on the blocking thread pool.
+    /// `layer` is the size of a layer of the map, this must be called from within a tokio runtime
+    pub fn build(revision: u64, blocks: Vec<u8>, layer: usize) -> Self {
+        let (sender, level) = watch::channel(None);
+        let progress = Arc::new(Progress::new());
+        let task_progress = progress.clone();
+        spawn_blocking(move || {
+            let gzipped = gzip_level(&blocks, layer, &task_progress);
+            task_progress.finish();
+            if sender.broadcast(Some(Arc::new(gzipped))).is_err() {
+                debug!("Nobody is waiting for the level built at revision {}", revision);
+            }
+        });
+        Self { revision, level, progress }
+    }
+
+    /// The revision of the world the level was taken from
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// The gzipped level, or None while it is still being built
+    pub fn get(&self) -> Option<Arc<Vec<u8>>> {
+        self.level.borrow().clone()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.level.borrow().is_some()
+    }
+
+    pub fn progress(&self) -> &Progress {
+        &self.progress
+    }
+
+    /// Wait for the level to be built, None if building it failed
+    pub async fn ready(&self) -> Option<Arc<Vec<u8>>> {
+        let mut level = self.level.clone();
+        while let Some(gzipped) = level.recv().await {
+            if gzipped.is_some() {
+                return gzipped;
+            }
+        }
+        None
+    }
+}
@@ -1 +1,9 @@
-pub mod classic;
\ No newline at end of file
+pub mod classic;
+pub mod crs;
+pub mod formats;
+pub mod generation;
+pub mod compression;
+pub mod level;
+pub mod progress;
+pub mod render;
+pub mod save;
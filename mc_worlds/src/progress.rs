@@ -0,0 +1,83 @@
+//! # Progress
+//! Counters shared with long running world jobs, so another task can report how far along they are
+
+use std::fmt;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Stage {
+    Waiting,
+    Generating,
+    Compressing,
+    Done,
+}
+
+impl From<u8> for Stage {
+    fn from(byte: u8) -> Self {
+        match byte {
+            1 => Self::Generating,
+            2 => Self::Compressing,
+            3 => Self::Done,
+            _ => Self::Waiting,
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::Waiting => write!(f, "Waiting"),
+            Stage::Generating => write!(f, "Generating"),
+            Stage::Compressing => write!(f, "Compressing"),
+            Stage::Done => write!(f, "Done"),
+        }
+    }
+}
+
+/// How far through its current [Stage] a job is, updated from any thread
+#[derive(Debug, Default)]
+pub struct Progress {
+    stage: AtomicU8,
+    done: AtomicUsize,
+    total: AtomicUsize,
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Begin `stage`, which takes `total` steps
+    pub fn start(&self, stage: Stage, total: usize) {
+        self.done.store(0, Ordering::Relaxed);
+        self.total.store(total, Ordering::Relaxed);
+        self.stage.store(stage as u8, Ordering::Release);
+    }
+
+    pub fn advance(&self, steps: usize) {
+        self.done.fetch_add(steps, Ordering::Relaxed);
+    }
+
+    pub fn finish(&self) {
+        self.start(Stage::Done, 0);
+    }
+
+    pub fn stage(&self) -> Stage {
+        self.stage.load(Ordering::Acquire).into()
+    }
+
+    /// How much of the current stage is done, from 0 to 1
+    pub fn fraction(&self) -> f32 {
+        let total = self.total.load(Ordering::Relaxed);
+        if total == 0 {
+            return 1.0;
+        }
+        (self.done.load(Ordering::Relaxed) as f32 / total as f32).min(1.0)
+    }
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {:.0}%", self.stage(), self.fraction() * 100.0)
+    }
+}
@@ -0,0 +1,292 @@
+//! # Render
+//! Pictures of worlds, either looking straight down with higher ground lighter and slopes shaded,
+//! or an isometric view from above the corner at the far end of the x and z axes.
+//!
+//! Blocks are coloured by [colour], glass, water, leaves and plants are see through so whatever
+//! is behind them shows with their colour over it. Images are saved with [Image::to_png].
+//! Neither is drawn with more than [MAX_PIXELS] pixels
+
+use rayon::prelude::*;
+
+use crate::classic::{Block, ClassicWorld};
+
+pub mod png;
+
+/// Most pixels an image is drawn with, 256 megabytes of RGBA
+pub const MAX_PIXELS: usize = 1 << 26;
+
+/// An RGBA image
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    /// Red, green, blue and alpha of each pixel, row by row from the top left
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    /// A fully transparent image
+    pub fn new(width: usize, height: usize) -> Self {
+        Self { width, height, pixels: vec![0; width * height * 4] }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        let i = (x + y * self.width) * 4;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2], self.pixels[i + 3]]
+    }
+
+    /// Put `colour` over the pixel at `x`, `y`, letting the pixel show through as much as `colour` is transparent
+    fn blend(&mut self, x: usize, y: usize, colour: [u8; 4]) {
+        let i = (x + y * self.width) * 4;
+        let pixel: &mut [u8] = &mut self.pixels[i..i + 4];
+        let (alpha, under) = (colour[3] as u32, pixel[3] as u32 * (255 - colour[3] as u32) / 255);
+        let out = alpha + under;
+        if out == 0 {
+            return;
+        }
+        for c in 0..3 {
+            pixel[c] = ((colour[c] as u32 * alpha + pixel[c] as u32 * under) / out) as u8;
+        }
+        pixel[3] = out as u8;
+    }
+
+    pub fn to_png(&self) -> Vec<u8> {
+        png::encode(self.width, self.height, &self.pixels)
+    }
+}
+
+/// Colour of a block as red, green, blue and alpha. Air is transparent and blocks that can be
+/// seen through, or that don't fill the whole block, are partly transparent
+pub fn colour(block: Block) -> [u8; 4] {
+    match block {
+        Block::Air => [0, 0, 0, 0],
+        Block::Stone => [125, 125, 125, 255],
+        Block::GrassBlock => [110, 170, 70, 255],
+        Block::Dirt => [134, 96, 67, 255],
+        Block::Cobblestone => [112, 112, 112, 255],
+        Block::Planks => [157, 128, 79, 255],
+        Block::Sapling => [70, 130, 40, 140],
+        Block::Bedrock => [70, 70, 70, 255],
+        Block::FlowingWater | Block::StationaryWater => [45, 80, 200, 150],
+        Block::FlowingLava | Block::StationaryLava => [210, 90, 20, 255],
+        Block::Sand => [218, 210, 158, 255],
+        Block::Gravel => [136, 126, 126, 255],
+        Block::GoldOre => [150, 142, 105, 255],
+        Block::IronOre => [140, 128, 120, 255],
+        Block::CoalOre => [98, 98, 98, 255],
+        Block::Wood => [102, 81, 51, 255],
+        Block::Leaves => [55, 135, 35, 200],
+        Block::Sponge => [195, 195, 78, 255],
+        Block::Glass => [205, 232, 240, 70],
+        Block::RedCloth => [222, 50, 50, 255],
+        Block::OrangeCloth => [222, 136, 50, 255],
+        Block::YellowCloth => [222, 222, 50, 255],
+        Block::ChartreuseCloth => [136, 222, 50, 255],
+        Block::GreenCloth => [50, 222, 50, 255],
+        Block::SpringGreenCloth => [50, 222, 136, 255],
+        Block::CyanCloth => [50, 222, 222, 255],
+        Block::CapriCloth => [104, 163, 222, 255],
+        Block::UltramarineCloth => [119, 119, 222, 255],
+        Block::VioletCloth => [136, 50, 222, 255],
+        Block::PurpleCloth => [174, 74, 222, 255],
+        Block::MagentaCloth => [222, 50, 222, 255],
+        Block::RoseCloth => [222, 50, 136, 255],
+        Block::DarkGrayCloth => [77, 77, 77, 255],
+        Block::LightGrayCloth => [153, 153, 153, 255],
+        Block::WhiteCloth => [222, 222, 222, 255],
+        Block::Dandelion => [230, 210, 30, 140],
+        Block::Rose => [200, 20, 20, 140],
+        Block::BrownMushroom => [145, 110, 85, 140],
+        Block::RedMushroom => [190, 40, 40, 140],
+        Block::GoldBlock => [245, 205, 60, 255],
+        Block::IronBlock => [220, 220, 220, 255],
+        Block::DoubleSlab | Block::Slab => [168, 168, 168, 255],
+        Block::Bricks => [150, 74, 58, 255],
+        Block::TNT => [200, 60, 40, 255],
+        Block::Bookshelf => [110, 90, 55, 255],
+        Block::MossyCobblestone => [90, 110, 90, 255],
+        Block::Obsidian => [22, 18, 32, 255],
+    }
+}
+
+fn is_opaque(block: u8) -> bool {
+    colour(Block::from(block))[3] == 255
+}
+
+/// Multiply the red, green and blue of a colour by `factor`
+fn shade(colour: [u8; 4], factor: f32) -> [u8; 4] {
+    let channel = |c: u8| (c as f32 * factor).round().clamp(0.0, 255.0) as u8;
+    [channel(colour[0]), channel(colour[1]), channel(colour[2]), colour[3]]
+}
+
+/// The world from above, one pixel per column with x across and z down. Higher ground is lighter,
+/// ground higher than the column to its north lighter still and ground lower than it darker.
+/// `None` if the world is more than [MAX_PIXELS] columns
+pub fn top_down(world: &ClassicWorld) -> Option<Image> {
+    let [x, y, z] = world.get_size();
+    if x * z > MAX_PIXELS {
+        return None;
+    }
+    let blocks = world.get_blocks();
+    let index = |bx: usize, by: usize, bz: usize| bx + x * (bz + z * by);
+    // Height of the highest block in each column that can't be seen through
+    let surface: Vec<Option<usize>> = (0..x * z).into_par_iter()
+        .map(|column| (0..y).rev().find(|&by| is_opaque(blocks[index(column % x, by, column / x)])))
+        .collect();
+
+    let mut image = Image::new(x, z);
+    if x == 0 {
+        return Some(image);
+    }
+    image.pixels.par_chunks_mut(x * 4).enumerate().for_each(|(bz, row)| {
+        for bx in 0..x {
+            let relief = match (surface[bx + x * bz], bz.checked_sub(1).and_then(|north| surface[bx + x * north])) {
+                (Some(height), Some(north)) if height > north => 1.1,
+                (Some(height), Some(north)) if height < north => 0.85,
+                _ => 1.0,
+            };
+            // Blend the blocks from the top down until nothing more would show through
+            let (mut rgb, mut alpha) = ([0f32; 3], 0f32);
+            for by in (0..y).rev() {
+                let block = colour(Block::from(blocks[index(bx, by, bz)]));
+                if block[3] == 0 {
+                    continue;
+                }
+                let mut factor = 0.6 + 0.4 * (by + 1) as f32 / y as f32;
+                if block[3] == 255 {
+                    factor *= relief;
+                }
+                let block = shade(block, factor);
+                let weight = (1.0 - alpha) * block[3] as f32 / 255.0;
+                for c in 0..3 {
+                    rgb[c] += weight * block[c] as f32;
+                }
+                alpha += weight;
+                if alpha > 0.99 {
+                    break;
+                }
+            }
+            if alpha > 0.0 {
+                let pixel = &mut row[bx * 4..bx * 4 + 4];
+                for c in 0..3 {
+                    pixel[c] = (rgb[c] / alpha).round().min(255.0) as u8;
+                }
+                pixel[3] = (alpha * 255.0).round() as u8;
+            }
+        }
+    });
+    Some(image)
+}
+
+/// Faces of a block seen in the isometric view
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Face {
+    Top,
+    /// The side facing +z
+    Left,
+    /// The side facing +x
+    Right,
+}
+
+/// Which face each pixel of a block's `2s` by `2s` sprite shows, row by row, None outside the block
+fn sprite(s: usize) -> Vec<Option<Face>> {
+    let size = 2 * s;
+    (0..size * size).map(|i| {
+        let (u, v) = ((i % size) as f32 + 0.5, (i / size) as f32 + 0.5);
+        // The outline is a hexagon, the top face a diamond in its upper half
+        let slope = (u - s as f32).abs() / 2.0;
+        if v < slope || v > size as f32 - slope {
+            None
+        } else if v <= s as f32 - slope {
+            Some(Face::Top)
+        } else if u < s as f32 {
+            Some(Face::Left)
+        } else {
+            Some(Face::Right)
+        }
+    }).collect()
+}
+
+/// Size of [isometric] images of a world `size` big
+pub fn isometric_size(size: [usize; 3], scale: usize) -> (usize, usize) {
+    let [x, y, z] = size;
+    let s = 2 * scale.max(1);
+    ((x + z) * s, (x + z) * s / 2 + y * s)
+}
+
+/// The largest scale up to `scale` that keeps [isometric] images of a world `size` big within [MAX_PIXELS],
+/// `None` if even a scale of 1 is too big
+pub fn isometric_scale(size: [usize; 3], scale: usize) -> Option<usize> {
+    let [x, y, z] = size;
+    // Images are 2s(x + z) by s(x + z + 2y) pixels
+    let pixels_per_scale = 2 * (x + z) * (x + z + 2 * y);
+    if pixels_per_scale == 0 {
+        return Some(scale.max(1));
+    }
+    let largest = ((MAX_PIXELS / pixels_per_scale) as f64).sqrt() as usize;
+    Some(scale.max(1).min(largest)).filter(|&scale| scale > 0)
+}
+
+/// The world seen from above its corner at the far end of the x and z axes, with each block `4 * scale`
+/// pixels wide, or less if that would be more than [MAX_PIXELS]. Tops of blocks are lit the most and
+/// their sides facing x the least. `None` if the world is too big to draw at all
+pub fn isometric(world: &ClassicWorld, scale: usize) -> Option<Image> {
+    let [x, y, z] = world.get_size();
+    let scale = isometric_scale([x, y, z], scale)?;
+    let s = 2 * scale.max(1);
+    let (width, height) = isometric_size([x, y, z], scale);
+    let mut image = Image::new(width, height);
+    let blocks = world.get_blocks();
+    let index = |bx: usize, by: usize, bz: usize| bx + x * (bz + z * by);
+    let sprite = sprite(s);
+
+    // Blocks further along each axis are in front so they're drawn after, over those behind them
+    for by in 0..y {
+        for bz in 0..z {
+            for bx in 0..x {
+                let block = blocks[index(bx, by, bz)];
+                let base = colour(Block::from(block));
+                if base[3] == 0 {
+                    continue;
+                }
+                // A face is hidden by a neighbour that can't be seen through, or by more of the same block
+                let shows = |neighbour: Option<u8>| match neighbour {
+                    Some(n) => !is_opaque(n) && colour(Block::from(n)) != base,
+                    None => true,
+                };
+                let faces = [
+                    (Face::Top, shows((by + 1 < y).then(|| blocks[index(bx, by + 1, bz)])), 1.0),
+                    (Face::Left, shows((bz + 1 < z).then(|| blocks[index(bx, by, bz + 1)])), 0.8),
+                    (Face::Right, shows((bx + 1 < x).then(|| blocks[index(bx + 1, by, bz)])), 0.64),
+                ];
+                if faces.iter().all(|(_, shows, _)| !shows) {
+                    continue;
+                }
+                let px = (bx + z - 1 - bz) * s;
+                let py = (bx + bz) * s / 2 + (y - 1 - by) * s;
+                for (i, face) in sprite.iter().enumerate() {
+                    let face = match face {
+                        Some(face) => face,
+                        None => continue,
+                    };
+                    if let Some((_, true, factor)) = faces.iter().find(|(f, ..)| f == face) {
+                        image.blend(px + i % (2 * s), py + i / (2 * s), shade(base, *factor));
+                    }
+                }
+            }
+        }
+    }
+    Some(image)
+}
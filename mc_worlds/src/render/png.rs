@@ -0,0 +1,55 @@
+//! # PNG
+//! Just enough of PNG to write 8 bit RGBA images, see https://www.w3.org/TR/png/.
+//!
+//! The file is the signature then chunks, each its length, type, data and a CRC of the type and data.
+//! IHDR holds the size and pixel format, IDAT the zlib compressed rows each starting with the filter
+//! used on it, and IEND ends the file
+
+use std::io::Write;
+use flate2::{Compression, Crc};
+use flate2::write::ZlibEncoder;
+
+pub const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+/// Bits per channel
+const BIT_DEPTH: u8 = 8;
+/// Colour type of pixels with red, green, blue and alpha
+const RGBA: u8 = 6;
+/// Filter that stores each byte as the difference from the byte of the pixel to its left
+pub const FILTER_SUB: u8 = 1;
+
+/// Encode a `width` by `height` image of RGBA `pixels`, row by row from the top left
+pub fn encode(width: usize, height: usize, pixels: &[u8]) -> Vec<u8> {
+    assert_eq!(pixels.len(), width * height * 4, "There must be 4 bytes for every pixel");
+    let mut png = SIGNATURE.to_vec();
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // Compression, filter and interlace methods are all the only ones there are or none
+    header.extend_from_slice(&[BIT_DEPTH, RGBA, 0, 0, 0]);
+    write_chunk(&mut png, b"IHDR", &header);
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    let mut filtered = Vec::with_capacity(width * 4 + 1);
+    for row in pixels.chunks(width.max(1) * 4) {
+        filtered.clear();
+        filtered.push(FILTER_SUB);
+        filtered.extend_from_slice(&row[..4]);
+        filtered.extend(row.windows(5).map(|w| w[4].wrapping_sub(w[0])));
+        encoder.write_all(&filtered).expect("Writing to a Vec can't fail");
+    }
+    let data = encoder.finish().expect("Writing to a Vec can't fail");
+    write_chunk(&mut png, b"IDAT", &data);
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let mut crc = Crc::new();
+    crc.update(kind);
+    crc.update(data);
+    png.extend_from_slice(&crc.sum().to_be_bytes());
+}
@@ -0,0 +1,92 @@
+//! # Save
+//! Writing world files so a crash part way through never leaves a broken file behind,
+//! and keeping a rotating set of timestamped backups of each world
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use tokio::fs::{self, File};
+use tokio::io::AsyncWriteExt;
+use tokio::stream::StreamExt;
+
+/// Format of the timestamp in backup file names, sorting the names sorts the backups oldest first.
+/// It goes down to the millisecond so backups taken in the same second don't replace each other
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d_%H-%M-%S-%3f";
+/// Format of the timestamp in the names of backups taken before milliseconds were added
+const SECONDS_FORMAT: &str = "%Y-%m-%d_%H-%M-%S";
+
+/// Replace the file at `path` with `contents`.
+///
+/// The contents are written to a temporary file next to it which is synced to disk before being
+/// renamed over the old file, so the file at `path` is always either the old or the new version
+pub async fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+
+    let mut file = File::create(&temp_path).await?;
+    file.write_all(contents).await?;
+    file.sync_all().await?;
+    drop(file);
+    fs::rename(&temp_path, path).await?;
+
+    // The rename itself only survives a crash once the directory holding it is synced
+    #[cfg(unix)]
+    if let Some(directory) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        File::open(directory).await?.sync_all().await?;
+    }
+    Ok(())
+}
+
+/// Name of the backup of `world` taken at `time`
+pub fn backup_name(world: &str, time: DateTime<Utc>) -> String {
+    format!("{}_{}.crs", world, time.format(TIMESTAMP_FORMAT))
+}
+
+/// When the backup called `file_name` was taken, None if it isn't a backup of `world`
+pub fn backup_time(world: &str, file_name: &str) -> Option<DateTime<Utc>> {
+    let timestamp = file_name.strip_prefix(world)?.strip_prefix('_')?.strip_suffix(".crs")?;
+    NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+        .or_else(|_| NaiveDateTime::parse_from_str(timestamp, SECONDS_FORMAT))
+        .ok()
+        .map(|t| Utc.from_utc_datetime(&t))
+}
+
+/// The backups of `world` in `directory`, oldest first
+pub async fn list_backups(directory: &Path, world: &str) -> io::Result<Vec<(DateTime<Utc>, PathBuf)>> {
+    let entries = match fs::read_dir(directory).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+    let paths: Vec<PathBuf> = entries.filter_map(|e| e.ok().map(|e| e.path())).collect().await;
+    let mut backups: Vec<(DateTime<Utc>, PathBuf)> = paths.into_iter()
+        .filter_map(|p| Some((backup_time(world, p.file_name()?.to_str()?)?, p)))
+        .collect();
+    backups.sort();
+    Ok(backups)
+}
+
+/// Delete the backups of `world` in `directory` beyond the newest `keep` or older than `max_age`,
+/// returning the paths deleted. A `keep` of 0 keeps any number and a `max_age` of None keeps them forever,
+/// but the newest backup is always kept
+pub async fn prune_backups(directory: &Path, world: &str, keep: usize, max_age: Option<Duration>)
+                           -> io::Result<Vec<PathBuf>> {
+    let mut backups = list_backups(directory, world).await?;
+    backups.pop();
+    let now = Utc::now();
+    let surplus = match keep {
+        0 => 0,
+        keep => (backups.len() + 1).saturating_sub(keep),
+    };
+    let mut pruned = vec![];
+    for (i, (time, path)) in backups.into_iter().enumerate() {
+        let expired = max_age.is_some_and(|age| (now - time).to_std().is_ok_and(|t| t > age));
+        if i < surplus || expired {
+            fs::remove_file(&path).await?;
+            pruned.push(path);
+        }
+    }
+    Ok(pruned)
+}
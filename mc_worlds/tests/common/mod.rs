@@ -0,0 +1,20 @@
+use mc_worlds::classic::{ClassicWorld, Block};
+use mc_worlds::generation::empty::Empty;
+
+/// An empty 16x16x16 world filled with stone below `height`
+pub fn stone_world(height: usize) -> ClassicWorld {
+    let mut world = ClassicWorld::generate("test", "tester", 16, 16, 16, &Empty, 0);
+    for y in 0..height {
+        for x in 0..16 {
+            for z in 0..16 {
+                world.set_block(x, y, z, Block::Stone);
+            }
+        }
+    }
+    world
+}
+
+/// Tick `world` `ticks` times, returning every block changed along the way
+pub fn run(world: &mut ClassicWorld, ticks: u64) -> Vec<(usize, usize, usize, Block)> {
+    (0..ticks).flat_map(|_| world.tick()).collect()
+}
@@ -0,0 +1,52 @@
+use std::io::Read;
+use flate2::read::GzDecoder;
+
+use mc_worlds::compression::{gzip_level, gzip_level_sequential};
+use mc_worlds::generation::WorldGenerator;
+use mc_worlds::generation::notch::NotchGenerator;
+use mc_worlds::progress::{Progress, Stage};
+
+fn gunzip(gzipped: &[u8]) -> Vec<u8> {
+    let mut decoder = GzDecoder::new(gzipped);
+    let mut level = vec![];
+    decoder.read_to_end(&mut level).expect("Not valid gzip");
+    // Anything after the first gzip member would be ignored by clients
+    assert_eq!(decoder.into_inner().len(), 0, "More than one gzip member");
+    level
+}
+
+fn level(blocks: &[u8]) -> Vec<u8> {
+    let mut level = (blocks.len() as u32).to_be_bytes().to_vec();
+    level.extend_from_slice(blocks);
+    level
+}
+
+#[test]
+fn parallel_gzip_matches_sequential() {
+    // 128 * 128 * 128 is 2MB so this is split into more than one slab
+    let blocks = NotchGenerator.generate(1234, 128, 128, 128, &Progress::new());
+    let progress = Progress::new();
+    let gzipped = gzip_level(&blocks, 128 * 128, &progress);
+
+    assert_eq!(gunzip(&gzipped), level(&blocks));
+    assert_eq!(gunzip(&gzipped), gunzip(&gzip_level_sequential(&blocks)));
+    assert_eq!(progress.stage(), Stage::Compressing);
+    assert_eq!(progress.fraction(), 1.0);
+}
+
+#[test]
+fn gzips_small_and_empty_levels() {
+    for blocks in [vec![], vec![7u8; 16], (0..=255u8).cycle().take(5000).collect::<Vec<u8>>()] {
+        assert_eq!(gunzip(&gzip_level(&blocks, 16, &Progress::new())), level(&blocks));
+    }
+}
+
+#[test]
+fn progress_reports_generation() {
+    let progress = Progress::new();
+    assert_eq!(progress.stage(), Stage::Waiting);
+    NotchGenerator.generate(1, 16, 16, 16, &progress);
+    assert_eq!(progress.stage(), Stage::Generating);
+    assert_eq!(progress.fraction(), 1.0);
+    assert_eq!(progress.to_string(), "Generating 100%");
+}
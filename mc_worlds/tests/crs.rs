@@ -0,0 +1,153 @@
+use std::fs;
+use std::sync::Arc;
+
+use mc_worlds::classic::{ClassicWorld, Block, Spawn, MapGenerator};
+use mc_worlds::crs::{self, CrsError, HEADER_LENGTH, MAX_RECORDS};
+use mc_worlds::generation::flatgrass::Flatgrass;
+use mc_worlds::generation::islands::Islands;
+
+const SPAWN: Spawn = Spawn { x: 100, y: 300, z: -5, h: 64, p: 200 };
+
+fn world() -> ClassicWorld {
+    let generator = Islands::new(&Default::default()).unwrap();
+    let mut world = ClassicWorld::generate("test", "tester", 32, 16, 48, &generator, 1234);
+    world.set_block(1, 2, 3, Block::Glass);
+    world.set_spawn(SPAWN);
+    world
+}
+
+/// A file from before CRS files had a header, raw blocks followed by a spawn record
+fn legacy_file(blocks: &[u8]) -> Vec<u8> {
+    let mut bytes = blocks.to_vec();
+    bytes.extend_from_slice(b"SPWN");
+    for position in [SPAWN.x, SPAWN.y, SPAWN.z] {
+        bytes.extend_from_slice(&position.to_be_bytes());
+    }
+    bytes.extend_from_slice(&[SPAWN.h, SPAWN.p]);
+    bytes
+}
+
+#[test]
+fn world_survives_a_round_trip() {
+    let world = world();
+    let bytes = world.to_crs_bytes();
+    assert!(crs::is_crs(&bytes));
+    assert!(bytes.len() < world.get_blocks().len() / 2, "Blocks aren't compressed");
+
+    // The size comes from the header, not the size given for old files
+    let loaded = ClassicWorld::from_crs_bytes("test", "tester", &bytes, [16, 16, 16]).unwrap();
+    assert_eq!(loaded.get_size(), [32, 16, 48]);
+    assert_eq!(loaded.get_blocks(), world.get_blocks());
+    assert_eq!(loaded.get_spawn(), SPAWN);
+    assert_eq!(loaded.get_map_generator(), Some(&MapGenerator::new("islands", Some(1234))));
+    // Along with everything else in the header
+    assert_eq!(loaded.to_crs_bytes(), bytes);
+}
+
+#[test]
+fn header_describes_the_world() {
+    let world = world();
+    let (header, body) = crs::decode(&world.to_crs_bytes()).unwrap();
+    assert_eq!(header.version, crs::VERSION);
+    assert_eq!([header.x, header.y, header.z], [32, 16, 48]);
+    assert_eq!(header.spawn, SPAWN);
+    assert!(header.time_created > 0 && header.last_modified >= header.time_created);
+    assert_eq!(&body[..header.volume()], world.get_blocks().as_slice());
+}
+
+#[test]
+fn truncated_files_are_rejected() {
+    let bytes = world().to_crs_bytes();
+    for length in [0, 10, HEADER_LENGTH, bytes.len() - 1] {
+        let result = ClassicWorld::from_crs_bytes("test", "tester", &bytes[..length], [32, 16, 48]);
+        assert!(matches!(result, Err(CrsError::Length(..))), "{} bytes were accepted", length);
+    }
+    let mut longer = bytes.clone();
+    longer.push(0);
+    assert!(matches!(crs::decode(&longer), Err(CrsError::Length(..))));
+}
+
+#[test]
+fn corrupt_files_are_rejected() {
+    let bytes = world().to_crs_bytes();
+    // A bit flipped in the size, the spawn and the compressed blocks
+    for position in [6, 12, bytes.len() - 20] {
+        let mut corrupt = bytes.clone();
+        corrupt[position] ^= 0x10;
+        assert!(matches!(crs::decode(&corrupt), Err(CrsError::Checksum(..))), "Byte {} wasn't checked", position);
+    }
+    let mut newer = bytes;
+    newer[4] = crs::VERSION + 1;
+    assert!(matches!(crs::decode(&newer), Err(CrsError::Version(_))));
+}
+
+#[test]
+fn mismatched_header_is_rejected() {
+    let world = world();
+    let (mut header, body) = crs::decode(&world.to_crs_bytes()).unwrap();
+    header.y = 32;
+    let bytes = crs::encode(&header, &body);
+    assert!(matches!(crs::decode(&bytes), Err(CrsError::Size([32, 32, 48], _))));
+
+    header.y = 8;
+    let bytes = crs::encode(&header, &body);
+    assert!(matches!(ClassicWorld::from_crs_bytes("test", "tester", &bytes, [32, 8, 48]),
+                     Err(CrsError::Trailing(_))));
+}
+
+#[test]
+fn huge_sizes_are_rejected() {
+    let world = world();
+    let (mut header, body) = crs::decode(&world.to_crs_bytes()).unwrap();
+    // The checksum is right, only the size is wrong
+    for size in [32767, 65535] {
+        header.x = size;
+        header.y = size;
+        header.z = size;
+        assert!(matches!(crs::decode(&crs::encode(&header, &body)), Err(CrsError::Size(..))), "{} was accepted", size);
+    }
+
+    // Records that decompress to more than there's room for
+    let (header, mut body) = crs::decode(&world.to_crs_bytes()).unwrap();
+    body.resize(header.volume() + MAX_RECORDS + 1, 0);
+    assert!(matches!(crs::decode(&crs::encode(&header, &body)), Err(CrsError::Trailing(_))));
+}
+
+#[test]
+fn legacy_files_load() {
+    let blocks = world().get_blocks().clone();
+    let bytes = legacy_file(&blocks);
+    assert!(!crs::is_crs(&bytes));
+
+    let loaded = ClassicWorld::from_crs_bytes("test", "tester", &bytes, [32, 16, 48]).unwrap();
+    assert_eq!(loaded.get_blocks(), &blocks);
+    assert_eq!(loaded.get_spawn(), SPAWN);
+
+    // Read with the wrong size they either run out of blocks or have blocks left over
+    assert!(matches!(ClassicWorld::from_crs_bytes("test", "tester", &bytes, [32, 32, 48]),
+                     Err(CrsError::Length(..))));
+    assert!(matches!(ClassicWorld::from_crs_bytes("test", "tester", &bytes, [32, 8, 48]),
+                     Err(CrsError::Trailing(_))));
+}
+
+#[tokio::test]
+async fn legacy_files_are_migrated() {
+    let directory = tempfile::tempdir().unwrap();
+    let blocks = world().get_blocks().clone();
+    let legacy = legacy_file(&blocks);
+    fs::write(directory.path().join("test.crs"), &legacy).unwrap();
+
+    let world = ClassicWorld::get_or_create(directory.path(), "test", "tester", 32, 16, 48,
+                                            Arc::new(Flatgrass::default()), 0).await.unwrap();
+    assert_eq!(world.get_blocks(), &blocks);
+
+    let saved = fs::read(directory.path().join("test.crs")).unwrap();
+    assert!(crs::is_crs(&saved));
+    assert_eq!(fs::read(directory.path().join("test.crs.v1")).unwrap(), legacy);
+
+    // Once migrated the configured size no longer matters
+    let world = ClassicWorld::get_or_create(directory.path(), "test", "tester", 64, 64, 64,
+                                            Arc::new(Flatgrass::default()), 0).await.unwrap();
+    assert_eq!(world.get_size(), [32, 16, 48]);
+    assert_eq!(world.get_spawn(), SPAWN);
+}
@@ -0,0 +1,74 @@
+use std::collections::HashSet;
+
+use mc_worlds::classic::Block;
+use mc_worlds::classic::explosion::{FUSE, CHAIN_FUSE};
+
+mod common;
+use common::{stone_world, run};
+
+#[test]
+fn tnt_does_nothing_until_turned_on() {
+    let mut world = stone_world(8);
+    world.set_block(8, 8, 8, Block::TNT);
+    assert!(!world.prime(8, 8, 8));
+    assert!(run(&mut world, FUSE * 2).is_empty());
+
+    world.set_tnt(Some(3));
+    assert_eq!(world.tnt_radius(), Some(3));
+    assert!(!world.prime(8, 7, 8));
+    assert!(!world.prime(16, 8, 8));
+    assert!(!world.prime(8, usize::MAX, 8));
+    assert!(world.prime(8, 8, 8));
+    assert!(run(&mut world, FUSE - 1).is_empty());
+    assert!(!run(&mut world, 1).is_empty());
+    assert_eq!(world.get_block(8, 8, 8), Block::Air);
+}
+
+#[test]
+fn explosions_clear_a_sphere_except_bedrock_and_obsidian() {
+    let mut world = stone_world(8);
+    world.set_tnt(Some(3));
+    world.set_block(8, 7, 8, Block::TNT);
+    world.set_block(8, 6, 8, Block::Obsidian);
+    world.set_block(9, 7, 8, Block::Bedrock);
+    world.prime(8, 7, 8);
+    let changes = run(&mut world, FUSE);
+
+    assert_eq!(world.get_block(8, 7, 8), Block::Air);
+    assert_eq!(world.get_block(8, 4, 8), Block::Air);
+    assert_eq!(world.get_block(8, 3, 8), Block::Stone);
+    assert_eq!(world.get_block(5, 7, 8), Block::Air);
+    assert_eq!(world.get_block(10, 5, 8), Block::Air);
+    assert_eq!(world.get_block(10, 5, 10), Block::Stone);
+    assert_eq!(world.get_block(8, 6, 8), Block::Obsidian);
+    assert_eq!(world.get_block(9, 7, 8), Block::Bedrock);
+
+    // Every block that changed is sent once, and none that were already air
+    let positions: HashSet<_> = changes.iter().map(|(x, y, z, _)| (*x, *y, *z)).collect();
+    assert_eq!(positions.len(), changes.len());
+    assert!(changes.iter().all(|change| change.3 == Block::Air));
+    assert!(!positions.contains(&(8, 9, 8)));
+}
+
+#[test]
+fn lava_lights_tnt_and_explosions_chain() {
+    let mut world = stone_world(8);
+    world.set_tnt(Some(2));
+    world.set_block(4, 8, 4, Block::TNT);
+    world.set_block(6, 8, 4, Block::TNT);
+    world.set_block(12, 8, 4, Block::TNT);
+    world.set_block(4, 9, 4, Block::StationaryLava);
+    run(&mut world, FUSE);
+    assert_eq!(world.get_block(4, 8, 4), Block::Air);
+    assert_eq!(world.get_block(6, 8, 4), Block::TNT);
+    run(&mut world, CHAIN_FUSE * 2);
+    assert_eq!(world.get_block(6, 8, 4), Block::Air);
+    assert_eq!(world.get_block(12, 8, 4), Block::TNT);
+
+    // Turning TNT off puts out lit fuses
+    world.prime(12, 8, 4);
+    world.set_tnt(None);
+    world.set_tnt(Some(2));
+    assert!(run(&mut world, FUSE * 2).is_empty());
+    assert_eq!(world.get_block(12, 8, 4), Block::TNT);
+}
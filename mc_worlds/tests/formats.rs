@@ -0,0 +1,369 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+
+use mc_worlds::classic::{ClassicWorld, Block, MapGenerator, Spawn};
+use mc_worlds::crs::CrsError;
+use mc_worlds::formats::{Format, FormatError, cw, dat, fcm, lvl, mclevel};
+use mc_worlds::generation::flatgrass::Flatgrass;
+use mc_worlds::generation::surface_spawn;
+
+/// Spawn in the middle of a block with the player's eyes above its feet, the only spawns .lvl files keep
+const SPAWN: Spawn = Spawn { x: 5 * 32 + 16, y: 9 * 32 + 51, z: 20 * 32 + 16, h: 64, p: 200 };
+
+fn world() -> ClassicWorld {
+    let generator = Flatgrass::default();
+    let mut world = ClassicWorld::generate("test", "tester", 32, 16, 48, &generator, 1234);
+    world.set_block(1, 2, 3, Block::Glass);
+    world.set_block(31, 15, 47, Block::Obsidian);
+    world.set_spawn(SPAWN);
+    world
+}
+
+/// A .lvl file of a 16x16x16 map of `blocks`, followed by `extra`
+fn lvl_file(blocks: &[u8], extra: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    for short in [lvl::VERSION, 16, 16, 16, 8, 8, 8] {
+        encoder.write_all(&short.to_le_bytes()).unwrap();
+    }
+    encoder.write_all(&[0, 0, 1, 2]).unwrap();
+    encoder.write_all(blocks).unwrap();
+    encoder.write_all(extra).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[test]
+fn fcm_round_trip() {
+    let mut world = world();
+    world.metadata_mut().set("fCraft.Permissions", "build", "builder");
+    world.metadata_mut().set("fCraft.Environment", "sky", "-1");
+    let bytes = fcm::write(&world).unwrap();
+    assert_eq!(bytes[..4], fcm::IDENTIFIER.to_le_bytes());
+
+    let (loaded, remapped) = fcm::read(&bytes, "test", "tester").unwrap();
+    assert!(remapped.is_empty());
+    assert_eq!(loaded.get_size(), [32, 16, 48]);
+    assert_eq!(loaded.get_blocks(), world.get_blocks());
+    assert_eq!(loaded.get_spawn(), SPAWN);
+    assert_eq!(loaded.get_metadata(), world.get_metadata());
+    let (header, loaded_header) = (world.get_header(), loaded.get_header());
+    assert_eq!(loaded_header.uuid, header.uuid);
+    assert_eq!(loaded_header.time_created, header.time_created);
+}
+
+#[test]
+fn lvl_round_trip() {
+    let mut world = world();
+    world.metadata_mut().set(lvl::METADATA_GROUP, "build_permission", "80");
+    let bytes = lvl::write(&world).unwrap();
+
+    let (loaded, _) = lvl::read(&bytes, "test", "tester").unwrap();
+    assert_eq!(loaded.get_size(), [32, 16, 48]);
+    assert_eq!(loaded.get_blocks(), world.get_blocks());
+    assert_eq!(loaded.get_spawn(), SPAWN);
+    assert_eq!(loaded.get_metadata().get(lvl::METADATA_GROUP, "build_permission"), Some("80"));
+    assert_eq!(loaded.get_metadata().get(lvl::METADATA_GROUP, "visit_permission"), Some("0"));
+}
+
+#[test]
+fn lvl_custom_blocks_fall_back_to_classic() {
+    let mut blocks = vec![Block::Stone as u8; 16 * 16 * 16];
+    blocks[0] = 163;
+    blocks[1] = 163;
+    blocks[2] = 60;
+    // An id from a physics block
+    blocks[3] = 200;
+    // A custom block sharing its id with an op block
+    blocks[4] = 163;
+    let mut custom = vec![0xBD, 1];
+    let mut ids = vec![0u8; 16 * 16 * 16];
+    ids[0] = 55;
+    ids[1] = 240;
+    ids[4] = 100;
+    custom.extend_from_slice(&ids);
+
+    let (mut loaded, remapped) = lvl::read(&lvl_file(&blocks, &custom), "test", "tester").unwrap();
+    assert_eq!(loaded.get_block(0, 0, 0), Block::RoseCloth);
+    assert_eq!(loaded.get_block(1, 0, 0), Block::Stone);
+    assert_eq!(loaded.get_block(2, 0, 0), Block::Glass);
+    assert_eq!(loaded.get_block(3, 0, 0), Block::Stone);
+    assert_eq!(loaded.get_block(4, 0, 0), Block::Stone);
+    assert!(loaded.get_blocks().iter().all(|b| *b <= Block::MAX_ID));
+    assert_eq!(remapped.get_name("custom 55"), Some((Block::RoseCloth, 1)));
+    assert_eq!(remapped.get_name("custom 240"), Some((Block::Stone, 1)));
+    assert_eq!(remapped.get_name("custom 100"), Some((Block::Stone, 1)));
+    assert_eq!(remapped.get(60), Some((Block::Glass, 1)));
+    assert_eq!(remapped.get(100), None);
+    assert_eq!(remapped.get(163), None);
+    assert_eq!(remapped.total(), 5);
+    // Spawn in block coordinates becomes fixed point
+    assert_eq!(loaded.get_spawn(), Spawn { x: 8 * 32 + 16, y: 8 * 32 + 51, z: 8 * 32 + 16, h: 0, p: 0 });
+}
+
+#[test]
+fn bad_files_are_rejected() {
+    let world = world();
+    let fcm = fcm::write(&world).unwrap();
+    assert!(matches!(fcm::read(&fcm[..2], "test", "tester"), Err(FormatError::Identifier)));
+    assert!(matches!(fcm::read(b"not a map at all", "test", "tester"), Err(FormatError::Identifier)));
+    let mut old = fcm.clone();
+    old[4] = 12;
+    assert!(matches!(fcm::read(&old, "test", "tester"), Err(FormatError::Version(12))));
+    assert!(matches!(fcm::read(&fcm[..fcm.len() / 2], "test", "tester"), Err(FormatError::Truncated)));
+
+    let blocks = vec![0u8; 16 * 16 * 16];
+    assert!(matches!(lvl::read(&fcm, "test", "tester"), Err(FormatError::Identifier)));
+    let truncated = lvl_file(&blocks[..100], &[]);
+    assert!(matches!(lvl::read(&truncated, "test", "tester"), Err(FormatError::Truncated)));
+
+    let big = ClassicWorld::new("big", "tester", 70000, 1, 1);
+    assert!(matches!(Format::Fcm.write(&big), Err(FormatError::Size([70000, 1, 1]))));
+}
+
+#[test]
+fn huge_sizes_are_rejected() {
+    let mut fcm = fcm::write(&world()).unwrap();
+    fcm[5..11].copy_from_slice(&[0xFF; 6]);
+    assert!(matches!(fcm::read(&fcm, "test", "tester"), Err(FormatError::Size([65535, 65535, 65535]))));
+
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    for short in [lvl::VERSION, 4096, 4096, 4096, 8, 8, 8] {
+        encoder.write_all(&short.to_le_bytes()).unwrap();
+    }
+    encoder.write_all(&[0, 0, 1, 2]).unwrap();
+    let lvl = encoder.finish().unwrap();
+    assert!(matches!(lvl::read(&lvl, "test", "tester"), Err(FormatError::Size([4096, 4096, 4096]))));
+}
+
+#[test]
+fn mcsharp_lvl_without_version() {
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    for short in [16u16, 16, 16, 2, 3, 4] {
+        encoder.write_all(&short.to_le_bytes()).unwrap();
+    }
+    encoder.write_all(&[32, 0]).unwrap();
+    let mut blocks = vec![Block::Air as u8; 16 * 16 * 16];
+    // An op glass and a door
+    blocks[0] = 100;
+    blocks[1] = 118;
+    blocks[2] = 118;
+    encoder.write_all(&blocks).unwrap();
+
+    let (mut loaded, remapped) = lvl::read(&encoder.finish().unwrap(), "test", "tester").unwrap();
+    assert_eq!(loaded.get_size(), [16, 16, 16]);
+    assert_eq!(loaded.get_block(0, 0, 0), Block::Glass);
+    assert_eq!(loaded.get_block(1, 0, 0), Block::Planks);
+    assert_eq!(loaded.get_spawn(), Spawn { x: 2 * 32 + 16, y: 4 * 32 + 51, z: 3 * 32 + 16, h: 32, p: 0 });
+    assert_eq!(remapped.to_string(), "1 100 to Glass, 2 118 to Planks");
+    assert!(loaded.get_metadata().is_empty());
+}
+
+#[test]
+fn mclevel_round_trip() {
+    let mut world = world();
+    world.metadata_mut().set(mclevel::METADATA_GROUP, "author", "notch");
+    let bytes = Format::Mclevel.write(&world).unwrap();
+
+    let (loaded, remapped) = Format::Mclevel.read(&bytes, "test", "tester").unwrap();
+    assert!(remapped.is_empty());
+    assert_eq!(loaded.get_size(), [32, 16, 48]);
+    assert_eq!(loaded.get_blocks(), world.get_blocks());
+    assert_eq!(loaded.get_spawn(), Spawn { h: 0, p: 0, ..SPAWN });
+    assert_eq!(loaded.get_header().time_created, world.get_header().time_created);
+    assert_eq!(loaded.get_metadata().get(mclevel::METADATA_GROUP, "author"), Some("notch"));
+    assert_eq!(loaded.get_metadata().get(mclevel::METADATA_GROUP, "name"), Some("test"));
+}
+
+#[test]
+fn indev_blocks_are_remapped() {
+    let mut blocks = vec![0i8; 4 * 4 * 4];
+    // A torch, a chest and ice
+    blocks[0] = 50;
+    blocks[1] = 54;
+    blocks[2] = 79;
+    blocks[3] = Block::Obsidian as i8;
+    let mut map = nbt::Map::new();
+    for (name, size) in [("Width", 4), ("Length", 4), ("Height", 4)] {
+        map.insert(name.to_string(), nbt::Value::Short(size));
+    }
+    map.insert("Spawn".to_string(), nbt::Value::List(vec![nbt::Value::Short(1); 3]));
+    map.insert("Blocks".to_string(), nbt::Value::ByteArray(blocks));
+    let mut blob = nbt::Blob::named("MinecraftLevel");
+    blob.insert("Map", nbt::Value::Compound(map)).unwrap();
+    let mut bytes = vec![];
+    blob.to_gzip_writer(&mut bytes).unwrap();
+
+    let (mut loaded, remapped) = mclevel::read(&bytes, "test", "tester").unwrap();
+    assert_eq!(loaded.get_block(0, 0, 0), Block::Air);
+    assert_eq!(loaded.get_block(1, 0, 0), Block::Planks);
+    assert_eq!(loaded.get_block(2, 0, 0), Block::Glass);
+    assert_eq!(loaded.get_block(3, 0, 0), Block::Obsidian);
+    assert_eq!(remapped.iter().collect::<Vec<_>>(),
+               vec![(50, Block::Air, 1), (54, Block::Planks, 1), (79, Block::Glass, 1)]);
+
+    let mut missing = nbt::Blob::named("MinecraftLevel");
+    missing.insert("About", nbt::Value::Compound(nbt::Map::new())).unwrap();
+    let mut bytes = vec![];
+    missing.to_gzip_writer(&mut bytes).unwrap();
+    assert!(matches!(mclevel::read(&bytes, "test", "tester"), Err(FormatError::Missing("Map"))));
+}
+
+#[test]
+fn cw_round_trip() {
+    let mut world = world();
+    world.metadata_mut().set("CPE", "EnvColors.Sky", "255");
+    world.set_map_generator(Some(MapGenerator { service: "Classic-RS".to_string(), name: "flatgrass".to_string(),
+                                                seed: None }));
+    let bytes = Format::Cw.write(&world).unwrap();
+    let blob = nbt::Blob::from_gzip_reader(&mut &bytes[..]).unwrap();
+    assert_eq!(blob.get("FormatVersion"), Some(&nbt::Value::Byte(cw::VERSION)));
+
+    let (loaded, remapped) = Format::Cw.read(&bytes, "test", "someone").unwrap();
+    assert!(remapped.is_empty());
+    assert_eq!(loaded.get_size(), [32, 16, 48]);
+    assert_eq!(loaded.get_blocks(), world.get_blocks());
+    assert_eq!(loaded.get_spawn(), SPAWN);
+    assert_eq!(loaded.get_author(), Some("tester"));
+    assert_eq!(loaded.get_map_generator(), world.get_map_generator());
+    assert_eq!(loaded.get_metadata(), world.get_metadata());
+    let (header, loaded_header) = (world.get_header(), loaded.get_header());
+    assert_eq!(loaded_header.uuid, header.uuid);
+    assert_eq!(loaded_header.last_modified, header.last_modified);
+}
+
+#[test]
+fn dat_round_trip() {
+    let world = world();
+    let bytes = Format::Dat.write(&world).unwrap();
+    let (loaded, remapped) = Format::Dat.read(&bytes, "test", "someone").unwrap();
+    assert!(remapped.is_empty());
+    assert_eq!(loaded.get_size(), [32, 16, 48]);
+    assert_eq!(loaded.get_blocks(), world.get_blocks());
+    assert_eq!(loaded.get_author(), Some("tester"));
+    assert_eq!(loaded.get_header().time_created, world.get_header().time_created);
+    // Version 1 has no spawn so it's found on the surface
+    assert_eq!(loaded.get_spawn(), surface_spawn(world.get_blocks(), 32, 16, 48));
+}
+
+/// Write a string as Java's `DataOutput.writeUTF` does
+fn java_utf(bytes: &mut Vec<u8>, text: &str) {
+    bytes.extend_from_slice(&(text.len() as u16).to_be_bytes());
+    bytes.extend_from_slice(text.as_bytes());
+}
+
+#[test]
+fn dat_huge_or_truncated_sizes_are_rejected() {
+    let gzip = |bytes: &[u8]| {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    };
+    let dat = |size: [u16; 3]| {
+        let mut bytes = dat::MAGIC.to_be_bytes().to_vec();
+        bytes.push(dat::VERSION);
+        java_utf(&mut bytes, "test");
+        java_utf(&mut bytes, "tester");
+        bytes.extend_from_slice(&0i64.to_be_bytes());
+        for size in size {
+            bytes.extend_from_slice(&size.to_be_bytes());
+        }
+        gzip(&bytes)
+    };
+    assert!(matches!(dat::read(&dat([65535; 3]), "test", "tester"), Err(FormatError::Size([65535, 65535, 65535]))));
+    assert!(matches!(dat::read(&dat([16; 3]), "test", "tester"), Err(FormatError::Truncated)));
+
+    // A name longer than the rest of the file
+    let mut bytes = dat::MAGIC.to_be_bytes().to_vec();
+    bytes.push(dat::VERSION);
+    bytes.extend_from_slice(&60000u16.to_be_bytes());
+    bytes.extend_from_slice(b"short");
+    assert!(matches!(dat::read(&gzip(&bytes), "test", "tester"), Err(FormatError::Truncated)));
+}
+
+#[test]
+fn dat_version_2_is_read() {
+    let mut level = vec![0xAC, 0xED, 0, 5];
+    level.extend_from_slice(&[0x73, 0x72]);
+    java_utf(&mut level, "com.mojang.minecraft.level.Level");
+    level.extend_from_slice(&[0; 8]);
+    level.push(0x02);
+    let fields: [(u8, &str); 10] = [(b'I', "width"), (b'I', "height"), (b'I', "depth"), (b'[', "blocks"),
+                                    (b'I', "xSpawn"), (b'I', "ySpawn"), (b'I', "zSpawn"), (b'F', "rotSpawn"),
+                                    (b'J', "createTime"), (b'L', "creator")];
+    level.extend_from_slice(&(fields.len() as u16).to_be_bytes());
+    for (code, name) in fields {
+        level.push(code);
+        java_utf(&mut level, name);
+        match code {
+            b'[' => { level.push(0x74); java_utf(&mut level, "[B"); }
+            b'L' => { level.push(0x74); java_utf(&mut level, "Ljava/lang/String;"); }
+            _ => {}
+        }
+    }
+    // No annotations and no super class
+    level.extend_from_slice(&[0x78, 0x70]);
+    for size in [4i32, 3, 2] {
+        level.extend_from_slice(&size.to_be_bytes());
+    }
+    level.extend_from_slice(&[0x75, 0x72]);
+    java_utf(&mut level, "[B");
+    level.extend_from_slice(&[0; 8]);
+    level.extend_from_slice(&[0x02, 0, 0, 0x78, 0x70]);
+    level.extend_from_slice(&24i32.to_be_bytes());
+    level.extend((0..24).map(|i| if i < 12 { Block::Dirt as u8 } else { 60 }));
+    for position in [1i32, 1, 2] {
+        level.extend_from_slice(&position.to_be_bytes());
+    }
+    level.extend_from_slice(&90f32.to_be_bytes());
+    level.extend_from_slice(&1_600_000_000_000i64.to_be_bytes());
+    level.push(0x74);
+    java_utf(&mut level, "notch");
+
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder.write_all(&dat::MAGIC.to_be_bytes()).unwrap();
+    encoder.write_all(&[2]).unwrap();
+    encoder.write_all(&level).unwrap();
+    let (mut loaded, remapped) = dat::read(&encoder.finish().unwrap(), "test", "someone").unwrap();
+    assert_eq!(loaded.get_size(), [4, 2, 3]);
+    assert_eq!(loaded.get_block(3, 0, 2), Block::Dirt);
+    assert_eq!(loaded.get_block(0, 1, 0), Block::Glass);
+    assert_eq!(remapped.get(60), Some((Block::Glass, 12)));
+    assert_eq!(loaded.get_spawn(), Spawn { x: 32 + 16, y: 32 + 51, z: 2 * 32 + 16, h: 64, p: 0 });
+    assert_eq!(loaded.get_author(), Some("notch"));
+    assert_eq!(loaded.get_header().time_created, 1_600_000_000);
+}
+
+#[test]
+fn formats_from_extension() {
+    assert_eq!(Format::from_path(Path::new("maps/main.fcm")), Some(Format::Fcm));
+    assert_eq!(Format::from_path(Path::new("main.LVL")), Some(Format::Lvl));
+    assert_eq!(Format::from_path(Path::new("old.mine")), Some(Format::Mclevel));
+    assert_eq!(Format::from_path(Path::new("server_level.dat")), Some(Format::Dat));
+    assert_eq!(Format::from_path(Path::new("main.cw")), Some(Format::Cw));
+    assert_eq!(Format::from_path(Path::new("main.crs")), None);
+    assert_eq!(Format::from_path(Path::new("lvl")), None);
+}
+
+#[tokio::test]
+async fn maps_in_the_world_directory_are_imported() {
+    let directory = tempfile::tempdir().unwrap();
+    let world = world();
+    fs::write(directory.path().join("other.lvl"), lvl::write(&world).unwrap()).unwrap();
+    fs::write(directory.path().join("broken.fcm"), b"not a map").unwrap();
+
+    let generator = Arc::new(Flatgrass::default());
+    let loaded = ClassicWorld::get_or_create(directory.path(), "other", "tester", 16, 16, 16,
+                                             generator.clone(), 0).await.unwrap();
+    assert_eq!(loaded.get_size(), [32, 16, 48]);
+    assert_eq!(loaded.get_blocks(), world.get_blocks());
+    // Saved as CRS, keeping the original
+    assert!(directory.path().join("other.crs").exists());
+    assert!(directory.path().join("other.lvl").exists());
+
+    let error = ClassicWorld::get_or_create(directory.path(), "broken", "tester", 16, 16, 16, generator, 0)
+        .await.err().unwrap();
+    assert!(matches!(error, CrsError::Import(_, FormatError::Identifier)));
+}
@@ -0,0 +1,127 @@
+use mc_worlds::classic::{ClassicWorld, Block};
+use mc_worlds::generation::{from_settings, GeneratorSettings, WorldGenerator, GENERATORS};
+use mc_worlds::generation::flatgrass::Layer;
+use mc_worlds::progress::Progress;
+
+const SEED: i64 = 1234;
+const SIZE: usize = 64;
+
+fn settings(name: &str) -> GeneratorSettings {
+    GeneratorSettings { name: name.to_string(), ..GeneratorSettings::default() }
+}
+
+fn generate(generator: &dyn WorldGenerator, seed: i64, x: usize, y: usize, z: usize) -> Vec<u8> {
+    generator.generate(seed, x, y, z, &Progress::new())
+}
+
+fn count(blocks: &[u8], block: Block) -> usize {
+    blocks.iter().filter(|b| **b == u8::from(block)).count()
+}
+
+#[test]
+fn every_generator_builds_by_name() {
+    for name in GENERATORS.iter() {
+        let generator = from_settings(&settings(&name.to_uppercase())).expect("Failed to build generator");
+        assert_eq!(generator.name(), *name);
+
+        let blocks = generate(generator.as_ref(), SEED, SIZE, SIZE, SIZE);
+        assert_eq!(blocks.len(), SIZE * SIZE * SIZE, "{} made the wrong number of blocks", name);
+        assert_eq!(blocks, generate(generator.as_ref(), SEED, SIZE, SIZE, SIZE), "{} is not deterministic", name);
+
+        let spawn = generator.spawn(&blocks, SIZE, SIZE, SIZE);
+        let (x, y, z) = (spawn.x as usize / 32, spawn.y as usize / 32, spawn.z as usize / 32);
+        assert!(x < SIZE && y <= SIZE && z < SIZE, "{} spawns outside the map", name);
+    }
+}
+
+#[test]
+fn every_generator_handles_empty_maps() {
+    for name in GENERATORS.iter() {
+        let generator = from_settings(&settings(name)).expect("Failed to build generator");
+        for &(x, y, z) in &[(0, SIZE, SIZE), (SIZE, 0, SIZE), (SIZE, SIZE, 0), (0, 0, 0)] {
+            let blocks = generate(generator.as_ref(), SEED, x, y, z);
+            assert!(blocks.is_empty(), "{} made blocks for an empty map", name);
+            generator.spawn(&blocks, x, y, z);
+        }
+    }
+}
+
+#[test]
+fn unknown_generator_is_rejected() {
+    assert!(from_settings(&settings("moon")).is_err());
+}
+
+#[test]
+fn flatgrass_layers() {
+    let mut settings = settings("flatgrass");
+    settings.flatgrass.layers = vec![
+        Layer { block: "bedrock".to_string(), depth: 1 },
+        Layer { block: "Stone".to_string(), depth: 2 },
+        Layer { block: "sand".to_string(), depth: 1 },
+    ];
+    let blocks = generate(from_settings(&settings).unwrap().as_ref(), SEED, 16, 16, 16);
+    let layer = |y: usize| &blocks[y * 256..(y + 1) * 256];
+
+    assert!(layer(0).iter().all(|b| *b == u8::from(Block::Bedrock)));
+    assert!(layer(2).iter().all(|b| *b == u8::from(Block::Stone)));
+    assert!(layer(3).iter().all(|b| *b == u8::from(Block::Sand)));
+    assert!(layer(4).iter().all(|b| *b == u8::from(Block::Air)));
+
+    settings.flatgrass.layers[1].block = "marble".to_string();
+    assert!(from_settings(&settings).is_err());
+}
+
+#[test]
+fn seeded_terrain_changes_with_seed() {
+    for name in ["islands", "mountains", "desert"] {
+        let generator = from_settings(&settings(name)).unwrap();
+        assert_ne!(generate(generator.as_ref(), SEED, SIZE, SIZE, SIZE),
+                   generate(generator.as_ref(), SEED + 1, SIZE, SIZE, SIZE), "{} ignores the seed", name);
+    }
+}
+
+#[test]
+fn generators_make_their_terrain() {
+    let blocks = |name: &str| generate(from_settings(&settings(name)).unwrap().as_ref(), SEED, 128, SIZE, 128);
+
+    let islands = blocks("islands");
+    assert!(count(&islands, Block::StationaryWater) > 0);
+    assert!(count(&islands, Block::GrassBlock) > 0);
+    assert!(count(&islands, Block::Sand) > 0);
+
+    let mountains = blocks("mountains");
+    assert!(count(&mountains, Block::GrassBlock) > 0);
+    assert!(mountains[128 * 128 * (SIZE * 3 / 4)..].iter().any(|b| *b == u8::from(Block::Stone)));
+
+    let desert = blocks("desert");
+    assert!(count(&desert, Block::Sand) > count(&desert, Block::GrassBlock));
+
+    let empty = blocks("empty");
+    assert_eq!(count(&empty, Block::Air), empty.len());
+
+    let pixel = blocks("pixel");
+    assert!(pixel[..128 * 128].iter().all(|b| *b == u8::from(Block::Bedrock)));
+    assert_eq!(count(&pixel, Block::WhiteCloth), (SIZE - 1) * (128 * 4 - 4));
+}
+
+#[test]
+fn world_records_generator() {
+    let generator = from_settings(&settings("islands")).unwrap();
+    let world = ClassicWorld::generate("test", "tester", SIZE, SIZE, SIZE, generator.as_ref(), SEED);
+    let metadata = world.get_map_generator().unwrap();
+    assert_eq!(metadata.name, "islands");
+    assert_eq!(metadata.seed, Some(SEED));
+
+    let generator = from_settings(&settings("flatgrass")).unwrap();
+    let world = ClassicWorld::generate("test", "tester", SIZE, SIZE, SIZE, generator.as_ref(), SEED);
+    assert_eq!(world.get_map_generator().unwrap().seed, None);
+}
+
+#[test]
+fn blocks_by_name() {
+    assert_eq!(Block::from_name("white_cloth"), Some(Block::WhiteCloth));
+    assert_eq!(Block::from_name("Grass Block"), Some(Block::GrassBlock));
+    assert_eq!(Block::from_name("49"), Some(Block::Obsidian));
+    assert_eq!(Block::from_name("50"), None);
+    assert_eq!(Block::from_name("marble"), None);
+}
@@ -0,0 +1,117 @@
+use mc_worlds::classic::{ClassicWorld, Block};
+use mc_worlds::generation::empty::Empty;
+use mc_worlds::generation::flatgrass::Flatgrass;
+
+/// An empty 16x16x16 world with a floor of stone under a layer of grass
+fn world() -> ClassicWorld {
+    let mut world = ClassicWorld::generate("test", "tester", 16, 16, 16, &Empty, 0);
+    for x in 0..16 {
+        for z in 0..16 {
+            world.set_block(x, 0, z, Block::Stone);
+            world.set_block(x, 1, z, Block::GrassBlock);
+        }
+    }
+    world
+}
+
+#[test]
+fn light_stops_at_the_highest_solid_block() {
+    let mut world = ClassicWorld::generate("test", "tester", 16, 16, 16, &Empty, 0);
+    assert_eq!(world.light_height(3, 3), 0);
+    world.set_block(3, 5, 3, Block::Stone);
+    assert_eq!(world.light_height(3, 3), 6);
+    world.set_block(3, 2, 3, Block::Dirt);
+    world.set_block(3, 8, 3, Block::Glass);
+    world.set_block(3, 9, 3, Block::Leaves);
+    world.set_block(3, 10, 3, Block::Rose);
+    assert_eq!(world.light_height(3, 3), 6);
+    assert!(world.is_lit(3, 6, 3) && !world.is_lit(3, 5, 3));
+    // Removing the top block lets light down to the next one
+    world.set_block(3, 5, 3, Block::Air);
+    assert_eq!(world.light_height(3, 3), 3);
+    assert_eq!(world.light_height(4, 3), 0);
+
+    // Generated worlds start with their light worked out
+    let mut world = ClassicWorld::generate("test", "tester", 8, 16, 8, &Flatgrass::default(), 0);
+    let top = (0..16).rev().find(|y| world.get_block(0, *y, 0) != Block::Air).unwrap();
+    assert_eq!(world.light_height(0, 0), top + 1);
+
+    // Heights past a u16 still count
+    let mut world = ClassicWorld::generate("tall", "tester", 1, 70000, 1, &Empty, 0);
+    world.set_block(0, 65535, 0, Block::Stone);
+    assert_eq!(world.light_height(0, 0), 65536);
+    assert!(!world.is_lit(0, 0, 0));
+}
+
+#[test]
+fn grass_needs_light() {
+    let mut world = world();
+    world.set_block(4, 1, 4, Block::Dirt);
+    assert_eq!(world.random_tick_at(4, 1, 4), vec![(4, 1, 4, Block::GrassBlock)]);
+
+    // Glass lets the light through, stone doesn't
+    world.set_block(4, 3, 4, Block::Glass);
+    assert!(world.random_tick_at(4, 1, 4).is_empty());
+    world.set_block(4, 4, 4, Block::Stone);
+    world.random_tick_at(4, 1, 4);
+    assert_eq!(world.get_block(4, 1, 4), Block::Dirt);
+    assert!(world.random_tick_at(4, 1, 4).is_empty());
+
+    // Random ticks only pick blocks while physics is on
+    assert!((0..100).all(|_| world.random_tick().is_empty()));
+    world.set_physics(true);
+    world.set_block(4, 4, 4, Block::Air);
+    let changes: Vec<_> = (0..20000).flat_map(|_| world.random_tick()).collect();
+    assert!(changes.contains(&(4, 1, 4, Block::GrassBlock)));
+}
+
+#[test]
+fn saplings_grow_into_trees() {
+    let mut world = world();
+    world.set_block(8, 2, 8, Block::Sapling);
+    let mut ticks = 0;
+    while world.get_block(8, 2, 8) == Block::Sapling && ticks < 1000 {
+        world.random_tick_at(8, 2, 8);
+        ticks += 1;
+    }
+    assert_eq!(world.get_block(8, 2, 8), Block::Wood);
+    let top = (2..16).find(|y| world.get_block(8, *y, 8) != Block::Wood).unwrap();
+    assert!((6..=8).contains(&top));
+    assert_eq!(world.get_block(8, top, 8), Block::Leaves);
+    assert_eq!(world.get_block(10, top - 3, 8), Block::Leaves);
+
+    // Without room for the tree the sapling waits
+    world.set_block(2, 2, 2, Block::Sapling);
+    world.set_block(2, 5, 2, Block::Glass);
+    for _ in 0..100 {
+        assert!(world.random_tick_at(2, 2, 2).is_empty());
+    }
+}
+
+#[test]
+fn plants_break_in_the_wrong_place() {
+    let mut world = world();
+    world.set_block(3, 1, 3, Block::Stone);
+    world.set_block(3, 2, 3, Block::Dandelion);
+    assert_eq!(world.random_tick_at(3, 2, 3), vec![(3, 2, 3, Block::Air)]);
+
+    // Flowers need light and mushrooms shadow
+    world.set_block(5, 2, 5, Block::Rose);
+    world.set_block(5, 1, 6, Block::Cobblestone);
+    world.set_block(5, 2, 6, Block::RedMushroom);
+    assert!(world.random_tick_at(5, 2, 5).is_empty());
+    assert_eq!(world.random_tick_at(5, 2, 6), vec![(5, 2, 6, Block::Air)]);
+    world.set_block(5, 2, 6, Block::BrownMushroom);
+    world.set_block(5, 6, 6, Block::Stone);
+    world.set_block(5, 6, 5, Block::Stone);
+    assert!(world.random_tick_at(5, 2, 6).is_empty());
+    assert_eq!(world.random_tick_at(5, 2, 5), vec![(5, 2, 5, Block::Air)]);
+
+    // With physics on, digging out from under a plant breaks it straight away
+    world.set_physics(true);
+    world.set_block(9, 2, 9, Block::Rose);
+    world.tick();
+    assert_eq!(world.get_block(9, 2, 9), Block::Rose);
+    world.set_block(9, 1, 9, Block::Air);
+    assert_eq!(world.tick(), vec![(9, 2, 9, Block::Air)]);
+}
@@ -0,0 +1,83 @@
+use std::io::Read;
+use flate2::read::GzDecoder;
+
+use mc_worlds::classic::{ClassicWorld, Block, MAX_CHANGES};
+
+fn gunzip(gzipped: &[u8]) -> Vec<u8> {
+    let mut level = vec![];
+    GzDecoder::new(gzipped).read_to_end(&mut level).expect("Not valid gzip");
+    level.split_off(4)
+}
+
+#[tokio::test]
+async fn level_is_shared_until_the_world_changes() {
+    let mut world = ClassicWorld::new("test", "tester", 16, 16, 16);
+    let first = world.get_gzipped();
+    let level = first.ready().await.expect("Failed to build level");
+    assert_eq!(gunzip(&level), *world.get_blocks());
+
+    // Nothing changed so everyone gets the same level
+    let second = world.get_gzipped();
+    assert_eq!(second.revision(), first.revision());
+    assert!(std::sync::Arc::ptr_eq(&level, &second.get().unwrap()));
+
+    world.set_block(1, 12, 1, Block::Stone);
+    let third = world.get_gzipped();
+    assert_eq!(third.revision(), world.get_revision());
+    assert_eq!(gunzip(&third.ready().await.unwrap()), *world.get_blocks());
+}
+
+#[tokio::test]
+async fn changes_during_download_are_replayed() {
+    let mut world = ClassicWorld::new("test", "tester", 16, 16, 16);
+    let stream = world.get_gzipped();
+    world.set_block(1, 12, 1, Block::Stone);
+    world.set_block(2, 12, 2, Block::Glass);
+
+    let changes = world.changes_since(stream.revision()).expect("Changes were dropped");
+    assert_eq!(changes, vec![(1, 12, 1, Block::Stone), (2, 12, 2, Block::Glass)]);
+
+    // Applying the replayed changes to the downloaded level gives the current world
+    let mut level = gunzip(&stream.ready().await.unwrap());
+    for (x, y, z, block) in changes {
+        level[x + 16 * (z + 16 * y)] = block.into();
+    }
+    assert_eq!(level, *world.get_blocks());
+}
+
+#[tokio::test]
+async fn old_changes_are_dropped_with_old_levels() {
+    let mut world = ClassicWorld::new("test", "tester", 16, 16, 16);
+    let first = world.get_gzipped();
+    first.ready().await.unwrap();
+    world.set_block(1, 12, 1, Block::Stone);
+    let second = world.get_gzipped();
+    second.ready().await.unwrap();
+    world.set_block(2, 12, 2, Block::Glass);
+    let _third = world.get_gzipped();
+
+    assert!(world.changes_since(first.revision()).is_none());
+    assert_eq!(world.changes_since(second.revision()), Some(vec![(2, 12, 2, Block::Glass)]));
+}
+
+#[tokio::test]
+async fn changes_stay_bounded_without_joins() {
+    let mut world = ClassicWorld::new("test", "tester", 16, 16, 16);
+    let stream = world.get_gzipped();
+    stream.ready().await.unwrap();
+    let full = stream.revision() + MAX_CHANGES as u64 + 1;
+    for i in 0..MAX_CHANGES * 3 {
+        let block = if i % 2 == 0 { Block::Stone } else { Block::Glass };
+        world.set_block(i % 16, 12, (i / 16) % 16, block);
+    }
+
+    // The stale level was dropped and nothing has been recorded since
+    assert!(world.changes_since(stream.revision()).is_none());
+    assert_eq!(world.changes_since(full), Some(vec![]));
+
+    // The next join gets a fresh level and changes are recorded for it again
+    let rebuilt = world.get_gzipped();
+    assert_eq!(rebuilt.revision(), world.get_revision());
+    world.set_block(1, 13, 1, Block::Stone);
+    assert_eq!(world.changes_since(rebuilt.revision()), Some(vec![(1, 13, 1, Block::Stone)]));
+}
@@ -0,0 +1,90 @@
+use mc_worlds::classic::{ClassicWorld, Block};
+use mc_worlds::generation::notch::{self, NotchGenerator};
+use mc_worlds::generation::WorldGenerator;
+use mc_worlds::progress::Progress;
+use mc_worlds::generation::random::JavaRandom;
+
+const SEED: i64 = 1234;
+
+fn generate(seed: i64, x: usize, y: usize, z: usize) -> Vec<u8> {
+    NotchGenerator.generate(seed, x, y, z, &Progress::new())
+}
+
+fn count(blocks: &[u8], block: Block) -> usize {
+    blocks.iter().filter(|b| **b == u8::from(block)).count()
+}
+
+#[test]
+fn random_matches_java() {
+    // new java.util.Random(42).nextFloat() and nextInt(10)
+    assert!((JavaRandom::new(42).next_float() - 0.727_563_7).abs() < 1e-6);
+    assert_eq!(JavaRandom::new(42).next_int(10), 0);
+}
+
+#[test]
+fn same_seed_same_map() {
+    assert_eq!(generate(SEED, 64, 64, 64), generate(SEED, 64, 64, 64));
+}
+
+#[test]
+fn different_seed_different_map() {
+    assert_ne!(generate(SEED, 64, 64, 64), generate(SEED + 1, 64, 64, 64));
+}
+
+#[test]
+fn generates_classic_terrain() {
+    let (x, y, z) = (128, 64, 128);
+    let blocks = generate(SEED, x, y, z);
+
+    assert_eq!(blocks.len(), x * y * z);
+    assert!(blocks[..x * z].iter().all(|b| *b == u8::from(Block::StationaryLava)));
+    for block in [Block::Stone, Block::Dirt, Block::GrassBlock, Block::StationaryWater, Block::Air,
+                  Block::CoalOre, Block::Wood, Block::Leaves] {
+        assert!(count(&blocks, block) > 0, "no {:?} generated", block);
+    }
+    // Nothing but air and leaves sits above the top layer
+    let top = &blocks[x * z * (y - 1)..];
+    assert!(top.iter().all(|b| *b == u8::from(Block::Air) || *b == u8::from(Block::Leaves)));
+}
+
+#[test]
+fn world_keeps_seed() {
+    let world = ClassicWorld::generate("test", "tester", 64, 64, 64, &NotchGenerator, SEED);
+    let generator = world.get_map_generator().expect("No generator metadata");
+
+    assert_eq!(generator.name, notch::NAME);
+    assert_eq!(generator.seed, Some(SEED));
+    assert_eq!(world.get_blocks(), &generate(SEED, 64, 64, 64));
+}
+
+#[test]
+fn spawns_above_ground() {
+    let mut world = ClassicWorld::generate("test", "tester", 64, 64, 64, &NotchGenerator, SEED);
+    let spawn = world.get_spawn();
+    let (x, y, z) = (spawn.x as usize / 32, spawn.y as usize / 32, spawn.z as usize / 32);
+
+    assert_eq!(world.get_block(x, y - 1, z), Block::Air);
+    assert_ne!(world.get_block(x, y - 3, z), Block::Air);
+}
+
+#[test]
+fn ore_veins_wander() {
+    let (x, y, z) = (256, 64, 256);
+    let blocks = generate(SEED, x, y, z);
+    let ore = |bx: usize, by: usize, bz: usize| blocks[bx + x * (bz + z * by)] == u8::from(Block::CoalOre);
+    // Veins turn as they go like caves do, so they stretch along x about as often as along z
+    let (mut along_x, mut along_z) = (0, 0);
+    for by in 0..y {
+        for bz in 0..z - 4 {
+            for bx in 0..x - 4 {
+                if ore(bx, by, bz) && ore(bx + 4, by, bz) {
+                    along_x += 1;
+                }
+                if ore(bx, by, bz) && ore(bx, by, bz + 4) {
+                    along_z += 1;
+                }
+            }
+        }
+    }
+    assert!(along_x * 3 > along_z && along_z * 3 > along_x, "{} along x and {} along z", along_x, along_z);
+}
@@ -0,0 +1,169 @@
+use mc_worlds::classic::{ClassicWorld, Block};
+use mc_worlds::classic::physics::{WATER_DELAY, LAVA_DELAY, SPONGE_RADIUS, FALL_DELAY};
+use mc_worlds::classic::scheduler::Scheduler;
+
+mod common;
+use common::{stone_world, run};
+
+/// An empty 16x16x16 world with physics on and a stone floor
+fn world() -> ClassicWorld {
+    let mut world = stone_world(1);
+    world.set_physics(true);
+    world
+}
+
+#[test]
+fn water_flows_down_and_out() {
+    let mut world = world();
+    world.set_block(8, 3, 8, Block::FlowingWater);
+    assert!(run(&mut world, WATER_DELAY - 1).is_empty());
+    let changes = run(&mut world, 1);
+    assert_eq!(changes.len(), 5);
+    assert!(changes.contains(&(8, 2, 8, Block::FlowingWater)));
+    assert!(changes.contains(&(9, 3, 8, Block::FlowingWater)));
+    // Never up
+    assert_eq!(world.get_block(8, 4, 8), Block::Air);
+
+    // Left long enough it fills the floor, but nothing above the water
+    run(&mut world, WATER_DELAY * 40);
+    assert_eq!(world.get_block(0, 1, 15), Block::FlowingWater);
+    assert_eq!(world.get_block(15, 3, 0), Block::FlowingWater);
+    assert_eq!(world.get_block(15, 4, 0), Block::Air);
+    assert!(run(&mut world, WATER_DELAY * 2).is_empty());
+}
+
+#[test]
+fn nothing_moves_without_physics() {
+    let mut world = world();
+    world.set_physics(false);
+    world.set_block(8, 3, 8, Block::FlowingWater);
+    assert!(run(&mut world, WATER_DELAY * 4).is_empty());
+
+    // Turning it on picks up where the water was left
+    world.set_physics(true);
+    assert_eq!(run(&mut world, WATER_DELAY).len(), 5);
+}
+
+#[test]
+fn updates_outside_the_world_are_ignored() {
+    let mut world = world();
+    let [x, y, z] = world.get_size();
+    world.schedule_update(x, 0, 0, 1);
+    world.schedule_update(0, y, 0, 1);
+    world.schedule_update(0, 0, z, 1);
+    world.schedule_update(usize::MAX, usize::MAX, usize::MAX, 1);
+    assert_eq!(world.pending_updates(), 0);
+    world.schedule_update(x - 1, y - 1, z - 1, 1);
+    assert_eq!(world.pending_updates(), 1);
+    run(&mut world, 2);
+}
+
+#[test]
+fn lava_is_slower_and_cools_in_water() {
+    let mut world = world();
+    world.set_block(2, 1, 2, Block::StationaryLava);
+    world.set_block(3, 1, 2, Block::Dirt);
+    assert!(run(&mut world, LAVA_DELAY - 1).is_empty());
+    assert_eq!(run(&mut world, 1).len(), 3);
+
+    // Water reaching a lava source turns it to stone
+    world.set_block(4, 1, 2, Block::StationaryWater);
+    world.set_block(3, 1, 2, Block::Air);
+    let changes = run(&mut world, WATER_DELAY);
+    assert!(changes.contains(&(3, 1, 2, Block::FlowingWater)));
+    let changes = run(&mut world, WATER_DELAY);
+    assert!(changes.contains(&(2, 1, 2, Block::Stone)));
+
+    // and flowing lava to cobblestone
+    world.set_block(10, 1, 10, Block::StationaryWater);
+    world.set_block(11, 1, 10, Block::FlowingLava);
+    run(&mut world, WATER_DELAY);
+    assert_eq!(world.get_block(11, 1, 10), Block::Cobblestone);
+
+    // Whichever of them is woken first
+    world.set_physics(false);
+    world.set_block(13, 1, 13, Block::StationaryWater);
+    world.set_block(13, 1, 14, Block::StationaryLava);
+    world.set_physics(true);
+    world.set_block(13, 2, 14, Block::Dirt);
+    run(&mut world, LAVA_DELAY);
+    assert_eq!(world.get_block(13, 1, 14), Block::Stone);
+}
+
+#[test]
+fn sponges_soak_up_water() {
+    let mut world = world();
+    for x in 0..16 {
+        for z in 0..16 {
+            world.set_block(x, 1, z, Block::StationaryWater);
+        }
+    }
+    world.set_block(8, 2, 8, Block::Sponge);
+    let changes = run(&mut world, 1);
+    let radius = SPONGE_RADIUS * 2 + 1;
+    assert_eq!(changes.len(), radius * radius);
+    assert!(changes.iter().all(|change| change.3 == Block::Air));
+    assert_eq!(world.get_block(6, 1, 6), Block::Air);
+    assert_eq!(world.get_block(5, 1, 6), Block::StationaryWater);
+    // The water around doesn't flow back in
+    assert!(run(&mut world, WATER_DELAY * 4).is_empty());
+
+    world.set_block(8, 2, 8, Block::Air);
+    run(&mut world, WATER_DELAY * 4);
+    assert_eq!(world.get_block(8, 1, 8), Block::FlowingWater);
+}
+
+#[test]
+fn sand_and_gravel_fall_until_they_land() {
+    let mut world = world();
+    world.set_block(4, 6, 4, Block::Sand);
+    // A block at a time, every step sent
+    assert_eq!(run(&mut world, FALL_DELAY), vec![(4, 5, 4, Block::Sand), (4, 6, 4, Block::Air)]);
+    run(&mut world, FALL_DELAY * 10);
+    assert_eq!(world.get_block(4, 1, 4), Block::Sand);
+    assert_eq!(world.get_block(4, 2, 4), Block::Air);
+    assert_eq!(world.pending_updates(), 0);
+
+    // Taking away the block under a stack drops all of it
+    world.set_block(8, 1, 8, Block::Dirt);
+    world.set_block(8, 2, 8, Block::Gravel);
+    world.set_block(8, 3, 8, Block::Sand);
+    assert!(run(&mut world, FALL_DELAY * 5).is_empty());
+    world.set_block(8, 1, 8, Block::Air);
+    run(&mut world, FALL_DELAY * 5);
+    assert_eq!(world.get_block(8, 1, 8), Block::Gravel);
+    assert_eq!(world.get_block(8, 2, 8), Block::Sand);
+    assert_eq!(world.get_block(8, 3, 8), Block::Air);
+
+    // Sand left in the air while physics was off falls once it's on, sinking through water
+    world.set_physics(false);
+    world.set_block(12, 1, 12, Block::StationaryWater);
+    world.set_block(12, 3, 12, Block::Sand);
+    world.set_physics(true);
+    run(&mut world, FALL_DELAY * 5);
+    assert_eq!(world.get_block(12, 1, 12), Block::Sand);
+    assert_eq!(world.get_block(12, 2, 12), Block::Air);
+}
+
+#[test]
+fn scheduler_updates_blocks_once_in_order() {
+    let mut scheduler = Scheduler::default();
+    scheduler.schedule(1, 2);
+    scheduler.schedule(2, 1);
+    scheduler.schedule(1, 1);
+    assert_eq!(scheduler.len(), 2);
+    assert!(scheduler.take_due(10).is_empty());
+    scheduler.advance();
+    assert_eq!(scheduler.take_due(10), vec![2]);
+    scheduler.advance();
+    assert_eq!(scheduler.take_due(10), vec![1]);
+    assert!(scheduler.is_empty());
+
+    // Updates past the limit wait for the next tick
+    for index in 0..3 {
+        scheduler.schedule(index, 0);
+    }
+    assert_eq!(scheduler.take_due(2).len(), 2);
+    assert!(scheduler.is_scheduled(0));
+    assert_eq!(scheduler.take_due(2), vec![0]);
+}
@@ -0,0 +1,138 @@
+use std::convert::TryInto;
+use std::io::Read;
+use flate2::Crc;
+use flate2::read::ZlibDecoder;
+
+use mc_worlds::classic::{ClassicWorld, Block};
+use mc_worlds::generation::empty::Empty;
+use mc_worlds::generation::flatgrass::Flatgrass;
+use mc_worlds::render::{self, Image, png};
+
+/// Read back an image written by [png::encode], checking every chunk's CRC
+fn decode(bytes: &[u8]) -> (usize, usize, Vec<u8>) {
+    assert_eq!(bytes[..8], png::SIGNATURE);
+    let (mut rest, mut size, mut data) = (&bytes[8..], (0, 0), vec![]);
+    while !rest.is_empty() {
+        let length = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+        let (kind, chunk) = (&rest[4..8], &rest[8..8 + length]);
+        let crc = u32::from_be_bytes(rest[8 + length..12 + length].try_into().unwrap());
+        let mut hasher = Crc::new();
+        hasher.update(kind);
+        hasher.update(chunk);
+        assert_eq!(hasher.sum(), crc);
+        match kind {
+            b"IHDR" => {
+                size = (u32::from_be_bytes(chunk[..4].try_into().unwrap()) as usize,
+                        u32::from_be_bytes(chunk[4..8].try_into().unwrap()) as usize);
+                assert_eq!(chunk[8..], [8, 6, 0, 0, 0]);
+            }
+            b"IDAT" => data.extend_from_slice(chunk),
+            _ => {}
+        }
+        rest = &rest[12 + length..];
+    }
+    let mut rows = vec![];
+    ZlibDecoder::new(data.as_slice()).read_to_end(&mut rows).unwrap();
+    let mut pixels = vec![];
+    for row in rows.chunks(size.0 * 4 + 1) {
+        assert_eq!(row[0], png::FILTER_SUB);
+        let start = pixels.len();
+        for (i, byte) in row[1..].iter().enumerate() {
+            let left = if i >= 4 { pixels[start + i - 4] } else { 0 };
+            pixels.push(byte.wrapping_add(left));
+        }
+    }
+    (size.0, size.1, pixels)
+}
+
+#[test]
+fn every_block_has_a_colour() {
+    assert_eq!(render::colour(Block::Air)[3], 0);
+    for block in [Block::Glass, Block::StationaryWater, Block::FlowingWater, Block::Leaves, Block::Rose] {
+        assert!((1..255).contains(&render::colour(block)[3]), "{:?} should be see through", block);
+    }
+    for block in [Block::Stone, Block::GrassBlock, Block::StationaryLava, Block::Obsidian] {
+        assert_eq!(render::colour(block)[3], 255);
+    }
+}
+
+#[test]
+fn png_round_trip() {
+    let generator = Flatgrass::default();
+    let world = ClassicWorld::generate("test", "tester", 7, 4, 5, &generator, 0);
+    let image = render::top_down(&world).unwrap();
+    let (width, height, pixels) = decode(&image.to_png());
+    assert_eq!((width, height), (7, 5));
+    assert_eq!(pixels, image.pixels());
+    assert_eq!(decode(&Image::new(0, 0).to_png()), (0, 0, vec![]));
+}
+
+#[test]
+fn top_down_shades_and_sees_through() {
+    let generator = Flatgrass::default();
+    let mut world = ClassicWorld::generate("test", "tester", 16, 16, 16, &generator, 0);
+    world.set_block(3, 8, 3, Block::Stone);
+    world.set_block(5, 8, 5, Block::Glass);
+    world.set_block(7, 7, 7, Block::Air);
+    world.set_block(10, 8, 10, Block::StationaryWater);
+    let image = render::top_down(&world).unwrap();
+    assert_eq!((image.width(), image.height()), (16, 16));
+
+    let grass = image.pixel(0, 0);
+    assert_eq!(grass[3], 255);
+    // Higher is lighter and a slope up from the north lighter still
+    let stone = render::colour(Block::Stone);
+    assert!(image.pixel(3, 3)[0] as u32 > stone[0] as u32 * 3 / 4);
+    assert!(image.pixel(3, 4)[0] < image.pixel(3, 2)[0]);
+    // Grass shows through the glass, tinted by it
+    let glass = image.pixel(5, 5);
+    assert_ne!(glass, image.pixel(5, 6));
+    assert!(glass[1] > glass[2]);
+    // The hole is darker than the grass around it
+    assert!(image.pixel(7, 7)[1] < image.pixel(7, 6)[1]);
+    assert!(image.pixel(10, 10)[2] > grass[2]);
+
+    let empty = ClassicWorld::generate("empty", "tester", 4, 4, 4, &Empty, 0);
+    assert!(render::top_down(&empty).unwrap().pixels().iter().all(|p| *p == 0));
+}
+
+#[test]
+fn isometric_draws_visible_faces() {
+    let mut world = ClassicWorld::generate("empty", "tester", 4, 3, 5, &Empty, 0);
+    assert_eq!(render::isometric_size(world.get_size(), 1), (18, 15));
+    assert!(render::isometric(&world, 1).unwrap().pixels().iter().all(|p| *p == 0));
+
+    // The block at the far corner is at the top of the image, the near corner at the bottom
+    world.set_block(0, 2, 0, Block::GoldBlock);
+    world.set_block(3, 0, 4, Block::RedCloth);
+    let image = render::isometric(&world, 2).unwrap();
+    assert_eq!((image.width(), image.height()), render::isometric_size([4, 3, 5], 2));
+    let gold = render::colour(Block::GoldBlock);
+    assert_eq!(image.pixel(4 * 4 + 4, 1), gold);
+    // Its sides are darker than its top
+    let left = image.pixel(4 * 4 + 1, 6);
+    assert!(left[3] == 255 && left[0] < gold[0]);
+    let (width, height) = (image.width(), image.height());
+    let bottom = image.pixel(width / 2 - 2, height - 2);
+    assert_eq!(bottom[3], 255);
+    assert!(bottom[0] > bottom[1]);
+    assert_eq!(image.pixel(0, 0)[3], 0);
+}
+
+#[test]
+fn renders_stay_under_the_pixel_limit() {
+    // Big worlds are drawn at a smaller scale, and not at all once even the smallest is too big
+    assert_eq!(render::isometric_scale([64, 64, 64], 8), Some(8));
+    let scale = render::isometric_scale([1024, 256, 1024], 8).unwrap();
+    assert!(scale < 8);
+    let (width, height) = render::isometric_size([1024, 256, 1024], scale);
+    assert!(width * height <= render::MAX_PIXELS);
+    let (width, height) = render::isometric_size([1024, 256, 1024], scale + 1);
+    assert!(width * height > render::MAX_PIXELS);
+    assert_eq!(render::isometric_scale([16384, 1, 16384], 1), None);
+    assert_eq!(render::isometric_scale([0, 0, 0], 0), Some(1));
+
+    let world = ClassicWorld::generate("wide", "tester", 8200, 1, 8200, &Empty, 0);
+    assert!(render::top_down(&world).is_none());
+    assert!(render::isometric(&world, 1).is_none());
+}
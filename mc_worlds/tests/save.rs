@@ -0,0 +1,107 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::{TimeZone, Utc};
+
+use mc_worlds::classic::{ClassicWorld, Block};
+use mc_worlds::generation::flatgrass::Flatgrass;
+use mc_worlds::save::{write_atomic, backup_name, backup_time, list_backups, prune_backups};
+
+fn file_names(directory: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(directory).unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[tokio::test]
+async fn write_atomic_replaces_the_whole_file() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("world.crs");
+    write_atomic(&path, &[1; 100]).await.unwrap();
+    write_atomic(&path, &[2; 10]).await.unwrap();
+
+    // A shorter save mustn't leave the end of the longer one behind
+    assert_eq!(fs::read(&path).unwrap(), vec![2; 10]);
+    assert_eq!(file_names(directory.path()), vec!["world.crs"]);
+}
+
+#[tokio::test]
+async fn saved_world_loads_again() {
+    let directory = tempfile::tempdir().unwrap();
+    let generator = Arc::new(Flatgrass::default());
+    let mut world = ClassicWorld::get_or_create(directory.path(), "test", "tester", 16, 16, 16,
+                                                generator.clone(), 0).await.unwrap();
+    world.set_block(3, 12, 4, Block::Glass);
+    world.save_crs_file().await.unwrap();
+    assert_eq!(file_names(directory.path()), vec!["test.crs"]);
+
+    let loaded = ClassicWorld::get_or_create(directory.path(), "test", "tester", 16, 16, 16, generator, 0)
+        .await.unwrap();
+    assert_eq!(loaded.get_blocks(), world.get_blocks());
+}
+
+#[tokio::test]
+async fn backups_are_timestamped() {
+    let directory = tempfile::tempdir().unwrap();
+    let world = ClassicWorld::new("test", "tester", 16, 16, 16);
+    let path = world.backup(&directory.path().join("test")).await.unwrap();
+
+    let name = path.file_name().unwrap().to_str().unwrap();
+    let taken = backup_time("test", name).expect("Backup name has no timestamp");
+    assert!((Utc::now() - taken).num_seconds() < 60);
+    assert_eq!(list_backups(&directory.path().join("test"), "test").await.unwrap(), vec![(taken, path)]);
+}
+
+#[tokio::test]
+async fn back_to_back_backups_are_kept() {
+    let directory = tempfile::tempdir().unwrap();
+    let world = ClassicWorld::new("test", "tester", 16, 16, 16);
+    let mut paths = vec![];
+    for _ in 0..3 {
+        paths.push(world.backup(directory.path()).await.unwrap());
+    }
+    let backups = list_backups(directory.path(), "test").await.unwrap();
+    assert_eq!(backups.into_iter().map(|(_, path)| path).collect::<Vec<_>>(), paths);
+}
+
+#[test]
+fn backup_names() {
+    let time = Utc.with_ymd_and_hms(2020, 11, 3, 14, 5, 9).unwrap() + chrono::Duration::milliseconds(42);
+    assert_eq!(backup_name("my_world", time), "my_world_2020-11-03_14-05-09-042.crs");
+    assert_eq!(backup_time("my_world", "my_world_2020-11-03_14-05-09-042.crs"), Some(time));
+    // Backups from before milliseconds were added are still found
+    assert_eq!(backup_time("my_world", "my_world_2020-11-03_14-05-09.crs"),
+               Some(time - chrono::Duration::milliseconds(42)));
+    assert_eq!(backup_time("my", "my_world_2020-11-03_14-05-09.crs"), None);
+    assert_eq!(backup_time("my_world", "my_world.crs"), None);
+}
+
+#[tokio::test]
+async fn prune_keeps_newest_backups() {
+    let directory = tempfile::tempdir().unwrap();
+    let now = Utc::now();
+    for hours in 0..6 {
+        let name = backup_name("test", now - chrono::Duration::hours(hours));
+        fs::write(directory.path().join(name), []).unwrap();
+    }
+    fs::write(directory.path().join("notes.txt"), []).unwrap();
+
+    let pruned = prune_backups(directory.path(), "test", 4, None).await.unwrap();
+    assert_eq!(pruned.len(), 2);
+    assert_eq!(list_backups(directory.path(), "test").await.unwrap().len(), 4);
+
+    // Older than 90 minutes
+    let pruned = prune_backups(directory.path(), "test", 0, Some(Duration::from_secs(90 * 60))).await.unwrap();
+    assert_eq!(pruned.len(), 2);
+    let left = list_backups(directory.path(), "test").await.unwrap();
+    assert_eq!(left.len(), 2);
+    assert!(file_names(directory.path()).contains(&"notes.txt".to_string()));
+
+    // The newest backup is never deleted however old it is
+    let pruned = prune_backups(directory.path(), "test", 1, Some(Duration::from_secs(1))).await.unwrap();
+    assert_eq!(pruned, vec![left[0].1.clone()]);
+    assert_eq!(list_backups(directory.path(), "test").await.unwrap(), vec![left[1].clone()]);
+}
@@ -0,0 +1,158 @@
+use std::collections::HashSet;
+
+use mc_worlds::classic::{ClassicWorld, Block};
+use mc_worlds::formats::{FormatError, Region, RegionFormat, palette, schematic, sponge};
+use mc_worlds::generation::flatgrass::Flatgrass;
+
+/// A region holding every classic block
+fn every_block() -> Region {
+    let blocks: Vec<u8> = (0..=Block::MAX_ID).chain(0..10).collect();
+    Region::new([5, 2, 6], blocks).unwrap()
+}
+
+fn gzipped(blob: &nbt::Blob) -> Vec<u8> {
+    let mut bytes = vec![];
+    blob.to_gzip_writer(&mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn every_block_has_its_own_modern_block() {
+    let blocks = (0..=Block::MAX_ID).map(Block::from);
+    assert_eq!(blocks.clone().map(palette::modern_block).collect::<HashSet<_>>().len(), 50);
+    assert_eq!(blocks.clone().map(palette::alpha_block).collect::<HashSet<_>>().len(), 50);
+    for block in blocks {
+        assert_eq!(palette::from_modern(palette::modern_block(block)), (block, true));
+        let (id, data) = palette::alpha_block(block);
+        assert_eq!(palette::from_alpha(id, data), (block, true));
+    }
+}
+
+#[test]
+fn modern_blocks_are_matched_by_name() {
+    assert_eq!(palette::from_modern("minecraft:water"), (Block::StationaryWater, true));
+    assert_eq!(palette::from_modern("minecraft:lava[level=4]"), (Block::FlowingLava, true));
+    assert_eq!(palette::from_modern("minecraft:oak_log[axis=x]"), (Block::Wood, true));
+    assert_eq!(palette::from_modern("minecraft:spruce_planks"), (Block::Planks, false));
+    assert_eq!(palette::from_modern("minecraft:light_gray_concrete"), (Block::LightGrayCloth, false));
+    assert_eq!(palette::from_modern("minecraft:red_stained_glass"), (Block::Glass, false));
+    assert_eq!(palette::from_modern("minecraft:cave_air"), (Block::Air, false));
+    assert_eq!(palette::from_modern("minecraft:wall_torch[facing=north]"), (Block::Air, false));
+    assert_eq!(palette::from_modern("minecraft:deepslate"), (Block::Stone, false));
+}
+
+#[test]
+fn copy_and_paste_regions() {
+    let generator = Flatgrass::default();
+    let mut world = ClassicWorld::generate("test", "tester", 16, 16, 16, &generator, 0);
+    world.set_block(2, 10, 3, Block::Glass);
+    world.set_block(3, 10, 3, Block::Slab);
+    world.replace_block(3, 11, 3, Block::Slab);
+
+    let region = Region::copy(&world, [2, 10, 3], [2, 3, 1]);
+    assert_eq!(region.size(), [2, 3, 1]);
+    assert_eq!(region.blocks(), &[20, 44, 0, 44, 0, 0]);
+    // Cut short at the edge of the world
+    assert_eq!(Region::copy(&world, [10, 14, 15], [10, 10, 10]).size(), [6, 2, 1]);
+
+    let changed = region.paste(&mut world, [0, 0, 0]);
+    assert_eq!(changed, vec![(0, 0, 0, Block::Glass), (1, 0, 0, Block::Slab), (0, 1, 0, Block::Air),
+                             (1, 1, 0, Block::Slab), (0, 2, 0, Block::Air), (1, 2, 0, Block::Air)]);
+    // Pasted slabs stay as they were rather than stacking
+    assert_eq!(Region::copy(&world, [0, 0, 0], [2, 3, 1]), region);
+    // What doesn't fit is left out
+    assert_eq!(region.paste(&mut world, [15, 15, 15]), vec![(15, 15, 15, Block::Glass)]);
+
+    assert!(matches!(Region::new([2, 2, 2], vec![0; 7]), Err(FormatError::Size([2, 2, 2]))));
+    assert!(Region::new([1, 1, 1], vec![Block::MAX_ID + 1]).is_err());
+}
+
+#[test]
+fn schematic_round_trip() {
+    let region = every_block();
+    let bytes = RegionFormat::Schematic.write(&region).unwrap();
+    let (loaded, remapped) = RegionFormat::Schematic.read(&bytes).unwrap();
+    assert_eq!(loaded, region);
+    assert!(remapped.is_empty());
+
+    let blob = nbt::Blob::from_gzip_reader(&mut &bytes[..]).unwrap();
+    assert_eq!(blob.get("Materials"), Some(&nbt::Value::String("Alpha".to_string())));
+}
+
+#[test]
+fn schematic_blocks_are_remapped() {
+    let mut blob = nbt::Blob::named("Schematic");
+    for name in ["Width", "Height", "Length"] {
+        blob.insert(name, nbt::Value::Short(1)).unwrap();
+    }
+    blob.insert("Materials", nbt::Value::String("Alpha".to_string())).unwrap();
+    for (blocks, data, block, remapped) in [(35, 14, Block::RedCloth, None), (35, 12, Block::OrangeCloth, Some(35)),
+                                            (54, 0, Block::Planks, Some(54)), (2, 0, Block::GrassBlock, None)] {
+        blob.insert("Blocks", nbt::Value::ByteArray(vec![blocks])).unwrap();
+        blob.insert("Data", nbt::Value::ByteArray(vec![data])).unwrap();
+        let (region, report) = schematic::read(&gzipped(&blob)).unwrap();
+        assert_eq!(region.blocks(), &[u8::from(block)]);
+        assert_eq!(report.iter().map(|(id, ..)| id as i8).collect::<Vec<_>>(),
+                   remapped.into_iter().collect::<Vec<_>>());
+    }
+
+    // Classic ids are read as they are
+    blob.insert("Materials", nbt::Value::String("Classic".to_string())).unwrap();
+    blob.insert("Blocks", nbt::Value::ByteArray(vec![21])).unwrap();
+    assert_eq!(schematic::read(&gzipped(&blob)).unwrap().0.blocks(), &[21]);
+
+    blob.insert("Materials", nbt::Value::String("Pocket".to_string())).unwrap();
+    assert!(matches!(schematic::read(&gzipped(&blob)), Err(FormatError::Missing("Materials"))));
+}
+
+#[test]
+fn sponge_round_trip() {
+    let region = every_block();
+    let bytes = RegionFormat::Sponge.write(&region).unwrap();
+    let (loaded, remapped) = RegionFormat::Sponge.read(&bytes).unwrap();
+    assert_eq!(loaded, region);
+    assert!(remapped.is_empty());
+
+    let blob = nbt::Blob::from_gzip_reader(&mut &bytes[..]).unwrap();
+    assert_eq!(blob.get("Version"), Some(&nbt::Value::Int(sponge::VERSION)));
+    assert_eq!(blob.get("PaletteMax"), Some(&nbt::Value::Int(50)));
+    // Palette indices above 127 take two bytes
+    let air = Region::new([200, 1, 1], vec![0; 200]).unwrap();
+    let (loaded, _) = sponge::read(&sponge::write(&air).unwrap()).unwrap();
+    assert_eq!(loaded, air);
+}
+
+#[test]
+fn sponge_blocks_are_remapped() {
+    let mut palette = nbt::Map::new();
+    palette.insert("minecraft:air".to_string(), nbt::Value::Int(0));
+    palette.insert("minecraft:birch_planks".to_string(), nbt::Value::Int(1));
+    palette.insert("minecraft:blue_stained_glass".to_string(), nbt::Value::Int(2));
+    palette.insert("minecraft:stone".to_string(), nbt::Value::Int(3));
+    let mut blob = nbt::Blob::named("Schematic");
+    blob.insert("Version", nbt::Value::Int(2)).unwrap();
+    for name in ["Width", "Height", "Length"] {
+        blob.insert(name, nbt::Value::Short(2)).unwrap();
+    }
+    blob.insert("Palette", nbt::Value::Compound(palette)).unwrap();
+    blob.insert("BlockData", nbt::Value::ByteArray(vec![0, 1, 1, 1, 2, 0, 0, 0])).unwrap();
+
+    let (region, remapped) = sponge::read(&gzipped(&blob)).unwrap();
+    assert_eq!(region.blocks(), &[0, 5, 5, 5, 20, 0, 0, 0]);
+    assert_eq!(remapped.get_name("minecraft:birch_planks"), Some((Block::Planks, 3)));
+    assert_eq!(remapped.get_name("minecraft:blue_stained_glass"), Some((Block::Glass, 1)));
+    assert_eq!(remapped.get_name("minecraft:stone"), None);
+    assert_eq!(remapped.to_string(), "3 minecraft:birch_planks to Planks, 1 minecraft:blue_stained_glass to Glass");
+
+    blob.insert("BlockData", nbt::Value::ByteArray(vec![0, 1, 1, 9, 2, 0, 0, 0])).unwrap();
+    assert!(matches!(sponge::read(&gzipped(&blob)), Err(FormatError::Corrupt("BlockData"))));
+    blob.insert("BlockData", nbt::Value::ByteArray(vec![0, 1])).unwrap();
+    assert!(matches!(sponge::read(&gzipped(&blob)), Err(FormatError::Truncated)));
+    // The biggest size a schematic can claim, with nowhere near enough data for it
+    for name in ["Width", "Height", "Length"] {
+        blob.insert(name, nbt::Value::Short(-1)).unwrap();
+    }
+    assert!(matches!(sponge::read(&gzipped(&blob)), Err(FormatError::Truncated)));
+    blob.insert("Version", nbt::Value::Int(3)).unwrap();
+    assert!(matches!(sponge::read(&gzipped(&blob)), Err(FormatError::Version(3))));
+}
@@ -1,12 +1,11 @@
 //! # Heartbeat
 //! Used for sending Minecraft Heartbeats to [Mineonline](http://mineonline.codie.gg)
 
-use json::{parse, stringify, JsonValue};
+use json::{stringify, JsonValue};
 use json::number::Number;
 use json::object::Object;
-use reqwest::{Body, Url, StatusCode};
-use log::{debug};
-use serde::{Serialize, Deserialize};
+use reqwest::{Url, StatusCode};
+use serde::Deserialize;
 
 #[derive(Deserialize, Debug)]
 struct Response {
@@ -21,6 +20,7 @@ pub struct Heartbeat {
     port: u16,
     name: String,
     motd: String,
+    #[allow(dead_code)]
     public: String,
     max_users: u16,
     online: String,
@@ -34,6 +34,7 @@ pub struct Heartbeat {
 impl Heartbeat {
     /// Create a Heartbeat Object.
     ///
+    #[allow(clippy::too_many_arguments)]
     pub fn new(url: &str, ip: &str, port: u16, name: &str, motd: &str, public: bool, max_players: u16, online: bool,
                client_hash: &str, whitelisted: bool) -> Self {
         Self {
@@ -58,8 +59,23 @@ impl Heartbeat {
         self.users = user_count;
     }
 
+    /// Update the name of the server in the heartbeat.
+    pub fn update_name(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+
+    /// Update the message of the day in the heartbeat.
+    pub fn update_motd(&mut self, motd: &str) {
+        self.motd = motd.to_string();
+    }
+
+    /// Update the maximum number of users allowed on the server in the heartbeat.
+    pub fn update_max_users(&mut self, max_users: u16) {
+        self.max_users = max_users;
+    }
+
     /// Update the usernames of users currently connected to the server in the heartbeat.
-    pub fn update_player_names(&mut self, user_names: &Vec<String>) {
+    pub fn update_player_names(&mut self, user_names: &[String]) {
         self.players_list = user_names.to_vec();
     }
     /// Builds the request data from the heartbeat.
@@ -90,7 +106,7 @@ impl Heartbeat {
         // TODO: Support Owner Name
 
         let stringified = stringify(mineonline_json);
-        self.request = (&stringified).parse().unwrap();
+        self.request = stringified.parse().unwrap();
         stringified
 
     }
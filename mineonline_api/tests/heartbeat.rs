@@ -8,6 +8,7 @@ fn create_request() {
         "0.0.0.0",
         25565,
         "TestServer",
+        "A Test Server",
         true,
         20,
         true,
@@ -21,7 +22,8 @@ fn create_request() {
         \"players\":[],\
         \"max\":20,\
         \"name\":\"TestServer\",\
-        \"onlinemode\":\"true\",\
+        \"motd\":\"A Test Server\",\
+        \"onlinemode\":true,\
         \"md5\":\"0\",\
         \"whitelisted\":true}";
 
@@ -36,6 +38,7 @@ fn update_users() {
         "0.0.0.0",
         25565,
         "TestServer",
+        "A Test Server",
         true,
         20,
         true,
@@ -1,9 +1,7 @@
 //! # Heartbeat
 //! Used for sending Mojang Minecraft Heartbeats
 
-use rand::{thread_rng, Rng};
-use reqwest::{Body, Url, StatusCode};
-use std::time::Duration;
+use reqwest::{Url, StatusCode};
 
 /// Heartbeat Object
 pub struct Heartbeat {
@@ -13,6 +11,7 @@ pub struct Heartbeat {
     name: String,
     public: String,
     max_users: u16,
+    #[allow(dead_code)]
     online: String,
     protocol: u16,
     salt: String,
@@ -23,6 +22,7 @@ pub struct Heartbeat {
 impl Heartbeat {
     /// Create a Heartbeat Object.
     ///
+    #[allow(clippy::too_many_arguments)]
     pub fn new(url: &str, ip: &str, port: u16, name: &str, public: bool, max_players: u16, online: bool,
                salt: &str, protocol: u16) -> Self {
         Self {
@@ -43,17 +43,27 @@ impl Heartbeat {
     pub fn update_users(&mut self, user_count: u16) {
         self.users = user_count;
     }
+    /// Update the name of the server in the heartbeat.
+    pub fn update_name(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+
+    /// Update the maximum number of users allowed on the server in the heartbeat.
+    pub fn update_max_users(&mut self, max_users: u16) {
+        self.max_users = max_users;
+    }
     /// Builds the request data from the heartbeat.
     pub fn build_request(&mut self) -> Vec<(String, String)> {
-        let mut query: Vec<(String, String)> = vec![];
-        query.push(("ip".to_string(), self.ip.to_string()));
-        query.push(("port".to_string(), self.port.to_string()));
-        query.push(("users".to_string(), self.users.to_string()));
-        query.push(("max".to_string(), self.max_users.to_string()));
-        query.push(("name".to_string(), self.name.to_string()));
-        query.push(("public".to_string(), self.public.to_string()));
-        query.push(("version".to_string(), self.protocol.to_string()));
-        query.push(("salt".to_string(), self.salt.to_string()));
+        let query: Vec<(String, String)> = vec![
+            ("ip".to_string(), self.ip.to_string()),
+            ("port".to_string(), self.port.to_string()),
+            ("users".to_string(), self.users.to_string()),
+            ("max".to_string(), self.max_users.to_string()),
+            ("name".to_string(), self.name.to_string()),
+            ("public".to_string(), self.public.to_string()),
+            ("version".to_string(), self.protocol.to_string()),
+            ("salt".to_string(), self.salt.to_string()),
+        ];
         self.request = query.clone();
         query
     }